@@ -0,0 +1,916 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::function_call::api::{ChatTransport, RetryNotice};
+use crate::function_call::context::{self, ContextBudget, ContextStrategy};
+use crate::function_call::errors::{AgentError, OpenAiCallError, ToolCallError};
+use crate::function_call::registry::ToolRegistry;
+use crate::function_call::tools::run_tool_calls;
+use crate::function_call::types::{
+    ChatMessage, ChatRequest, ChatResponse, ResponseFormat, ToolCall, Usage,
+};
+use crate::function_call::usage::UsageTracker;
+use crate::function_call::validate::{format_errors, validate};
+
+/// エージェントの設定
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    pub model: String,
+    pub system_prompt: String,
+    pub temperature: f64,
+    pub max_iter: usize, // 1 回の run で API を呼ぶ最大回数（無限ループ安全弁）
+    pub max_parallel_tools: usize, // 1 ターン内で同時に実行するツール数
+    /// 端末で確認を取るなど、並行実行してはいけないツール
+    /// （1 件でも含まれるターンは 1 件ずつ実行する）
+    pub exclusive_tools: Vec<&'static str>,
+    /// ストリーミングで受信し、本文の差分を `AgentEvent::ContentDelta` で通知する
+    pub stream: bool,
+    /// 履歴のトークン予算（None なら履歴を減らさない）
+    pub context: Option<ContextBudget>,
+    /// 最終回答の形式（JSON Schema を指定すると回答をそのスキーマで検証する）
+    pub response_format: Option<ResponseFormat>,
+    /// ツールの引数や最終回答がスキーマに合わないとき、モデルに直させる最大回数
+    pub max_corrections: usize,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            model: "gpt-4o-mini".to_string(),
+            system_prompt: String::new(),
+            temperature: 0.0,
+            max_iter: 5,
+            max_parallel_tools: 4,
+            exclusive_tools: Vec::new(),
+            stream: false,
+            context: None,
+            response_format: None,
+            max_corrections: 2,
+        }
+    }
+}
+
+/// ループ中に発生するイベント（表示やログ用）
+#[derive(Debug)]
+pub enum AgentEvent<'a> {
+    /// モデルを呼び出す直前
+    Request { iteration: usize },
+    /// ストリーミング中に届いた本文の差分
+    ContentDelta { text: &'a str },
+    /// ツールを実行する直前
+    ToolStarted { name: &'a str, arguments: &'a str },
+    /// ツールの実行結果
+    ToolFinished {
+        name: &'a str,
+        result: &'a Result<String, String>,
+    },
+    /// 最終回答
+    Finished { text: &'a str },
+    /// 最大反復に達して打ち切った
+    MaxIterations { max_iter: usize },
+    /// 予算を超えたので古い発言を取り除いた（summarized: 要約を残したか）
+    ContextTrimmed { removed: usize, summarized: bool },
+    /// 引数や最終回答がスキーマに合わないので、モデルに直させる
+    Correction { attempt: usize, reason: &'a str },
+    /// 直させる回数の上限に達して打ち切った
+    CorrectionsExhausted {
+        max_corrections: usize,
+        reason: &'a str,
+    },
+    /// API 呼び出しが失敗したので、`wait` 後に再送する（attempt は 1 始まり）
+    Retrying {
+        attempt: u32,
+        max_retries: u32,
+        wait: Duration,
+        error: &'a OpenAiCallError,
+    },
+}
+
+/// ツール呼び出し 1 回分の記録
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolTrace {
+    pub iteration: usize,
+    pub tool_call_id: String,
+    pub name: String,
+    pub arguments: String,
+    pub result: Result<String, String>,
+}
+
+/// run の終わり方
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Completed,
+    MaxIterations,
+    /// `max_corrections` 回直させてもスキーマに合わなかった
+    InvalidOutput,
+}
+
+/// run の結果
+#[derive(Debug, Clone)]
+pub struct AgentRun {
+    pub final_text: Option<String>, // Completed 以外は None
+    pub tool_trace: Vec<ToolTrace>,
+    pub usage: Usage, // 全反復の合計
+    pub iterations: usize,
+    pub stop: StopReason,
+}
+
+type EventHandler = Arc<dyn Fn(&AgentEvent) + Send + Sync>;
+
+/// 「モデル呼び出し → tool_calls 実行 → 結果を履歴に追加」を繰り返すエージェント
+pub struct Agent {
+    config: AgentConfig,
+    registry: ToolRegistry,
+    transport: Box<dyn ChatTransport>,
+    on_event: Option<EventHandler>,
+    usage_tracker: Option<Arc<UsageTracker>>,
+}
+
+impl Agent {
+    pub fn new(
+        config: AgentConfig,
+        registry: ToolRegistry,
+        transport: Box<dyn ChatTransport>,
+    ) -> Self {
+        Self {
+            config,
+            registry,
+            transport,
+            on_event: None,
+            usage_tracker: None,
+        }
+    }
+
+    /// イベントを受け取るハンドラを設定
+    ///
+    /// 送信先がリトライするときの通知も `AgentEvent::Retrying` としてここに届く。
+    pub fn on_event(mut self, handler: impl Fn(&AgentEvent) + Send + Sync + 'static) -> Self {
+        let handler: EventHandler = Arc::new(handler);
+        let on_retry = Arc::clone(&handler);
+        self.transport
+            .set_on_retry(Arc::new(move |notice: &RetryNotice| {
+                on_retry(&AgentEvent::Retrying {
+                    attempt: notice.attempt,
+                    max_retries: notice.max_retries,
+                    wait: notice.wait,
+                    error: notice.error,
+                })
+            }));
+        self.on_event = Some(handler);
+        self
+    }
+
+    /// API 呼び出しごとの使用量を記録する（アプリ単位で共有する想定）
+    pub fn with_usage_tracker(mut self, tracker: Arc<UsageTracker>) -> Self {
+        self.usage_tracker = Some(tracker);
+        self
+    }
+
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
+
+    /// 会話開始時の履歴（system メッセージのみ）
+    pub fn initial_messages(&self) -> Vec<ChatMessage> {
+        vec![ChatMessage::system(&self.config.system_prompt)]
+    }
+
+    /// 1 回だけの質問（履歴を持たない）
+    pub async fn ask(&self, user_text: &str) -> Result<AgentRun, AgentError> {
+        let mut messages = self.initial_messages();
+        messages.push(ChatMessage::user(user_text));
+        self.run(&mut messages).await
+    }
+
+    /// 履歴の続きを処理する（末尾はユーザー発言の想定）
+    ///
+    /// assistant / tool メッセージと最終回答を `messages` に追加する。
+    /// エラー時は途中まで追加された状態で返るので、必要なら呼び出し側で巻き戻す。
+    pub async fn run(&self, messages: &mut Vec<ChatMessage>) -> Result<AgentRun, AgentError> {
+        let mut tool_trace = Vec::new();
+        let mut usage = Usage::default();
+        let mut corrections = 0;
+
+        for iteration in 0..self.config.max_iter {
+            self.fit_context(messages).await;
+            self.emit(&AgentEvent::Request { iteration });
+
+            let request = self.request(messages);
+            let raw = if self.config.stream {
+                let on_delta = |text: &str| self.emit(&AgentEvent::ContentDelta { text });
+                self.transport.send_stream(&request, &on_delta).await?
+            } else {
+                self.transport.send(&request).await?
+            };
+            let parsed: ChatResponse = serde_json::from_str(&raw)
+                .map_err(|e| OpenAiCallError::JsonParse(format!("{e}; raw: {raw}")))?;
+            if let Some(u) = parsed.usage {
+                usage += u;
+                if let Some(tracker) = &self.usage_tracker {
+                    // 実際に応答したモデル名（日付付き）があればそちらで記録する
+                    tracker.record(parsed.model.as_deref().unwrap_or(&self.config.model), u);
+                }
+            }
+
+            let choice = parsed.choices.into_iter().next();
+            let msg = choice.ok_or(AgentError::EmptyChoices)?.message;
+
+            // tool_calls がない = 最終回答（次の発言の文脈として履歴に残す）
+            if msg.tool_calls.is_empty() {
+                let text = msg.content.unwrap_or_default();
+                if let Err(reason) = self.check_answer(&text) {
+                    messages.push(ChatMessage::assistant(text));
+                    if corrections == self.config.max_corrections {
+                        self.emit(&AgentEvent::CorrectionsExhausted {
+                            max_corrections: self.config.max_corrections,
+                            reason: &reason,
+                        });
+                        return Ok(AgentRun {
+                            final_text: None,
+                            tool_trace,
+                            usage,
+                            iterations: iteration + 1,
+                            stop: StopReason::InvalidOutput,
+                        });
+                    }
+                    corrections += 1;
+                    self.emit(&AgentEvent::Correction {
+                        attempt: corrections,
+                        reason: &reason,
+                    });
+                    messages.push(ChatMessage::user(format!(
+                        "回答が指定の JSON Schema に合いません: {reason}。スキーマに合う JSON だけを返してください。"
+                    )));
+                    continue;
+                }
+                self.emit(&AgentEvent::Finished { text: &text });
+                messages.push(ChatMessage::assistant(text.clone()));
+                return Ok(AgentRun {
+                    final_text: Some(text),
+                    tool_trace,
+                    usage,
+                    iterations: iteration + 1,
+                    stop: StopReason::Completed,
+                });
+            }
+
+            let results = self.execute(&msg.tool_calls).await;
+            messages.push(ChatMessage::assistant_tool_calls(
+                msg.content,
+                msg.tool_calls.clone(),
+            ));
+
+            // 引数の誤りは違反箇所を返して直させる（回数に上限あり）
+            let invalid: Vec<String> = results
+                .iter()
+                .filter_map(|r| r.as_ref().err())
+                .filter(|e| e.is_argument_error())
+                .map(ToolCallError::to_string)
+                .collect();
+            let exhausted = !invalid.is_empty() && corrections == self.config.max_corrections;
+
+            // 結果を tool メッセージとして呼び出し順に追加
+            for (tc, result) in msg.tool_calls.into_iter().zip(results) {
+                let content = match &result {
+                    Ok(s) => s.clone(),
+                    Err(e) if e.is_argument_error() && !exhausted => {
+                        format!("❌ エラー: {}（引数を修正して呼び出し直してください）", e)
+                    }
+                    Err(e) => format!("❌ エラー: {}", e),
+                };
+                messages.push(ChatMessage::tool(&tc.id, content));
+                tool_trace.push(ToolTrace {
+                    iteration,
+                    tool_call_id: tc.id,
+                    name: tc.function.name,
+                    arguments: tc.function.arguments,
+                    result: result.map_err(|e| e.to_string()),
+                });
+            }
+
+            if exhausted {
+                self.emit(&AgentEvent::CorrectionsExhausted {
+                    max_corrections: self.config.max_corrections,
+                    reason: &invalid.join("; "),
+                });
+                return Ok(AgentRun {
+                    final_text: None,
+                    tool_trace,
+                    usage,
+                    iterations: iteration + 1,
+                    stop: StopReason::InvalidOutput,
+                });
+            }
+            if !invalid.is_empty() {
+                corrections += 1;
+                self.emit(&AgentEvent::Correction {
+                    attempt: corrections,
+                    reason: &invalid.join("; "),
+                });
+            }
+        }
+
+        self.emit(&AgentEvent::MaxIterations {
+            max_iter: self.config.max_iter,
+        });
+        Ok(AgentRun {
+            final_text: None,
+            tool_trace,
+            usage,
+            iterations: self.config.max_iter,
+            stop: StopReason::MaxIterations,
+        })
+    }
+
+    /// 履歴が予算を超えていたら古い発言を要約するか捨てる
+    ///
+    /// 要約に失敗したときは会話を止めず、捨てるだけにする。
+    async fn fit_context(&self, messages: &mut Vec<ChatMessage>) {
+        let Some(budget) = &self.config.context else {
+            return;
+        };
+        let Some(range) = context::removable_range(messages, budget) else {
+            return;
+        };
+
+        let summary = match budget.strategy {
+            ContextStrategy::Drop => None,
+            ContextStrategy::Summarize => self.summarize(&messages[range.clone()]).await.ok(),
+        };
+        let removed = range.len();
+        context::apply(messages, range, summary.as_deref());
+        self.emit(&AgentEvent::ContextTrimmed {
+            removed,
+            summarized: summary.is_some(),
+        });
+    }
+
+    /// 古い発言の要約をモデルに頼む（ツールは渡さない）
+    async fn summarize(&self, old: &[ChatMessage]) -> Result<String, AgentError> {
+        let prompt = context::summary_prompt(old);
+        let request = ChatRequest::new(&self.config.model, &prompt)
+            .temperature(0.0)
+            .max_tokens(400);
+        let raw = self.transport.send(&request).await?;
+        let parsed: ChatResponse = serde_json::from_str(&raw)
+            .map_err(|e| OpenAiCallError::JsonParse(format!("{e}; raw: {raw}")))?;
+        if let (Some(u), Some(tracker)) = (parsed.usage, &self.usage_tracker) {
+            tracker.record(parsed.model.as_deref().unwrap_or(&self.config.model), u);
+        }
+        parsed
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.message.content)
+            .filter(|text| !text.trim().is_empty())
+            .ok_or(AgentError::EmptyChoices)
+    }
+
+    /// 最終回答が `response_format` に合っているか（合わなければ理由）
+    fn check_answer(&self, text: &str) -> Result<(), String> {
+        let schema = match &self.config.response_format {
+            Some(ResponseFormat::JsonSchema { json_schema }) => &json_schema.schema,
+            Some(ResponseFormat::JsonObject) => &serde_json::json!({"type": "object"}),
+            Some(ResponseFormat::Text) | None => return Ok(()),
+        };
+        let value: serde_json::Value =
+            serde_json::from_str(text).map_err(|e| format!("JSON として読めません: {e}"))?;
+        validate(&value, schema).map_err(|errors| format_errors(&errors))
+    }
+
+    /// 現在の履歴から送るリクエスト
+    fn request<'a>(&'a self, messages: &'a [ChatMessage]) -> ChatRequest<'a> {
+        let mut request =
+            ChatRequest::new(&self.config.model, messages).temperature(self.config.temperature);
+        if let Some(format) = &self.config.response_format {
+            request = request.response_format(format.clone());
+        }
+        // parallel_tool_calls は tools があるときしか指定できない
+        let tools = self.registry.definitions();
+        if tools.is_empty() {
+            return request;
+        }
+        request.tools(tools).parallel_tool_calls(true)
+    }
+
+    /// 1 ターン分の tool_calls を実行（結果は呼び出し順）
+    async fn execute(&self, tool_calls: &[ToolCall]) -> Vec<Result<String, ToolCallError>> {
+        let exclusive = tool_calls.iter().any(|tc| {
+            self.config
+                .exclusive_tools
+                .contains(&tc.function.name.as_str())
+        });
+        let limit = if exclusive {
+            1
+        } else {
+            self.config.max_parallel_tools
+        };
+
+        run_tool_calls(tool_calls, limit, |tc| async move {
+            self.emit(&AgentEvent::ToolStarted {
+                name: &tc.function.name,
+                arguments: &tc.function.arguments,
+            });
+            let result = self
+                .registry
+                .call(&tc.function.name, &tc.function.arguments)
+                .await;
+            self.emit(&AgentEvent::ToolFinished {
+                name: &tc.function.name,
+                result: &result
+                    .as_ref()
+                    .map(String::clone)
+                    .map_err(|e| e.to_string()),
+            });
+            result
+        })
+        .await
+    }
+
+    fn emit(&self, event: &AgentEvent) {
+        if let Some(handler) = &self.on_event {
+            handler(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use serde::Deserialize;
+    use serde_json::{Value, json};
+
+    use crate::function_call::api::{OnDelta, OnRetry};
+    use crate::function_call::schema::tool_args;
+    use crate::function_call::stream::assemble_sse;
+    use crate::function_call::usage::PriceTable;
+
+    /// 用意したレスポンスを順に返し、送られたリクエストを記録する
+    struct ScriptedTransport {
+        responses: Mutex<VecDeque<String>>,
+        requests: Arc<Mutex<Vec<Value>>>,
+    }
+
+    #[async_trait]
+    impl ChatTransport for ScriptedTransport {
+        async fn send(&self, request: &ChatRequest<'_>) -> Result<String, OpenAiCallError> {
+            let body = serde_json::to_value(request).unwrap();
+            self.requests.lock().unwrap().push(body);
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| OpenAiCallError::Http("レスポンスがもうありません".to_string()))
+        }
+    }
+
+    tool_args! {
+        #[derive(Debug, Deserialize)]
+        struct AddArgs {
+            a: f64,
+            b: f64,
+        }
+    }
+
+    tool_args! {
+        #[derive(Debug, Deserialize)]
+        struct AnswerArgs {
+            answer: f64,
+        }
+    }
+
+    fn tool_response(calls: &[(&str, &str, &str)], total_tokens: u64) -> String {
+        let tool_calls: Vec<Value> = calls
+            .iter()
+            .map(|(id, name, args)| {
+                json!({"id": id, "type": "function",
+                       "function": {"name": name, "arguments": args}})
+            })
+            .collect();
+        json!({
+            "choices": [{"message": {"role": "assistant", "content": null, "tool_calls": tool_calls}}],
+            "usage": {"prompt_tokens": total_tokens - 1, "completion_tokens": 1, "total_tokens": total_tokens}
+        })
+        .to_string()
+    }
+
+    fn text_response(text: &str) -> String {
+        json!({
+            "choices": [{"message": {"role": "assistant", "content": text}}],
+            "usage": {"prompt_tokens": 20, "completion_tokens": 5, "total_tokens": 25}
+        })
+        .to_string()
+    }
+
+    fn agent(responses: Vec<String>) -> (Agent, Arc<Mutex<Vec<Value>>>) {
+        let mut registry = ToolRegistry::new();
+        registry.register("add", "足し算", |args: AddArgs| async move {
+            Ok((args.a + args.b).to_string())
+        });
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let transport = ScriptedTransport {
+            responses: Mutex::new(responses.into()),
+            requests: Arc::clone(&requests),
+        };
+        let config = AgentConfig {
+            system_prompt: "計算係".to_string(),
+            max_iter: 3,
+            ..Default::default()
+        };
+        (Agent::new(config, registry, Box::new(transport)), requests)
+    }
+
+    #[tokio::test]
+    async fn test_run_executes_tools_then_finishes() {
+        let (agent, requests) = agent(vec![
+            tool_response(
+                &[("c1", "add", r#"{"a": 1, "b": 2}"#), ("c2", "nope", "{}")],
+                10,
+            ),
+            text_response("答えは 3 です"),
+        ]);
+
+        let run = agent.ask("1+2 は？").await.unwrap();
+
+        assert_eq!(run.stop, StopReason::Completed);
+        assert_eq!(run.final_text.as_deref(), Some("答えは 3 です"));
+        assert_eq!(run.iterations, 2);
+        assert_eq!(run.usage.total_tokens, 35);
+        assert_eq!(run.tool_trace.len(), 2);
+        assert_eq!(run.tool_trace[0].result, Ok("3".to_string()));
+        assert!(run.tool_trace[1].result.is_err());
+
+        // 2 回目のリクエストには assistant(tool_calls) と tool の結果が順に入る
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let messages = requests[1]["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["content"], "計算係");
+        assert_eq!(messages[2]["tool_calls"][0]["id"], "c1");
+        assert_eq!(messages[3]["tool_call_id"], "c1");
+        assert_eq!(messages[3]["content"], "3");
+        assert_eq!(messages[4]["tool_call_id"], "c2");
+        assert!(messages[4]["content"].as_str().unwrap().starts_with("❌"));
+        assert_eq!(requests[0]["model"], "gpt-4o-mini");
+        assert_eq!(requests[0]["tools"][0]["function"]["name"], "add");
+    }
+
+    #[tokio::test]
+    async fn test_run_appends_to_history() {
+        let (agent, _) = agent(vec![text_response("こんにちは")]);
+        let mut messages = agent.initial_messages();
+        messages.push(ChatMessage::user("やあ"));
+
+        agent.run(&mut messages).await.unwrap();
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2], ChatMessage::assistant("こんにちは"));
+    }
+
+    #[tokio::test]
+    async fn test_usage_tracker_records_each_completion() {
+        let (agent, _) = agent(vec![
+            tool_response(&[("c1", "add", r#"{"a": 1, "b": 2}"#)], 10),
+            text_response("3"),
+        ]);
+        let tracker = Arc::new(UsageTracker::with_settings(
+            "test",
+            PriceTable::default(),
+            None,
+        ));
+        let agent = agent.with_usage_tracker(Arc::clone(&tracker));
+
+        agent.ask("1+2").await.unwrap();
+
+        let totals = tracker.totals();
+        assert_eq!(totals.requests, 2);
+        assert_eq!(totals.usage.total_tokens, 35);
+        assert_eq!(totals.unpriced_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_at_max_iter() {
+        let call = tool_response(&[("c", "add", r#"{"a": 1, "b": 1}"#)], 10);
+        let (agent, requests) = agent(vec![call.clone(), call.clone(), call]);
+
+        let run = agent.ask("ずっと足して").await.unwrap();
+
+        assert_eq!(run.stop, StopReason::MaxIterations);
+        assert_eq!(run.final_text, None);
+        assert_eq!(run.tool_trace.len(), 3);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_run_errors() {
+        let (a, _) = agent(vec![]);
+        assert!(matches!(
+            a.ask("?").await.unwrap_err(),
+            AgentError::Api(OpenAiCallError::Http(_))
+        ));
+
+        let (a, _) = agent(vec![json!({"choices": []}).to_string()]);
+        assert!(matches!(
+            a.ask("?").await.unwrap_err(),
+            AgentError::EmptyChoices
+        ));
+
+        let (a, _) = agent(vec!["not json".to_string()]);
+        assert!(matches!(
+            a.ask("?").await.unwrap_err(),
+            AgentError::Api(OpenAiCallError::JsonParse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments_are_returned_for_correction() {
+        let (agent, requests) = agent(vec![
+            tool_response(&[("c1", "add", r#"{"a": 1, "c": 2}"#)], 10),
+            tool_response(&[("c2", "add", r#"{"a": 1, "b": 2}"#)], 10),
+            text_response("3"),
+        ]);
+
+        let run = agent.ask("1+2").await.unwrap();
+
+        assert_eq!(run.stop, StopReason::Completed);
+        assert_eq!(run.final_text.as_deref(), Some("3"));
+        assert_eq!(run.tool_trace[1].result, Ok("3".to_string()));
+        // 違反箇所と直し方がモデルに返る
+        let requests = requests.lock().unwrap();
+        let feedback = requests[1]["messages"][3]["content"].as_str().unwrap();
+        assert!(
+            feedback.contains("$.b: 必須の項目がありません"),
+            "{feedback}"
+        );
+        assert!(feedback.contains("呼び出し直してください"), "{feedback}");
+    }
+
+    #[tokio::test]
+    async fn test_stops_after_max_corrections() {
+        let bad = tool_response(&[("c", "add", r#"{"a": "1", "b": 2}"#)], 10);
+        let (agent, requests) = agent(vec![bad.clone(), bad.clone(), bad]);
+        let mut agent = agent;
+        agent.config.max_corrections = 1;
+
+        let run = agent.ask("1+2").await.unwrap();
+
+        assert_eq!(run.stop, StopReason::InvalidOutput);
+        assert_eq!(run.final_text, None);
+        assert_eq!(run.tool_trace.len(), 2);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_final_answer_is_validated_against_response_format() {
+        let (agent, requests) = agent(vec![
+            text_response("答えは 3 です"),
+            text_response(r#"{"answer": "3"}"#),
+            text_response(r#"{"answer": 3}"#),
+        ]);
+        let mut agent = agent;
+        agent.config.response_format = Some(ResponseFormat::json_schema::<AnswerArgs>("answer"));
+
+        let run = agent.ask("1+2").await.unwrap();
+
+        assert_eq!(run.stop, StopReason::Completed);
+        let answer: AnswerArgs = serde_json::from_str(&run.final_text.unwrap()).unwrap();
+        assert_eq!(answer.answer, 3.0);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0]["response_format"]["type"], "json_schema");
+        // 読めない JSON と型違いは、それぞれ理由を添えて直させる
+        let sent = requests[2]["messages"].as_array().unwrap();
+        assert!(
+            sent[3]["content"]
+                .as_str()
+                .unwrap()
+                .contains("JSON として読めません")
+        );
+        assert!(
+            sent[5]["content"]
+                .as_str()
+                .unwrap()
+                .contains("$.answer: number が必要です（実際: string）")
+        );
+    }
+
+    fn long_history() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system("計算係"),
+            ChatMessage::user("1+2 は？"),
+            ChatMessage::assistant("3 です"),
+            ChatMessage::user("では 3+4 は？"),
+        ]
+    }
+
+    fn with_budget(agent: Agent, strategy: ContextStrategy) -> Agent {
+        let mut agent = agent;
+        agent.config.context = Some(ContextBudget {
+            max_tokens: 10,
+            keep_recent_turns: 1,
+            strategy,
+        });
+        agent
+    }
+
+    #[tokio::test]
+    async fn test_old_turns_are_summarized_over_budget() {
+        let (agent, requests) = agent(vec![
+            text_response("1+2=3 を計算済み"),
+            text_response("7 です"),
+        ]);
+        let agent = with_budget(agent, ContextStrategy::Summarize);
+        let mut messages = long_history();
+
+        let run = agent.run(&mut messages).await.unwrap();
+
+        assert_eq!(run.final_text.as_deref(), Some("7 です"));
+        // 1 回目は要約の依頼（ツールなし）、2 回目は要約入りの履歴
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].get("tools").is_none());
+        assert!(
+            requests[0]["messages"][1]["content"]
+                .as_str()
+                .unwrap()
+                .contains("ユーザー: 1+2 は？")
+        );
+        let sent = requests[1]["messages"].as_array().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[1]["content"], "これまでの会話の要約: 1+2=3 を計算済み");
+        assert_eq!(sent[2]["content"], "では 3+4 は？");
+        assert_eq!(
+            messages[1],
+            ChatMessage::system("これまでの会話の要約: 1+2=3 を計算済み")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_summary_failure_falls_back_to_drop() {
+        let empty = json!({"choices": []}).to_string();
+        let (agent, requests) = agent(vec![empty, text_response("7 です")]);
+        let agent = with_budget(agent, ContextStrategy::Summarize);
+        let trimmed = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&trimmed);
+        let agent = agent.on_event(move |event| {
+            if let AgentEvent::ContextTrimmed {
+                removed,
+                summarized,
+            } = event
+            {
+                sink.lock().unwrap().push((*removed, *summarized));
+            }
+        });
+        let mut messages = long_history();
+
+        agent.run(&mut messages).await.unwrap();
+
+        assert_eq!(*trimmed.lock().unwrap(), vec![(2, false)]);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[1]["messages"].as_array().unwrap().len(), 2);
+    }
+
+    /// 1 回失敗してから成功する送信先（リトライの通知だけ出す）
+    struct FlakyTransport {
+        on_retry: Option<Arc<OnRetry<'static>>>,
+    }
+
+    #[async_trait]
+    impl ChatTransport for FlakyTransport {
+        async fn send(&self, _: &ChatRequest<'_>) -> Result<String, OpenAiCallError> {
+            let error = OpenAiCallError::Server("status=503".to_string());
+            if let Some(on_retry) = &self.on_retry {
+                on_retry(&RetryNotice {
+                    attempt: 1,
+                    max_retries: 3,
+                    wait: Duration::from_millis(500),
+                    error: &error,
+                });
+            }
+            Ok(text_response("ok"))
+        }
+
+        fn set_on_retry(&mut self, on_retry: Arc<OnRetry<'static>>) {
+            self.on_retry = Some(on_retry);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transport_retries_are_reported_as_events() {
+        let transport = FlakyTransport { on_retry: None };
+        let retries = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&retries);
+        let agent = Agent::new(
+            AgentConfig::default(),
+            ToolRegistry::new(),
+            Box::new(transport),
+        )
+        .on_event(move |event| {
+            if let AgentEvent::Retrying {
+                attempt,
+                max_retries,
+                wait,
+                error,
+            } = event
+            {
+                sink.lock()
+                    .unwrap()
+                    .push((*attempt, *max_retries, *wait, error.to_string()));
+            }
+        });
+
+        agent.ask("こんにちは").await.unwrap();
+
+        let retries = retries.lock().unwrap();
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].0, 1);
+        assert_eq!(retries[0].1, 3);
+        assert_eq!(retries[0].2, Duration::from_millis(500));
+        assert!(retries[0].3.contains("503"));
+    }
+
+    #[tokio::test]
+    async fn test_events_are_emitted_in_order() {
+        let (agent, _) = agent(vec![
+            tool_response(&[("c1", "add", r#"{"a": 1, "b": 2}"#)], 10),
+            text_response("3"),
+        ]);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let agent = agent.on_event(move |event| {
+            let label = match event {
+                AgentEvent::Request { iteration } => format!("request {iteration}"),
+                AgentEvent::ToolStarted { name, .. } => format!("start {name}"),
+                AgentEvent::ToolFinished { name, result } => {
+                    format!("finish {name} {}", result.is_ok())
+                }
+                AgentEvent::Finished { text } => format!("done {text}"),
+                AgentEvent::MaxIterations { .. } => "max".to_string(),
+                AgentEvent::ContentDelta { text } => format!("delta {text}"),
+                AgentEvent::ContextTrimmed { removed, .. } => format!("trim {removed}"),
+                AgentEvent::Correction { attempt, .. } => format!("correct {attempt}"),
+                AgentEvent::CorrectionsExhausted { .. } => "exhausted".to_string(),
+                AgentEvent::Retrying { attempt, .. } => format!("retry {attempt}"),
+            };
+            sink.lock().unwrap().push(label);
+        });
+
+        agent.ask("1+2").await.unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "request 0",
+                "start add",
+                "finish add true",
+                "request 1",
+                "done 3"
+            ]
+        );
+    }
+
+    /// 記録した SSE を差分ごとに流す
+    struct SseTransport;
+
+    #[async_trait]
+    impl ChatTransport for SseTransport {
+        async fn send(&self, _: &ChatRequest<'_>) -> Result<String, OpenAiCallError> {
+            unreachable!("stream=true のときは send_stream を使う")
+        }
+
+        async fn send_stream(
+            &self,
+            _: &ChatRequest<'_>,
+            on_delta: &OnDelta<'_>,
+        ) -> Result<String, OpenAiCallError> {
+            let sse = include_str!("testdata/stream_text.sse");
+            Ok(assemble_sse(sse.as_bytes(), on_delta)?.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_emits_content_deltas() {
+        let config = AgentConfig {
+            stream: true,
+            ..Default::default()
+        };
+        let deltas = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&deltas);
+        let agent = Agent::new(config, ToolRegistry::new(), Box::new(SseTransport)).on_event(
+            move |event| {
+                if let AgentEvent::ContentDelta { text } = event {
+                    sink.lock().unwrap().push(text.to_string());
+                }
+            },
+        );
+
+        let run = agent.ask("東京の天気は？").await.unwrap();
+
+        assert_eq!(run.final_text.as_deref(), Some("東京は晴れです。"));
+        assert_eq!(run.usage.total_tokens, 18);
+        assert_eq!(*deltas.lock().unwrap(), vec!["東京は", "晴れ", "です。"]);
+    }
+}
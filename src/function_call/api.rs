@@ -1,84 +1,792 @@
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use serde_json::json;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::StatusCode;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
 
 use crate::function_call::errors::OpenAiCallError;
-use crate::function_call::types::{OutgoingMessage, ToolDefinition};
-use serde_json::Value;
-
-pub fn build_request_json<'a>(
-    model: &str,
-    messages: &[OutgoingMessage<'a>],
-    tools: &[ToolDefinition<'a>],
-) -> serde_json::Value {
-    json!({
-        "model": model,
-        "messages": messages,
-        "tools": tools
-    })
-}
-
-// 1 回目返信の tool_call を受けローカルで実行した結果を tool ロールとして差し込み、
-// その続きの最終回答を得るための payload を構築する。
-// messages_original: 最初に送った system+user (今後履歴伸ばすなら Vec<Value> で保持しても良い)
-pub fn build_second_payload<'a>(
-    model: &str,
-    original_messages: &[OutgoingMessage<'a>],
-    tool_call_id: &str,
-    tool_name: &str,
-    raw_arguments: &str,
-    result_number: f64,
-) -> Value {
-    // assistant (tool_call 宣言) と tool (結果) を追加する簡易形。
-    // 将来: 最初のレスポンス JSON からそのまま assistant メッセージを再利用する形に変えても良い。
-    let mut msgs: Vec<Value> = Vec::new();
-    for m in original_messages {
-        msgs.push(json!({"role": m.role, "content": m.content}));
-    }
-    // assistant: tool_calls 宣言 (今回は 1 件想定)
-    msgs.push(json!({
-        "role": "assistant",
-        "tool_calls": [{
-            "id": tool_call_id,
-            "type": "function",
-            "function": {"name": tool_name, "arguments": raw_arguments}
-        }]
-    }));
-    // tool: 実行結果
-    msgs.push(json!({
-        "role": "tool",
-        "tool_call_id": tool_call_id,
-        "content": result_number.to_string()
-    }));
-
-    json!({
-        "model": model,
-        "messages": msgs
-    })
+use crate::function_call::stream::{SseParser, StreamAccumulator};
+use crate::function_call::types::ChatRequest;
+
+/// OpenAI 本家の API（他の OpenAI 互換サーバーは base_url を変えて使う）
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// 接続先の設定（OpenAI または llama.cpp server / Ollama / vLLM などの互換サーバー）
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointConfig {
+    /// `/chat/completions` の手前まで（例: `http://localhost:11434/v1`）
+    pub base_url: String,
+    pub model: String,
+    /// 不要なサーバーでは None（Authorization ヘッダーを送らない）
+    pub api_key: Option<String>,
+}
+
+impl EndpointConfig {
+    /// 環境変数から読む
+    ///
+    /// アプリごとの `{prefix}_BASE_URL` / `{prefix}_MODEL` / `{prefix}_API_KEY` を優先し、
+    /// なければ共通の `OPENAI_BASE_URL` / `OPENAI_MODEL` / `OPENAI_API_KEY` を使う。
+    pub fn from_env(prefix: &str, default_model: &str) -> Self {
+        Self::from_lookup(prefix, default_model, |key| env::var(key).ok())
+    }
+
+    fn from_lookup(
+        prefix: &str,
+        default_model: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let get = |name: &str| {
+            [format!("{prefix}_{name}"), format!("OPENAI_{name}")]
+                .iter()
+                .filter_map(|key| lookup(key))
+                .map(|v| v.trim().to_string())
+                .find(|v| !v.is_empty())
+        };
+        Self {
+            base_url: get("BASE_URL").unwrap_or_else(|| OPENAI_BASE_URL.to_string()),
+            model: get("MODEL").unwrap_or_else(|| default_model.to_string()),
+            api_key: get("API_KEY"),
+        }
+    }
+
+    /// CLI で指定された値で上書きする（None の項目はそのまま）
+    pub fn with_overrides(
+        mut self,
+        base_url: Option<&str>,
+        model: Option<&str>,
+        api_key: Option<&str>,
+    ) -> Self {
+        if let Some(base_url) = base_url {
+            self.base_url = base_url.to_string();
+        }
+        if let Some(model) = model {
+            self.model = model.to_string();
+        }
+        if let Some(api_key) = api_key {
+            self.api_key = Some(api_key.to_string());
+        }
+        self
+    }
+
+    /// OpenAI 本家に API キーなしで送ろうとしていないか
+    pub fn validate(&self) -> Result<(), String> {
+        if self.api_key.is_none() && self.base_url.trim_end_matches('/') == OPENAI_BASE_URL {
+            return Err(
+                "OPENAI_API_KEY が未設定です（ローカルのサーバーを使う場合は OPENAI_BASE_URL を指定してください）"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn chat_completions_url(&self) -> String {
+        format!("{}/chat/completions", self.base_url.trim_end_matches('/'))
+    }
+}
+
+/// API 呼び出しのタイムアウトとリトライの方針
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 1 回の待ち時間の上限
+    ///
+    /// 応答ヘッダーまで、非ストリームなら本文を読み終えるまで、
+    /// ストリーミングなら次の差分が届くまでの間隔に、それぞれ掛かる。
+    pub timeout: Duration,
+    /// 初回を除くリトライ回数
+    pub max_retries: u32,
+    /// 1 回目のリトライ前の待ち時間（以降 2 倍ずつ）
+    pub base_delay: Duration,
+    /// 待ち時間の上限（Retry-After が指定された場合も含む）
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 環境変数 `OPENAI_TIMEOUT_SECS` / `OPENAI_MAX_RETRIES` で既定値を上書きする
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(secs) = env_number::<u64>("OPENAI_TIMEOUT_SECS") {
+            policy.timeout = Duration::from_secs(secs);
+        }
+        if let Some(n) = env_number::<u32>("OPENAI_MAX_RETRIES") {
+            policy.max_retries = n;
+        }
+        policy
+    }
+
+    /// `attempt` 回目（0 始まり）の失敗後に待つ時間
+    ///
+    /// Retry-After があればそれに従い、なければ指数バックオフに
+    /// ジッター（0.5〜1.0 倍）を掛けて、複数クライアントの再送が重ならないようにする。
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(wait) = retry_after {
+            return wait.min(self.max_delay);
+        }
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        exp.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+fn env_number<T: std::str::FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
+
+/// リトライする前の通知（表示するかどうかはアプリが決める）
+#[derive(Debug)]
+pub struct RetryNotice<'a> {
+    /// 何回目のリトライか（1 始まり）
+    pub attempt: u32,
+    pub max_retries: u32,
+    /// 再送までの待ち時間
+    pub wait: Duration,
+    /// 直前の失敗
+    pub error: &'a OpenAiCallError,
+}
+
+/// リトライの前に呼ばれるコールバック
+pub type OnRetry<'a> = dyn Fn(&RetryNotice) + Send + Sync + 'a;
+
+/// Retry-After ヘッダー（秒数または HTTP 日付）を待ち時間にする
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        // 表せないほど長い値（inf や 1e30）は最長とみなし、delay() で上限に丸める
+        return (secs >= 0.0).then(|| Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// 待ち時間切れのエラー
+fn timeout_error(policy: &RetryPolicy) -> OpenAiCallError {
+    OpenAiCallError::Timeout(format!(
+        "{} 秒以内に応答がありません",
+        policy.timeout.as_secs_f64()
+    ))
+}
+
+/// 失敗したステータスをエラーの種類に振り分ける
+fn status_error(status: StatusCode, body: &str) -> OpenAiCallError {
+    let detail = format!("status={} body={}", status, body);
+    match status.as_u16() {
+        429 => OpenAiCallError::RateLimited(detail),
+        401 | 403 => OpenAiCallError::Auth(detail),
+        408 => OpenAiCallError::Timeout(detail),
+        500..=599 => OpenAiCallError::Server(detail),
+        _ => OpenAiCallError::Http(detail),
+    }
+}
+
+/// POST して成功ステータスのレスポンスを返す（リトライ可能な失敗は待って再送）
+///
+/// 再送の前に `on_retry` を呼ぶ。
+async fn post_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<&str>,
+    body: &ChatRequest<'_>,
+    policy: &RetryPolicy,
+    on_retry: &OnRetry<'_>,
+) -> Result<reqwest::Response, OpenAiCallError> {
+    let mut attempt = 0;
+    loop {
+        let (err, retry_after) = match post_once(client, url, api_key, body, policy).await {
+            Ok(resp) => return Ok(resp),
+            Err(failure) => failure,
+        };
+        if !err.is_retryable() || attempt >= policy.max_retries {
+            return Err(err);
+        }
+
+        let wait = policy.delay(attempt, retry_after);
+        on_retry(&RetryNotice {
+            attempt: attempt + 1,
+            max_retries: policy.max_retries,
+            wait,
+            error: &err,
+        });
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+/// 1 回だけ送る。失敗時はエラーと Retry-After を返す
+async fn post_once(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<&str>,
+    body: &ChatRequest<'_>,
+    policy: &RetryPolicy,
+) -> Result<reqwest::Response, (OpenAiCallError, Option<Duration>)> {
+    let mut request = client.post(url).header(CONTENT_TYPE, "application/json");
+    if let Some(api_key) = api_key {
+        request = request.header(AUTHORIZATION, format!("Bearer {}", api_key));
+    }
+    let request = request.json(body).send();
+
+    let resp = match tokio::time::timeout(policy.timeout, request).await {
+        Err(_) => return Err((timeout_error(policy), None)),
+        Ok(Err(e)) if e.is_timeout() => {
+            return Err((OpenAiCallError::Timeout(e.to_string()), None));
+        }
+        Ok(Err(e)) => return Err((OpenAiCallError::Http(e.to_string()), None)),
+        Ok(Ok(resp)) => resp,
+    };
+
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let retry_after = resp
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+    let text = resp.text().await.unwrap_or_default();
+    Err((status_error(status, &text), retry_after))
 }
 
 pub async fn send_chat_completion(
     client: &reqwest::Client,
-    api_key: &str,
-    body: &serde_json::Value,
+    endpoint: &EndpointConfig,
+    body: &ChatRequest<'_>,
+    policy: &RetryPolicy,
+    on_retry: &OnRetry<'_>,
 ) -> Result<String, OpenAiCallError> {
-    let resp = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header(AUTHORIZATION, format!("Bearer {}", api_key))
-        .header(CONTENT_TYPE, "application/json")
-        .json(body)
-        .send()
+    let url = endpoint.chat_completions_url();
+    let resp = post_with_retry(
+        client,
+        &url,
+        endpoint.api_key.as_deref(),
+        body,
+        policy,
+        on_retry,
+    )
+    .await?;
+    // なぜ: ヘッダーの後で止まるサーバーもあるので、本文の受信にも上限を掛ける
+    match tokio::time::timeout(policy.timeout, resp.text()).await {
+        Err(_) => Err(timeout_error(policy)),
+        Ok(result) => result.map_err(|e| OpenAiCallError::Http(e.to_string())),
+    }
+}
+
+/// ストリーミングで送信し、本文の差分を受け取るたびに `on_delta` を呼ぶ
+///
+/// 戻り値は非ストリームのレスポンスと同じ形に組み立て直した JSON 文字列
+/// （tool_calls の arguments の断片もつなげてある）。
+/// リトライは応答が始まる前の失敗だけ（差分を表示し始めた後は再送しない）。
+/// 差分の間隔が `policy.timeout` を超えたら打ち切る。
+pub async fn stream_chat_completion(
+    client: &reqwest::Client,
+    endpoint: &EndpointConfig,
+    body: &ChatRequest<'_>,
+    policy: &RetryPolicy,
+    on_retry: &OnRetry<'_>,
+    mut on_delta: impl FnMut(&str),
+) -> Result<String, OpenAiCallError> {
+    let body = body.clone().streaming();
+
+    let url = endpoint.chat_completions_url();
+    let mut resp = post_with_retry(
+        client,
+        &url,
+        endpoint.api_key.as_deref(),
+        &body,
+        policy,
+        on_retry,
+    )
+    .await?;
+
+    let mut parser = SseParser::new();
+    let mut acc = StreamAccumulator::new();
+    loop {
+        let chunk = match tokio::time::timeout(policy.timeout, resp.chunk()).await {
+            Err(_) => return Err(timeout_error(policy)),
+            Ok(result) => result.map_err(|e| OpenAiCallError::Http(e.to_string()))?,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        for data in parser.feed(&chunk) {
+            if let Some(text) = acc.apply_data(&data)? {
+                on_delta(&text);
+            }
+        }
+    }
+    // 空行で閉じられずに切れた最後のイベント
+    if let Some(data) = parser.finish()
+        && let Some(text) = acc.apply_data(&data)?
+    {
+        on_delta(&text);
+    }
+    Ok(acc.finish().to_string())
+}
+
+/// ストリーミング中に本文の差分を受け取るコールバック
+pub type OnDelta<'a> = dyn Fn(&str) + Send + Sync + 'a;
+
+/// Chat Completions のリクエストを送る手段（テストでは差し替える）
+#[async_trait]
+pub trait ChatTransport: Send + Sync {
+    /// リクエストを送り、レスポンス本文を返す
+    async fn send(&self, request: &ChatRequest<'_>) -> Result<String, OpenAiCallError>;
+
+    /// ストリーミングで送る（本文の差分ごとに `on_delta` を呼ぶ）
+    ///
+    /// 戻り値は `send` と同じ形。ストリーミングできない実装は `send` で代用する
+    /// （その場合 `on_delta` は呼ばれない）。
+    async fn send_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<String, OpenAiCallError> {
+        let _ = on_delta;
+        self.send(request).await
+    }
+
+    /// リトライの前に呼ぶコールバックを設定する（リトライしない実装では何もしない）
+    fn set_on_retry(&mut self, on_retry: Arc<OnRetry<'static>>) {
+        let _ = on_retry;
+    }
+}
+
+/// OpenAI（互換）API に送る ChatTransport
+pub struct OpenAiTransport {
+    client: reqwest::Client,
+    endpoint: EndpointConfig,
+    retry: RetryPolicy,
+    on_retry: Arc<OnRetry<'static>>,
+}
+
+impl OpenAiTransport {
+    /// リトライ方針は環境変数から（`RetryPolicy::from_env`）
+    pub fn new(endpoint: EndpointConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            retry: RetryPolicy::from_env(),
+            on_retry: Arc::new(|_: &RetryNotice| {}),
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+#[async_trait]
+impl ChatTransport for OpenAiTransport {
+    async fn send(&self, request: &ChatRequest<'_>) -> Result<String, OpenAiCallError> {
+        send_chat_completion(
+            &self.client,
+            &self.endpoint,
+            request,
+            &self.retry,
+            self.on_retry.as_ref(),
+        )
         .await
-        .map_err(|e| OpenAiCallError::Http(e.to_string()))?;
-    let status = resp.status();
-    let text = resp
-        .text()
+    }
+
+    async fn send_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<String, OpenAiCallError> {
+        stream_chat_completion(
+            &self.client,
+            &self.endpoint,
+            request,
+            &self.retry,
+            self.on_retry.as_ref(),
+            on_delta,
+        )
+        .await
+    }
+
+    fn set_on_retry(&mut self, on_retry: Arc<OnRetry<'static>>) {
+        self.on_retry = on_retry;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// モックサーバーの 1 回分の応答
+    enum Reply {
+        /// 書き込んで閉じる
+        Send(String),
+        /// 書き込んだ後、閉じずに黙る（ヘッダーや本文の途中で止まるサーバー）
+        Stall(String),
+        /// 何も返さずに黙る
+        Silent,
+    }
+
+    /// 用意したレスポンスを順に返すだけのローカル HTTP サーバー
+    ///
+    /// 戻り値は base_url と受け付けたリクエスト（ヘッダー部分）の記録。
+    async fn mock_server(responses: Vec<Reply>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&requests);
+
+        tokio::spawn(async move {
+            for response in responses {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let head = read_request(&mut socket).await;
+                log.lock().unwrap().push(head);
+                match response {
+                    Reply::Send(raw) => {
+                        let _ = socket.write_all(raw.as_bytes()).await;
+                        let _ = socket.shutdown().await;
+                    }
+                    // 接続を保持したまま続きを送らない
+                    Reply::Stall(raw) => {
+                        let _ = socket.write_all(raw.as_bytes()).await;
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                    Reply::Silent => tokio::time::sleep(Duration::from_secs(5)).await,
+                }
+            }
+        });
+        (base_url, requests)
+    }
+
+    /// ヘッダーと Content-Length 分の本文を読み、ヘッダー部分を返す
+    async fn read_request(socket: &mut TcpStream) -> String {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        loop {
+            let n = socket.read(&mut chunk).await.unwrap_or(0);
+            if n == 0 {
+                return String::from_utf8_lossy(&buf).into_owned();
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text[..end]
+                    .lines()
+                    .find_map(|l| {
+                        l.to_ascii_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                    })
+                    .unwrap_or(0);
+                if buf.len() >= end + 4 + length {
+                    return text[..end].to_string();
+                }
+            }
+        }
+    }
+
+    fn http(status: &str, headers: &[&str], body: &str) -> Reply {
+        let mut raw = format!(
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            body.len()
+        );
+        for h in headers {
+            raw.push_str(h);
+            raw.push_str("\r\n");
+        }
+        raw.push_str("\r\n");
+        raw.push_str(body);
+        Reply::Send(raw)
+    }
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            timeout: Duration::from_millis(300),
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(20),
+        }
+    }
+
+    fn endpoint(base_url: &str, api_key: Option<&str>) -> EndpointConfig {
+        EndpointConfig {
+            base_url: base_url.to_string(),
+            model: "local-model".to_string(),
+            api_key: api_key.map(str::to_string),
+        }
+    }
+
+    async fn post(base_url: &str, policy: &RetryPolicy) -> Result<String, OpenAiCallError> {
+        let client = reqwest::Client::new();
+        send_chat_completion(
+            &client,
+            &endpoint(base_url, Some("test-key")),
+            &ChatRequest::new("local-model", &[]),
+            policy,
+            &|_: &RetryNotice| {},
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_error_then_succeeds() {
+        let (url, requests) = mock_server(vec![
+            http("500 Internal Server Error", &[], "oops"),
+            http("429 Too Many Requests", &["Retry-After: 0"], "slow down"),
+            http("200 OK", &[], r#"{"ok":true}"#),
+        ])
+        .await;
+
+        let notices = Mutex::new(Vec::new());
+        let body = send_chat_completion(
+            &reqwest::Client::new(),
+            &endpoint(&url, Some("test-key")),
+            &ChatRequest::new("local-model", &[]),
+            &fast_policy(3),
+            &|notice: &RetryNotice| {
+                let kind = match notice.error {
+                    OpenAiCallError::Server(_) => "server",
+                    OpenAiCallError::RateLimited(_) => "rate_limited",
+                    _ => "other",
+                };
+                notices
+                    .lock()
+                    .unwrap()
+                    .push((notice.attempt, notice.max_retries, kind));
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(body, r#"{"ok":true}"#);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        // 再送の前に毎回通知される（表示はアプリ側）
+        assert_eq!(
+            *notices.lock().unwrap(),
+            vec![(1, 3, "server"), (2, 3, "rate_limited")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_auth_error_is_not_retried() {
+        let (url, requests) = mock_server(vec![
+            http("401 Unauthorized", &[], "bad key"),
+            http("200 OK", &[], "{}"),
+        ])
+        .await;
+
+        let err = post(&url, &fast_policy(3)).await.unwrap_err();
+
+        assert!(matches!(err, OpenAiCallError::Auth(ref m) if m.contains("bad key")));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let (url, requests) = mock_server(vec![
+            http("503 Service Unavailable", &[], ""),
+            http("503 Service Unavailable", &[], ""),
+            http("429 Too Many Requests", &[], ""),
+        ])
+        .await;
+
+        let err = post(&url, &fast_policy(2)).await.unwrap_err();
+
+        assert!(matches!(err, OpenAiCallError::RateLimited(_)));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let (url, _) = mock_server(vec![Reply::Silent]).await;
+
+        let err = post(&url, &fast_policy(0)).await.unwrap_err();
+
+        assert!(matches!(err, OpenAiCallError::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_while_reading_body() {
+        // ヘッダーと本文の一部だけ送って止まる
+        let (url, _) = mock_server(vec![Reply::Stall(
+            "HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n{\"choices\"".to_string(),
+        )])
+        .await;
+
+        let start = std::time::Instant::now();
+        let err = post(&url, &fast_policy(0)).await.unwrap_err();
+
+        assert!(matches!(err, OpenAiCallError::Timeout(_)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_stream_timeout_between_chunks() {
+        let (url, _) = mock_server(vec![Reply::Stall(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n\
+             data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"途中\"}}]}\n\n"
+                .to_string(),
+        )])
+        .await;
+        let client = reqwest::Client::new();
+        let mut deltas = Vec::new();
+
+        let err = stream_chat_completion(
+            &client,
+            &endpoint(&url, None),
+            &ChatRequest::new("local-model", &[]),
+            &fast_policy(0),
+            &|_: &RetryNotice| {},
+            |text| deltas.push(text.to_string()),
+        )
         .await
-        .map_err(|e| OpenAiCallError::Http(e.to_string()))?;
-    if !status.is_success() {
-        return Err(OpenAiCallError::Http(format!(
-            "status={} body={}",
-            status, text
-        )));
-    }
-    Ok(text)
+        .unwrap_err();
+
+        assert!(matches!(err, OpenAiCallError::Timeout(_)));
+        assert_eq!(deltas, vec!["途中"]);
+    }
+
+    #[tokio::test]
+    async fn test_custom_endpoint_without_api_key() {
+        let (base_url, requests) = mock_server(vec![http("200 OK", &[], "{}")]).await;
+        let client = reqwest::Client::new();
+
+        send_chat_completion(
+            &client,
+            &endpoint(&format!("{base_url}/"), None),
+            &ChatRequest::new("local-model", &[]),
+            &fast_policy(0),
+            &|_: &RetryNotice| {},
+        )
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("POST /v1/chat/completions HTTP/1.1"));
+        assert!(!requests[0].to_ascii_lowercase().contains("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_sent_as_bearer() {
+        let (base_url, requests) = mock_server(vec![http("200 OK", &[], "{}")]).await;
+
+        post(&base_url, &fast_policy(0)).await.unwrap();
+
+        let head = requests.lock().unwrap()[0].to_ascii_lowercase();
+        assert!(head.contains("authorization: bearer test-key"));
+    }
+
+    #[test]
+    fn test_endpoint_from_env() {
+        let env = |pairs: &[(&str, &str)]| {
+            let map: HashMap<String, String> = pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            move |key: &str| map.get(key).cloned()
+        };
+
+        let config = EndpointConfig::from_lookup("WEATHER", "gpt-4o-mini", env(&[]));
+        assert_eq!(config.base_url, OPENAI_BASE_URL);
+        assert_eq!(config.model, "gpt-4o-mini");
+        assert_eq!(config.api_key, None);
+        assert!(config.validate().is_err());
+
+        // アプリ別の設定が共通の設定より優先（空文字は未設定扱い）
+        let config = EndpointConfig::from_lookup(
+            "WEATHER",
+            "gpt-4o-mini",
+            env(&[
+                ("OPENAI_BASE_URL", "http://localhost:8080/v1"),
+                ("WEATHER_BASE_URL", "http://localhost:11434/v1/"),
+                ("OPENAI_MODEL", "llama3"),
+                ("WEATHER_API_KEY", ""),
+            ]),
+        );
+        assert_eq!(
+            config.chat_completions_url(),
+            "http://localhost:11434/v1/chat/completions"
+        );
+        assert_eq!(config.model, "llama3");
+        assert_eq!(config.api_key, None);
+        assert!(config.validate().is_ok());
+
+        let config = config.with_overrides(None, Some("qwen2.5"), Some("sk-local"));
+        assert_eq!(config.model, "qwen2.5");
+        assert_eq!(config.api_key.as_deref(), Some("sk-local"));
+        assert_eq!(config.base_url, "http://localhost:11434/v1/");
+    }
+
+    #[test]
+    fn test_delay_backoff_and_retry_after() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        for attempt in 0..3 {
+            let full = Duration::from_millis(100 * 2u64.pow(attempt));
+            let wait = policy.delay(attempt, None);
+            assert!(wait >= full / 2 && wait <= full, "{attempt}: {wait:?}");
+        }
+        assert!(policy.delay(10, None) <= Duration::from_secs(1));
+
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_millis(700))),
+            Duration::from_millis(700)
+        );
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(60))),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_retry_after("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("NaN"), None);
+        assert_eq!(parse_retry_after("inf"), Some(Duration::MAX));
+        assert_eq!(parse_retry_after("1e30"), Some(Duration::MAX));
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0, parse_retry_after("1e30")), policy.max_delay);
+        // 過去の日付は待たない
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        let future = (Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let wait = parse_retry_after(&future).unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_status_error_kinds() {
+        let kind = |code: u16| status_error(StatusCode::from_u16(code).unwrap(), "");
+        assert!(matches!(kind(429), OpenAiCallError::RateLimited(_)));
+        assert!(matches!(kind(403), OpenAiCallError::Auth(_)));
+        assert!(matches!(kind(502), OpenAiCallError::Server(_)));
+        assert!(matches!(kind(400), OpenAiCallError::Http(_)));
+        assert!(!kind(400).is_retryable());
+        assert!(kind(500).is_retryable());
+    }
 }
@@ -0,0 +1,308 @@
+use std::ops::Range;
+
+use crate::function_call::types::ChatMessage;
+
+/// 要約を入れた system メッセージの先頭（2 件目の system がこれで始まれば要約）
+pub const SUMMARY_PREFIX: &str = "これまでの会話の要約: ";
+
+/// 要約を頼むときのプロンプト
+const SUMMARY_PROMPT: &str = "あなたは会話の記録係です。以下の会話を、この後の会話を続けるのに必要な事実（ユーザーの依頼・調べた都市や数値・保存したメモやアラートの ID・未解決の事項）が残るように、日本語で簡潔に要約してください。";
+
+/// 要約に渡すツール結果の最大文字数（長い一覧は途中で切る）
+const SUMMARY_TOOL_RESULT_CHARS: usize = 500;
+
+/// 予算を超えたときの減らし方
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContextStrategy {
+    /// 古い発言をそのまま捨てる
+    Drop,
+    /// 古い発言をモデルに要約させ、system メッセージとして残す（失敗したら捨てる）
+    Summarize,
+}
+
+/// 履歴のトークン予算
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextBudget {
+    /// 履歴（messages）の推定トークン数の上限（ツール定義の分は含まない）
+    pub max_tokens: usize,
+    /// 予算を超えても必ず残す直近の発言数（1 未満は 1 とみなす）
+    pub keep_recent_turns: usize,
+    pub strategy: ContextStrategy,
+}
+
+impl Default for ContextBudget {
+    fn default() -> Self {
+        Self {
+            max_tokens: 8000,
+            keep_recent_turns: 2,
+            strategy: ContextStrategy::Summarize,
+        }
+    }
+}
+
+impl ContextBudget {
+    /// CONTEXT_MAX_TOKENS / CONTEXT_KEEP_TURNS / CONTEXT_STRATEGY（drop / summarize）で上書き
+    pub fn from_env() -> Self {
+        let mut budget = Self::default();
+        let number = |key: &str| std::env::var(key).ok()?.trim().parse::<usize>().ok();
+        if let Some(max_tokens) = number("CONTEXT_MAX_TOKENS") {
+            budget.max_tokens = max_tokens;
+        }
+        if let Some(keep) = number("CONTEXT_KEEP_TURNS") {
+            budget.keep_recent_turns = keep;
+        }
+        match std::env::var("CONTEXT_STRATEGY")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" => {}
+            "drop" => budget.strategy = ContextStrategy::Drop,
+            "summarize" => budget.strategy = ContextStrategy::Summarize,
+            other => eprintln!(
+                "⚠️ 不明な CONTEXT_STRATEGY です: {}（drop / summarize、既定の summarize を使います）",
+                other
+            ),
+        }
+        budget
+    }
+}
+
+/// 文字列の推定トークン数
+///
+/// 英数字は 4 文字で 1 トークン、日本語などは 1 文字 1 トークンとして数える
+/// （tiktoken の実測より少し多めになる）。
+fn text_tokens(text: &str) -> usize {
+    let ascii = text.chars().filter(char::is_ascii).count();
+    let other = text.chars().count() - ascii;
+    ascii.div_ceil(4) + other
+}
+
+/// メッセージ 1 件の推定トークン数（role などの付加分として 4 を足す）
+fn message_tokens(message: &ChatMessage) -> usize {
+    let calls: usize = match message {
+        ChatMessage::Assistant { tool_calls, .. } => tool_calls
+            .iter()
+            .map(|tc| text_tokens(&tc.function.name) + text_tokens(&tc.function.arguments) + 4)
+            .sum(),
+        _ => 0,
+    };
+    4 + message.content().map_or(0, text_tokens) + calls
+}
+
+/// 履歴全体の推定トークン数
+pub fn estimate_tokens(messages: &[ChatMessage]) -> usize {
+    messages.iter().map(message_tokens).sum()
+}
+
+/// 要約メッセージか
+fn is_summary(message: &ChatMessage) -> bool {
+    matches!(message, ChatMessage::System { content } if content.starts_with(SUMMARY_PREFIX))
+}
+
+/// 予算に収めるために取り除く範囲（不要なら None）
+///
+/// 先頭の system メッセージは残し、ユーザー発言の直前でだけ区切るので、
+/// tool_calls 付きの assistant と tool の結果が別々になることはない。
+/// 前回の要約は範囲に含める（新しい要約にまとめ直す）。
+/// 直近 `keep_recent_turns` 件の発言は予算を超えても残す。
+pub fn removable_range(messages: &[ChatMessage], budget: &ContextBudget) -> Option<Range<usize>> {
+    if estimate_tokens(messages) <= budget.max_tokens {
+        return None;
+    }
+
+    let head = 1; // system プロンプト
+    let user_starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .skip(head)
+        .filter(|(_, m)| matches!(m, ChatMessage::User { .. }))
+        .map(|(i, _)| i)
+        .collect();
+    let keep = budget.keep_recent_turns.max(1);
+    let removable_turns = user_starts.len().saturating_sub(keep);
+
+    // 古い発言から 1 つずつ増やし、収まった時点で止める（収まらなければ消せるだけ消す）
+    let mut range = None;
+    for &end in user_starts.iter().skip(1).take(removable_turns) {
+        range = Some(head..end);
+        let removed = estimate_tokens(&messages[head..end]);
+        if estimate_tokens(messages) - removed <= budget.max_tokens {
+            break;
+        }
+    }
+    range.filter(|r| !r.is_empty())
+}
+
+/// `range` を取り除き、要約があれば system プロンプトの直後に入れる
+pub fn apply(messages: &mut Vec<ChatMessage>, range: Range<usize>, summary: Option<&str>) {
+    let at = range.start;
+    messages.drain(range);
+    if let Some(summary) = summary {
+        messages.insert(
+            at,
+            ChatMessage::system(format!("{}{}", SUMMARY_PREFIX, summary)),
+        );
+    }
+}
+
+/// 要約を頼むリクエストのメッセージ
+pub fn summary_prompt(old: &[ChatMessage]) -> Vec<ChatMessage> {
+    let lines: Vec<String> = old
+        .iter()
+        .filter_map(|m| match m {
+            ChatMessage::System { content } if is_summary(m) => Some(format!(
+                "（前回までの要約）{}",
+                &content[SUMMARY_PREFIX.len()..]
+            )),
+            ChatMessage::System { .. } => None,
+            ChatMessage::User { content } => Some(format!("ユーザー: {}", content)),
+            ChatMessage::Assistant {
+                content,
+                tool_calls,
+            } => {
+                let mut parts: Vec<String> = tool_calls
+                    .iter()
+                    .map(|tc| {
+                        format!(
+                            "ツール呼び出し: {}({})",
+                            tc.function.name, tc.function.arguments
+                        )
+                    })
+                    .collect();
+                if let Some(text) = content.as_deref().filter(|t| !t.is_empty()) {
+                    parts.insert(0, format!("アシスタント: {}", text));
+                }
+                Some(parts.join("\n"))
+            }
+            ChatMessage::Tool { content, .. } => {
+                let mut result: String = content.chars().take(SUMMARY_TOOL_RESULT_CHARS).collect();
+                if content.chars().count() > SUMMARY_TOOL_RESULT_CHARS {
+                    result.push('…');
+                }
+                Some(format!("ツール結果: {}", result))
+            }
+        })
+        .collect();
+
+    vec![
+        ChatMessage::system(SUMMARY_PROMPT),
+        ChatMessage::user(lines.join("\n")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function_call::types::{CalledFunction, ToolCall};
+
+    fn fetch_weather_call() -> ChatMessage {
+        ChatMessage::assistant_tool_calls(
+            None,
+            vec![ToolCall {
+                id: "c1".to_string(),
+                call_type: "function".to_string(),
+                function: CalledFunction {
+                    name: "fetch_weather".to_string(),
+                    arguments: r#"{"city":"東京"}"#.to_string(),
+                },
+            }],
+        )
+    }
+
+    fn conversation() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system("係"),
+            ChatMessage::user("東京の天気は？"),
+            fetch_weather_call(),
+            ChatMessage::tool("c1", "晴れ"),
+            ChatMessage::assistant("晴れです"),
+            ChatMessage::user("じゃあ大阪は？"),
+            ChatMessage::assistant("雨です"),
+            ChatMessage::user("ありがとう"),
+        ]
+    }
+
+    fn budget(max_tokens: usize, keep_recent_turns: usize) -> ContextBudget {
+        ContextBudget {
+            max_tokens,
+            keep_recent_turns,
+            strategy: ContextStrategy::Drop,
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(text_tokens("hello world"), 3);
+        assert_eq!(text_tokens("東京"), 2);
+        assert_eq!(estimate_tokens(&[ChatMessage::user("東京")]), 6);
+        // tool_calls の名前と引数も数える
+        assert!(message_tokens(&fetch_weather_call()) > 4 + 4);
+    }
+
+    #[test]
+    fn test_within_budget_removes_nothing() {
+        let messages = conversation();
+        let total = estimate_tokens(&messages);
+        assert_eq!(removable_range(&messages, &budget(total, 1)), None);
+    }
+
+    #[test]
+    fn test_drops_oldest_turns_without_splitting_tool_results() {
+        let mut messages = conversation();
+        let range = removable_range(&messages, &budget(35, 1)).unwrap();
+
+        apply(&mut messages, range, None);
+
+        // 1 回目の発言（tool_calls と tool の結果ごと）が消え、発言の先頭から始まる
+        assert_eq!(messages[0].role(), "system");
+        assert_eq!(messages[1].content(), Some("じゃあ大阪は？"));
+        assert!(messages.iter().all(|m| m.role() != "tool"));
+    }
+
+    #[test]
+    fn test_keeps_recent_turns_over_budget() {
+        let mut messages = conversation();
+        let range = removable_range(&messages, &budget(1, 2)).unwrap();
+        apply(&mut messages, range, None);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1].content(), Some("じゃあ大阪は？"));
+
+        // 最新の発言しかなければそれ以上削らない
+        let single = vec![
+            ChatMessage::system("係"),
+            ChatMessage::user("a"),
+            ChatMessage::assistant("b"),
+        ];
+        assert_eq!(removable_range(&single, &budget(1, 0)), None);
+    }
+
+    #[test]
+    fn test_summary_is_inserted_and_folded_next_time() {
+        let mut messages = conversation();
+        let range = removable_range(&messages, &budget(35, 1)).unwrap();
+        let prompt = summary_prompt(&messages[range.clone()]);
+        let transcript = prompt[1].content().unwrap();
+        assert!(transcript.contains("ユーザー: 東京の天気は？"));
+        assert!(transcript.contains(r#"ツール呼び出し: fetch_weather({"city":"東京"})"#));
+        assert!(transcript.contains("ツール結果: 晴れ"));
+
+        apply(&mut messages, range, Some("東京は晴れ"));
+        assert_eq!(
+            messages[1].content(),
+            Some("これまでの会話の要約: 東京は晴れ")
+        );
+
+        // 次に削るときは前回の要約も範囲に入る
+        let range = removable_range(&messages, &budget(1, 1)).unwrap();
+        assert_eq!(range.start, 1);
+        let prompt = summary_prompt(&messages[range]);
+        assert!(
+            prompt[1]
+                .content()
+                .unwrap()
+                .starts_with("（前回までの要約）東京は晴れ")
+        );
+    }
+}
@@ -1,47 +1,88 @@
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug)]
-pub enum ToolCallOutcome {
-    NoToolCall,
-    Executed {
-        tool_name: String,
-        result_number: f64,
-        tool_call_id: String,
-        raw_arguments: String,
-    },
-}
-
 #[derive(Debug)]
 pub enum ToolCallError {
-    EmptyChoices,
     UnknownTool(String),
     ArgParse(String),
+    InvalidArgs(String), // JSON Schema に合わない（違反箇所を列挙）
+    Failed(String),      // ツール本体が返したエラー
 }
 
 impl Display for ToolCallError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            ToolCallError::EmptyChoices => write!(f, "choices が空"),
             ToolCallError::UnknownTool(n) => write!(f, "未知のツール: {n}"),
             ToolCallError::ArgParse(e) => write!(f, "引数パース失敗: {e}"),
+            ToolCallError::InvalidArgs(e) => write!(f, "引数がスキーマに合いません: {e}"),
+            ToolCallError::Failed(e) => write!(f, "ツール実行失敗: {e}"),
         }
     }
 }
+impl ToolCallError {
+    /// 引数の誤り（モデルが引数を直せば成功する見込みがある）
+    pub fn is_argument_error(&self) -> bool {
+        matches!(
+            self,
+            ToolCallError::ArgParse(_) | ToolCallError::InvalidArgs(_)
+        )
+    }
+}
 impl std::error::Error for ToolCallError {}
 
 #[derive(Debug)]
 pub enum OpenAiCallError {
     Http(String),
+    RateLimited(String), // 429（リトライしても解消しなかった）
+    Auth(String),        // 401 / 403（API キーの誤りなど。リトライしない）
+    Server(String),      // 5xx
+    Timeout(String),     // 応答が時間内に返らなかった
     JsonBuild(String),
     JsonParse(String),
+    Replay(String), // 記録したやり取りと合わない（LLM_CASSETTE の再生中）
+}
+impl OpenAiCallError {
+    /// 時間をおけば成功する見込みがあるか
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            OpenAiCallError::RateLimited(_)
+                | OpenAiCallError::Server(_)
+                | OpenAiCallError::Timeout(_)
+        )
+    }
 }
 impl Display for OpenAiCallError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             OpenAiCallError::Http(e) => write!(f, "HTTPエラー: {e}"),
+            OpenAiCallError::RateLimited(e) => write!(f, "レート制限: {e}"),
+            OpenAiCallError::Auth(e) => write!(f, "認証エラー: {e}"),
+            OpenAiCallError::Server(e) => write!(f, "サーバーエラー: {e}"),
+            OpenAiCallError::Timeout(e) => write!(f, "タイムアウト: {e}"),
             OpenAiCallError::JsonBuild(e) => write!(f, "JSON生成失敗: {e}"),
             OpenAiCallError::JsonParse(e) => write!(f, "JSONパース失敗: {e}"),
+            OpenAiCallError::Replay(e) => write!(f, "再生エラー: {e}"),
         }
     }
 }
 impl std::error::Error for OpenAiCallError {}
+
+#[derive(Debug)]
+pub enum AgentError {
+    Api(OpenAiCallError),
+    EmptyChoices,
+}
+impl Display for AgentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AgentError::Api(e) => write!(f, "API 呼び出し失敗: {e}"),
+            AgentError::EmptyChoices => write!(f, "choices が空"),
+        }
+    }
+}
+impl std::error::Error for AgentError {}
+impl From<OpenAiCallError> for AgentError {
+    fn from(e: OpenAiCallError) -> Self {
+        AgentError::Api(e)
+    }
+}
@@ -0,0 +1,841 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use num_rational::BigRational;
+use num_traits::Signed;
+
+use crate::function_call::number::{Number, NumberError, NumberMode, exact_root};
+
+/// 括弧や単項マイナスの入れ子の上限（深すぎる入力でスタックを使い切らないように）
+const MAX_DEPTH: usize = 64;
+
+/// 式の誤り
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprError {
+    pub message: String,
+    /// 誤りのある範囲（入力の文字位置、0 始まり・終端は含まない）
+    pub span: Range<usize>,
+}
+
+impl ExprError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// 入力の下に `^` で誤りの場所を示した表示（モデルへ返すエラー用）
+    pub fn render(&self, input: &str) -> String {
+        let width = (self.span.end - self.span.start).max(1);
+        format!(
+            "{}\n{}{}\n{}",
+            input,
+            " ".repeat(self.span.start),
+            "^".repeat(width),
+            self
+        )
+    }
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} 文字目: {}", self.span.start + 1, self.message)
+    }
+}
+
+/// 有理数での計算（None を返したら f64 で近似する）
+type ExactFn = fn(&[BigRational]) -> Option<BigRational>;
+
+/// 使える関数（引数の数は min〜max）
+struct Function {
+    name: &'static str,
+    min_args: usize,
+    max_args: usize,
+    apply: fn(&[f64]) -> f64,
+    /// 厳密モードでの計算
+    exact: Option<ExactFn>,
+}
+
+/// 引数 1 個の関数（`f64` のメソッドをそのまま使う、`exact` なら有理数でも同名のメソッドで）
+macro_rules! unary {
+    ($name:literal, $method:ident) => {
+        Function {
+            name: $name,
+            min_args: 1,
+            max_args: 1,
+            apply: |a| a[0].$method(),
+            exact: None,
+        }
+    };
+    ($name:literal, $method:ident, exact) => {
+        Function {
+            name: $name,
+            min_args: 1,
+            max_args: 1,
+            apply: |a| a[0].$method(),
+            exact: Some(|a| Some(a[0].$method())),
+        }
+    };
+}
+
+const FUNCTIONS: &[Function] = &[
+    // 平方数の平方根（sqrt(9/4) = 3/2）は厳密に求める
+    Function {
+        name: "sqrt",
+        min_args: 1,
+        max_args: 1,
+        apply: |a| a[0].sqrt(),
+        exact: Some(|a| exact_root(&a[0], 2)),
+    },
+    unary!("abs", abs, exact),
+    unary!("sin", sin),
+    unary!("cos", cos),
+    unary!("tan", tan),
+    unary!("asin", asin),
+    unary!("acos", acos),
+    unary!("atan", atan),
+    unary!("exp", exp),
+    unary!("ln", ln),
+    // log(x) は常用対数、log(x, b) は b を底とする対数
+    Function {
+        name: "log",
+        min_args: 1,
+        max_args: 2,
+        apply: |a| match a {
+            [x, base] => x.log(*base),
+            _ => a[0].log10(),
+        },
+        exact: None,
+    },
+    unary!("log2", log2),
+    unary!("floor", floor, exact),
+    unary!("ceil", ceil, exact),
+    unary!("round", round, exact),
+    Function {
+        name: "min",
+        min_args: 1,
+        max_args: usize::MAX,
+        apply: |a| a.iter().copied().fold(f64::INFINITY, f64::min),
+        exact: Some(|a| a.iter().min().cloned()),
+    },
+    Function {
+        name: "max",
+        min_args: 1,
+        max_args: usize::MAX,
+        apply: |a| a.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        exact: Some(|a| a.iter().max().cloned()),
+    },
+];
+
+const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("e", std::f64::consts::E),
+    ("tau", std::f64::consts::TAU),
+];
+
+impl Function {
+    fn arity(&self) -> String {
+        if self.min_args == self.max_args {
+            format!("{} 個", self.min_args)
+        } else if self.max_args == usize::MAX {
+            format!("{} 個以上", self.min_args)
+        } else {
+            format!("{}〜{} 個", self.min_args, self.max_args)
+        }
+    }
+}
+
+/// 関数名と定数名の一覧（不明な名前のエラーに添える）
+fn known_names() -> String {
+    let functions: Vec<&str> = FUNCTIONS.iter().map(|f| f.name).collect();
+    let constants: Vec<&str> = CONSTANTS.iter().map(|(name, _)| *name).collect();
+    format!(
+        "関数: {} / 定数: {}",
+        functions.join(", "),
+        constants.join(", ")
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    /// 値は構文解析でモードに応じて読む（厳密モードでは 10 進のまま）
+    Number,
+    Ident(String),
+    /// 演算子・括弧・カンマ（`**` は `^`、`×` `÷` は `*` `/` に読み替える）
+    Symbol(char),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    span: Range<usize>,
+}
+
+/// 数の終わりの位置（`1.5`、`2e-3` のような指数表記も含む）
+fn scan_number(chars: &[char], mut i: usize) -> usize {
+    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+        i += 1;
+    }
+    if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+        let mut j = i + 1;
+        if j < chars.len() && matches!(chars[j], '+' | '-') {
+            j += 1;
+        }
+        // 数字が続かなければ指数ではない（`2e` は 2 と定数 e）
+        if j < chars.len() && chars[j].is_ascii_digit() {
+            i = j;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+    }
+    i
+}
+
+fn tokenize(chars: &[char]) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let kind = if c.is_ascii_digit() || c == '.' {
+            i = scan_number(chars, i);
+            let text: String = chars[start..i].iter().collect();
+            if text.parse::<f64>().is_err() {
+                return Err(ExprError::new(
+                    format!("数として読めません: {}", text),
+                    start..i,
+                ));
+            }
+            TokenKind::Number
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            TokenKind::Ident(chars[start..i].iter().collect())
+        } else {
+            i += 1;
+            let symbol = match c {
+                '*' if chars.get(i) == Some(&'*') => {
+                    i += 1;
+                    '^'
+                }
+                '+' | '-' | '*' | '/' | '%' | '^' | '(' | ')' | ',' => c,
+                '×' => '*',
+                '÷' => '/',
+                _ => {
+                    return Err(ExprError::new(
+                        format!("使えない文字です: '{}'", c),
+                        start..i,
+                    ));
+                }
+            };
+            TokenKind::Symbol(symbol)
+        };
+        tokens.push(Token {
+            kind,
+            text: chars[start..i].iter().collect(),
+            span: start..i,
+        });
+    }
+    Ok(tokens)
+}
+
+enum Node {
+    Number(Number),
+    Neg(Box<Expr>),
+    /// 左結合の演算の並び（`1 + 2 - 3` は 1 と [(+, 2), (-, 3)]）
+    ///
+    /// 木を左に深く伸ばすと、項の多い式で計算や破棄の再帰がスタックを使い切るので平らに持つ。
+    Chain(Box<Expr>, Vec<(char, Expr)>),
+    Call(&'static Function, Vec<Expr>),
+}
+
+/// 構文木（span はその部分式が入力のどこにあるか）
+struct Expr {
+    node: Node,
+    span: Range<usize>,
+}
+
+impl Expr {
+    /// `first` に演算を続けた式（続きがなければ `first` のまま）
+    fn chain(first: Expr, rest: Vec<(char, Expr)>) -> Self {
+        let Some((_, last)) = rest.last() else {
+            return first;
+        };
+        Self {
+            span: first.span.start..last.span.end,
+            node: Node::Chain(Box::new(first), rest),
+        }
+    }
+
+    fn eval(&self) -> Result<Number, ExprError> {
+        match &self.node {
+            Node::Number(n) => Ok(n.clone()),
+            Node::Neg(operand) => Ok(operand.eval()?.neg()),
+            Node::Chain(first, rest) => {
+                let mut a = first.eval()?;
+                for (op, rhs) in rest {
+                    let b = rhs.eval()?;
+                    // 誤りの範囲は先頭からこの演算まで
+                    let span = first.span.start..rhs.span.end;
+                    a = a.binary(*op, &b).map_err(|e| match e {
+                        NumberError::DivisionByZero => {
+                            ExprError::new(e.to_string(), rhs.span.clone())
+                        }
+                        NumberError::NotReal => {
+                            ExprError::new(format!("{}（{} {} {}）", e, a, op, b), span)
+                        }
+                        NumberError::TooLarge => ExprError::new(e.to_string(), span),
+                    })?;
+                }
+                Ok(a)
+            }
+            Node::Call(function, args) => {
+                let values = args.iter().map(Expr::eval).collect::<Result<Vec<_>, _>>()?;
+                let exact = values
+                    .iter()
+                    .map(|v| v.as_exact().cloned())
+                    .collect::<Option<Vec<_>>>();
+                if let (Some(exact_fn), Some(exact)) = (function.exact, exact)
+                    && let Some(value) = exact_fn(&exact)
+                {
+                    return Ok(Number::Exact(value));
+                }
+
+                let floats: Vec<f64> = values.iter().map(Number::to_f64).collect();
+                let value = (function.apply)(&floats);
+                if !value.is_finite() {
+                    let shown: Vec<String> = values.iter().map(Number::to_string).collect();
+                    return Err(ExprError::new(
+                        format!(
+                            "{}({}) は計算できません（定義域の外です）",
+                            function.name,
+                            shown.join(", ")
+                        ),
+                        self.span.clone(),
+                    ));
+                }
+                Ok(Number::Approx(value))
+            }
+        }
+    }
+}
+
+/// 再帰下降の構文解析
+///
+/// ```text
+/// expr    = term (("+" | "-") term)*
+/// term    = unary (("*" | "/" | "%") unary)*
+/// unary   = ("-" | "+") unary | power
+/// power   = primary ("^" unary)?        右結合、-2^2 は -(2^2)
+/// primary = 数 | 定数 | 関数 "(" expr ("," expr)* ")" | "(" expr ")"
+/// ```
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// 入力の文字数（「途中で終わっている」エラーの位置）
+    end: usize,
+    depth: usize,
+    mode: NumberMode,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// 次が `symbols` のどれかなら読み進めてその記号を返す
+    fn eat(&mut self, symbols: &[char]) -> Option<char> {
+        match self.peek()?.kind {
+            TokenKind::Symbol(c) if symbols.contains(&c) => {
+                self.pos += 1;
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    /// 直前に読んだトークンの終わり
+    fn prev_end(&self) -> usize {
+        self.tokens[self.pos - 1].span.end
+    }
+
+    fn end_span(&self) -> Range<usize> {
+        self.end..self.end + 1
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, ExprError> {
+        let first = self.parse_term()?;
+        let mut rest = Vec::new();
+        while let Some(op) = self.eat(&['+', '-']) {
+            rest.push((op, self.parse_term()?));
+        }
+        Ok(Expr::chain(first, rest))
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, ExprError> {
+        let first = self.parse_unary()?;
+        let mut rest = Vec::new();
+        while let Some(op) = self.eat(&['*', '/', '%']) {
+            rest.push((op, self.parse_unary()?));
+        }
+        Ok(Expr::chain(first, rest))
+    }
+
+    /// 再帰はすべてここを通るので、入れ子の深さもここで数える
+    fn parse_unary(&mut self) -> Result<Expr, ExprError> {
+        if self.depth == MAX_DEPTH {
+            let span = self.peek().map_or(self.end_span(), |t| t.span.clone());
+            return Err(ExprError::new("式の入れ子が深すぎます", span));
+        }
+        self.depth += 1;
+        let result = self.parse_signed();
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_signed(&mut self) -> Result<Expr, ExprError> {
+        let start = self.peek().map_or(self.end, |t| t.span.start);
+        let Some(sign) = self.eat(&['-', '+']) else {
+            return self.parse_power();
+        };
+        let operand = self.parse_unary()?;
+        let span = start..operand.span.end;
+        let node = match sign {
+            '-' => Node::Neg(Box::new(operand)),
+            _ => operand.node,
+        };
+        Ok(Expr { node, span })
+    }
+
+    fn parse_power(&mut self) -> Result<Expr, ExprError> {
+        let base = self.parse_primary()?;
+        if self.eat(&['^']).is_some() {
+            let exponent = self.parse_unary()?;
+            return Ok(Expr::chain(base, vec![('^', exponent)]));
+        }
+        Ok(base)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ExprError> {
+        let Some(token) = self.next() else {
+            return Err(ExprError::new("式が途中で終わっています", self.end_span()));
+        };
+        match token.kind {
+            TokenKind::Number => match self.mode.literal(&token.text) {
+                Some(n) => Ok(Expr {
+                    node: Node::Number(n),
+                    span: token.span,
+                }),
+                None => Err(ExprError::new("数が大きすぎます", token.span)),
+            },
+            TokenKind::Symbol('(') => {
+                let inner = self.parse_expr()?;
+                self.expect_close(&token.span)?;
+                Ok(Expr {
+                    node: inner.node,
+                    span: token.span.start..self.prev_end(),
+                })
+            }
+            TokenKind::Ident(name) => self.parse_name(&name, token.span),
+            TokenKind::Symbol(_) => Err(ExprError::new(
+                format!("数・関数・括弧が必要です（'{}' があります）", token.text),
+                token.span,
+            )),
+        }
+    }
+
+    /// `(` に対応する `)` を読む
+    fn expect_close(&mut self, open: &Range<usize>) -> Result<(), ExprError> {
+        if self.eat(&[')']).is_some() {
+            return Ok(());
+        }
+        match self.peek() {
+            None => Err(ExprError::new("対応する ')' がありません", open.clone())),
+            Some(token) => Err(ExprError::new(
+                format!("')' が必要です（'{}' があります）", token.text),
+                token.span.clone(),
+            )),
+        }
+    }
+
+    fn parse_name(&mut self, name: &str, span: Range<usize>) -> Result<Expr, ExprError> {
+        let lower = name.to_lowercase();
+        if let Some((_, value)) = CONSTANTS.iter().find(|(n, _)| *n == lower) {
+            return Ok(Expr {
+                node: Node::Number(Number::Approx(*value)),
+                span,
+            });
+        }
+        let Some(function) = FUNCTIONS.iter().find(|f| f.name == lower) else {
+            return Err(ExprError::new(
+                format!("不明な名前です: {}（{}）", name, known_names()),
+                span,
+            ));
+        };
+
+        let open = match self.peek() {
+            Some(token) if token.kind == TokenKind::Symbol('(') => token.span.clone(),
+            _ => {
+                return Err(ExprError::new(
+                    format!("{} の後に '(' が必要です", name),
+                    span,
+                ));
+            }
+        };
+        self.pos += 1;
+        let mut args = Vec::new();
+        if self.eat(&[')']).is_none() {
+            loop {
+                args.push(self.parse_expr()?);
+                if self.eat(&[',']).is_none() {
+                    self.expect_close(&open)?;
+                    break;
+                }
+            }
+        }
+
+        let span = span.start..self.prev_end();
+        if args.len() < function.min_args || args.len() > function.max_args {
+            return Err(ExprError::new(
+                format!(
+                    "{} の引数は {}です（実際: {} 個）",
+                    function.name,
+                    function.arity(),
+                    args.len()
+                ),
+                span,
+            ));
+        }
+        Ok(Expr {
+            node: Node::Call(function, args),
+            span,
+        })
+    }
+}
+
+/// 数式を f64 で計算する
+///
+/// 四則演算と `%`（剰余）・`^`（べき乗、`**` も可）、括弧、単項マイナス、
+/// `sqrt` `sin` `log` などの関数と `pi` `e` `tau` が使える。三角関数はラジアン。
+/// 誤りは入力のどこが悪いかを `ExprError::span` で返す。
+pub fn evaluate(input: &str) -> Result<f64, ExprError> {
+    evaluate_with(input, NumberMode::Float).map(|n| n.to_f64())
+}
+
+/// 数式を指定のモードで計算する
+///
+/// 厳密モードでは数を 10 進のまま有理数として扱い、`1/3 + 1/6` は 1/2 になる。
+/// 定数や三角関数など有理数にならないものを含むと結果は近似値になる。
+pub fn evaluate_with(input: &str, mode: NumberMode) -> Result<Number, ExprError> {
+    let chars: Vec<char> = input.chars().collect();
+    let tokens = tokenize(&chars)?;
+    if tokens.is_empty() {
+        return Err(ExprError::new("式が空です", 0..1));
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: chars.len(),
+        depth: 0,
+        mode,
+    };
+    let expr = parser.parse_expr()?;
+    if let Some(token) = parser.peek() {
+        let message = match token.kind {
+            TokenKind::Symbol(')') => "対応する '(' がありません".to_string(),
+            _ => format!("演算子が必要です（'{}' があります）", token.text),
+        };
+        return Err(ExprError::new(message, token.span.clone()));
+    }
+
+    // -0 は 0 として返す（浮動小数点のパターンは -0.0 にも一致する）
+    Ok(match expr.eval()? {
+        Number::Approx(0.0) => Number::Approx(0.0),
+        value => value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> f64 {
+        evaluate(input).unwrap_or_else(|e| panic!("{}", e.render(input)))
+    }
+
+    fn error(input: &str) -> ExprError {
+        evaluate(input).unwrap_err()
+    }
+
+    fn assert_close(input: &str, expected: f64) {
+        let actual = eval(input);
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{input} = {actual}（期待値 {expected}）"
+        );
+    }
+
+    #[test]
+    fn test_precedence_and_parentheses() {
+        assert_eq!(eval("(3+4)*2-1/5"), 13.8);
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("10 - 4 - 3"), 3.0);
+        assert_eq!(eval("2 * 3 % 4"), 2.0);
+        assert_eq!(eval("((2))"), 2.0);
+    }
+
+    #[test]
+    fn test_power_is_right_associative_and_binds_tighter_than_minus() {
+        assert_eq!(eval("2^3^2"), 512.0);
+        assert_eq!(eval("2**10"), 1024.0);
+        assert_eq!(eval("-2^2"), -4.0);
+        assert_eq!(eval("(-2)^2"), 4.0);
+        assert_eq!(eval("2^-1"), 0.5);
+        assert_eq!(eval("4^0.5"), 2.0);
+    }
+
+    #[test]
+    fn test_unary_signs() {
+        assert_eq!(eval("-3"), -3.0);
+        assert_eq!(eval("--3"), 3.0);
+        assert_eq!(eval("+3"), 3.0);
+        assert_eq!(eval("5 - -3"), 8.0);
+        assert_eq!(eval("-(2 + 3) * 2"), -10.0);
+        assert_eq!(eval("-0"), 0.0);
+        assert!(eval("-0").is_sign_positive());
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(eval("1.5 + .5"), 2.0);
+        assert_eq!(eval("1e3"), 1000.0);
+        assert_eq!(eval("2.5E-1"), 0.25);
+        assert_eq!(eval("2e+1"), 20.0);
+        // 数字が続かない e は指数ではなく定数の e（掛け算は省略できない）
+        assert_eq!(error("2e").span, 1..2);
+        assert_close("2*e", 2.0 * std::f64::consts::E);
+        assert_eq!(eval("6 × 7 ÷ 2"), 21.0);
+    }
+
+    #[test]
+    fn test_functions_and_constants() {
+        assert_eq!(eval("sqrt(16)"), 4.0);
+        assert_eq!(eval("abs(-3) + max(1, 5, 2) - min(4, 2)"), 6.0);
+        assert_close("sin(pi / 6)", 0.5);
+        assert_close("cos(0) + tan(0)", 1.0);
+        assert_close("asin(1) * 2", std::f64::consts::PI);
+        assert_close("ln(e^2)", 2.0);
+        assert_close("log(1000)", 3.0);
+        assert_close("log(8, 2)", 3.0);
+        assert_close("log2(1024)", 10.0);
+        assert_close("exp(0) + tau / pi", 3.0);
+        assert_eq!(eval("floor(2.7) + ceil(2.1) + round(2.5)"), 8.0);
+        assert_close("SQRT(9) + PI - pi", 3.0);
+        assert_eq!(eval("sqrt(sqrt(16))"), 2.0);
+    }
+
+    #[test]
+    fn test_syntax_errors_point_at_the_problem() {
+        let e = error("(3+*2");
+        assert_eq!(e.span, 3..4);
+        assert_eq!(e.message, "数・関数・括弧が必要です（'*' があります）");
+
+        let e = error("(3+4");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (0..1, "対応する ')' がありません")
+        );
+
+        let e = error("3+4)");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (3..4, "対応する '(' がありません")
+        );
+
+        let e = error("3 4");
+        assert_eq!(e.span, 2..3);
+        assert_eq!(e.message, "演算子が必要です（'4' があります）");
+
+        let e = error("1 +");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (3..4, "式が途中で終わっています")
+        );
+
+        assert_eq!(error("   ").message, "式が空です");
+        assert_eq!(error("").message, "式が空です");
+    }
+
+    #[test]
+    fn test_lexical_errors() {
+        let e = error("2 $ 3");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (2..3, "使えない文字です: '$'")
+        );
+
+        let e = error("1.2.3 + 1");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (0..5, "数として読めません: 1.2.3")
+        );
+
+        assert_eq!(error("1e999").message, "数が大きすぎます");
+
+        // 位置は文字単位（全角文字の後ろでもずれない）
+        assert_eq!(error("3×$").span, 2..3);
+    }
+
+    #[test]
+    fn test_name_errors() {
+        let e = error("1 + foo(2)");
+        assert_eq!(e.span, 4..7);
+        assert!(
+            e.message
+                .starts_with("不明な名前です: foo（関数: sqrt, abs"),
+            "{}",
+            e.message
+        );
+        assert!(e.message.ends_with("定数: pi, e, tau）"), "{}", e.message);
+
+        let e = error("sqrt 4");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (0..4, "sqrt の後に '(' が必要です")
+        );
+
+        let e = error("sqrt(1, 2)");
+        assert_eq!(e.span, 0..10);
+        assert_eq!(e.message, "sqrt の引数は 1 個です（実際: 2 個）");
+
+        assert_eq!(
+            error("log()").message,
+            "log の引数は 1〜2 個です（実際: 0 個）"
+        );
+        assert_eq!(
+            error("max()").message,
+            "max の引数は 1 個以上です（実際: 0 個）"
+        );
+
+        let e = error("max(1, 2");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (3..4, "対応する ')' がありません")
+        );
+        let e = error("max(1 2)");
+        assert_eq!(
+            (e.span, e.message.as_str()),
+            (6..7, "')' が必要です（'2' があります）")
+        );
+    }
+
+    #[test]
+    fn test_evaluation_errors() {
+        let e = error("10 / (5 - 5)");
+        assert_eq!((e.span, e.message.as_str()), (5..12, "0 で割っています"));
+        assert_eq!(error("7 % 0").message, "0 で割っています");
+
+        let e = error("1 + sqrt(-4)");
+        assert_eq!(e.span, 4..12);
+        assert_eq!(e.message, "sqrt(-4) は計算できません（定義域の外です）");
+        assert_eq!(error("ln(0)").span, 0..5);
+
+        let e = error("(-8)^0.5");
+        assert_eq!(e.span, 0..8);
+        assert!(
+            e.message.starts_with("結果が実数になりません"),
+            "{}",
+            e.message
+        );
+
+        assert_eq!(error("10^400").message, "結果が大きすぎます");
+    }
+
+    #[test]
+    fn test_negative_and_fractional_powers() {
+        assert_close("(-8)^(1/3)", -2.0);
+        assert_close("(-32)^(2/5)", 4.0);
+        assert_eq!(eval("4^-0.5"), 0.5);
+    }
+
+    #[test]
+    fn test_exact_mode() {
+        let mode = NumberMode::Exact { precision: 20 };
+        let exact = |input: &str| mode.format(&evaluate_with(input, mode).unwrap());
+
+        assert_eq!(exact("0.1 + 0.2"), "0.3");
+        assert_eq!(exact("1/3 + 1/6"), "0.5");
+        assert_eq!(exact("2/3"), "2/3 ≈ 0.66666666666666666667");
+        assert_eq!(exact("2^100 + 1"), "1267650600228229401496703205377");
+        assert_eq!(
+            exact("123456789012345678901234567890 * 10"),
+            "1234567890123456789012345678900"
+        );
+        assert_eq!(exact("(-8)^(1/3) + sqrt(9/4) + abs(-1/2)"), "0");
+        assert_eq!(
+            exact("round(5/2) + floor(-1/3) + max(1/3, 1/4)"),
+            "7/3 ≈ 2.33333333333333333333"
+        );
+        // 有理数にならないものは近似値
+        assert_eq!(exact("sqrt(2)"), "≈ 1.4142135623730951");
+        assert_eq!(exact("pi * 2"), "≈ 6.283185307179586");
+
+        let e = evaluate_with("1 / (1/3 - 1/3)", mode).unwrap_err();
+        assert_eq!((e.span, e.message.as_str()), (4..15, "0 で割っています"));
+        assert_eq!(
+            evaluate_with("10^10000000", mode).unwrap_err().message,
+            "結果が大きすぎます"
+        );
+        // 浮動小数点なら桁あふれする数も読める
+        assert!(evaluate_with("1e400 / 1e399", mode).is_ok());
+    }
+
+    #[test]
+    fn test_long_flat_chains_do_not_overflow_the_stack() {
+        let sum = vec!["1"; 20_000].join("+");
+        assert_eq!(eval(&sum), 20_000.0);
+        let product = vec!["1"; 20_000].join("*");
+        assert_eq!(eval(&product), 1.0);
+
+        let mode = NumberMode::Exact { precision: 20 };
+        let thirds = vec!["1/3"; 3_000].join(" + ");
+        assert_eq!(mode.format(&evaluate_with(&thirds, mode).unwrap()), "1000");
+    }
+
+    #[test]
+    fn test_deep_nesting_is_rejected() {
+        let deep = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(error(&deep).message, "式の入れ子が深すぎます");
+        assert_eq!(error(&"-".repeat(100)).message, "式の入れ子が深すぎます");
+
+        let ok = format!("{}1{}", "(".repeat(20), ")".repeat(20));
+        assert_eq!(eval(&ok), 1.0);
+    }
+
+    #[test]
+    fn test_render_marks_the_span() {
+        let input = "2 * (3 + )";
+        let rendered = error(input).render(input);
+        assert_eq!(
+            rendered,
+            "2 * (3 + )\n         ^\n10 文字目: 数・関数・括弧が必要です（')' があります）"
+        );
+    }
+}
@@ -0,0 +1,640 @@
+use std::fmt::{Display, Formatter};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+
+/// 厳密モードで小数を表示する既定の桁数
+pub const DEFAULT_PRECISION: usize = 20;
+
+/// 厳密モードで表示できる桁数の上限（`exact:1000000000` で桁を作り続けないように）
+pub const MAX_PRECISION: usize = 1000;
+
+/// 厳密モードで扱う数の大きさの上限（ビット数、約 31 万桁）
+const MAX_BITS: u64 = 1 << 20;
+
+/// 指数表記の指数の上限（`1e999999999` で桁を使い切らないように）
+const MAX_DECIMAL_EXPONENT: u64 = 100_000;
+
+/// 厳密に求める累乗根の次数の上限（これより大きい分母の指数は近似する）
+const MAX_EXACT_ROOT: u32 = 1000;
+
+/// 浮動小数点で負の数の分数乗を実数として求めるときに探す奇数の分母の上限
+const MAX_ODD_ROOT: i64 = 99;
+
+/// 計算のモード（計算アプリの会話ごとに選ぶ）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberMode {
+    /// f64 で計算する（従来どおり）
+    Float,
+    /// 多倍長の有理数で誤差なく計算し、割り切れない小数は `precision` 桁で表示する
+    Exact { precision: usize },
+}
+
+impl NumberMode {
+    /// CALC_NUMBER_MODE（float / exact、既定 float）と CALC_PRECISION（既定 20、上限 1000）から
+    pub fn from_env() -> Self {
+        let precision = match std::env::var("CALC_PRECISION") {
+            Ok(text) => parse_precision(&text).unwrap_or_else(|e| {
+                eprintln!("⚠️ {}（既定の {} 桁を使います）", e, DEFAULT_PRECISION);
+                DEFAULT_PRECISION
+            }),
+            Err(_) => DEFAULT_PRECISION,
+        };
+        match std::env::var("CALC_NUMBER_MODE")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "float" => Self::Float,
+            "exact" => Self::Exact { precision },
+            other => {
+                eprintln!(
+                    "⚠️ 不明な CALC_NUMBER_MODE です: {}（float / exact、既定の float を使います）",
+                    other
+                );
+                Self::Float
+            }
+        }
+    }
+
+    /// `float` / `exact` / `exact:<桁数>` から（`calc --mode` や `/mode`、保存した会話の設定）
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "計算モードは float / exact / exact:<桁数> で指定してください: {}",
+                text
+            )
+        };
+        let lower = text.trim().to_lowercase();
+        match lower.split_once(':') {
+            None if lower == "float" => Ok(Self::Float),
+            None if lower == "exact" => Ok(Self::Exact {
+                precision: DEFAULT_PRECISION,
+            }),
+            Some(("exact", digits)) => Ok(Self::Exact {
+                precision: parse_precision(digits)?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// 起動時に表示する説明
+    pub fn label(&self) -> String {
+        match self {
+            Self::Float => "浮動小数点 (f64)".to_string(),
+            Self::Exact { precision } => {
+                format!(
+                    "厳密（有理数、割り切れない小数は {} 桁まで表示）",
+                    precision
+                )
+            }
+        }
+    }
+
+    /// 式の中の数（`0.1` や `2e-3`）をこのモードの数にする（大きすぎれば None）
+    pub fn literal(&self, text: &str) -> Option<Number> {
+        match self {
+            Self::Float => text
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(Number::Approx),
+            Self::Exact { .. } => parse_decimal(text).map(Number::Exact),
+        }
+    }
+
+    /// ツールの引数をこのモードの数にする（大きすぎればエラー）
+    ///
+    /// JSON に書かれた表記のまま読むので、厳密モードでは `0.1` や桁の多い整数も誤差なく受け取る。
+    pub fn argument(&self, arg: &NumberArg) -> Result<Number, NumberError> {
+        self.literal(arg.as_str()).ok_or(NumberError::TooLarge)
+    }
+
+    /// 計算結果の表示（モデルへ返す文字列）
+    ///
+    /// 厳密モードでは割り切れる小数はそのまま（`0.3`）、割り切れなければ
+    /// 分数と近似値（`1/3 ≈ 0.333…`）、近似でしか求まらなかった値は `≈` を付ける。
+    pub fn format(&self, value: &Number) -> String {
+        match (self, value) {
+            (Self::Exact { precision }, Number::Exact(r)) => format_rational(r, *precision),
+            (Self::Exact { .. }, Number::Approx(f)) => format!("≈ {}", f),
+            (Self::Float, _) => value.to_f64().to_string(),
+        }
+    }
+}
+
+/// `parse` で読める表記（保存した会話に残す）
+impl Display for NumberMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Float => write!(f, "float"),
+            Self::Exact { precision } => write!(f, "exact:{}", precision),
+        }
+    }
+}
+
+/// ツールの引数で受け取る数（JSON に書かれた表記のまま持つ）
+///
+/// serde_json は数を読むと f64 にしてしまうので、元の表記を残して
+/// 厳密モードで誤差なく扱えるようにする。`Value` を経由せず文字列から読むときだけ使える。
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberArg(String);
+
+impl NumberArg {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// f64 としての値（大きすぎれば inf）
+    pub fn to_f64(&self) -> f64 {
+        self.0.parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl<'de> Deserialize<'de> for NumberArg {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = Box::<RawValue>::deserialize(deserializer)?;
+        let text = raw.get();
+        // JSON の数は - か数字で始まる（文字列・真偽値・null は受け付けない）
+        if !text.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+            return Err(serde::de::Error::custom(format!(
+                "数ではありません: {}",
+                text
+            )));
+        }
+        Ok(Self(text.to_string()))
+    }
+}
+
+/// 計算できなかった理由
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberError {
+    DivisionByZero,
+    /// 負の数の平方根など
+    NotReal,
+    TooLarge,
+}
+
+impl Display for NumberError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DivisionByZero => write!(f, "0 で割っています"),
+            Self::NotReal => write!(f, "結果が実数になりません"),
+            Self::TooLarge => write!(f, "結果が大きすぎます"),
+        }
+    }
+}
+
+/// 計算に使う数（厳密な有理数か、f64 の近似値）
+///
+/// 厳密な数どうしの計算は厳密なまま、どちらかが近似値なら f64 で計算する。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Exact(BigRational),
+    Approx(f64),
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(r) => write!(f, "{}", r),
+            Self::Approx(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl Number {
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Self::Exact(r) => r.to_f64().unwrap_or(f64::NAN),
+            Self::Approx(x) => *x,
+        }
+    }
+
+    pub fn as_exact(&self) -> Option<&BigRational> {
+        match self {
+            Self::Exact(r) => Some(r),
+            Self::Approx(_) => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Self::Exact(r) => r.is_zero(),
+            Self::Approx(x) => *x == 0.0,
+        }
+    }
+
+    pub fn neg(&self) -> Number {
+        match self {
+            Self::Exact(r) => Self::Exact(-r),
+            Self::Approx(x) => Self::Approx(-x),
+        }
+    }
+
+    /// 二項演算（`+ - * / % ^`）
+    pub fn binary(&self, op: char, rhs: &Number) -> Result<Number, NumberError> {
+        if matches!(op, '/' | '%') && rhs.is_zero() {
+            return Err(NumberError::DivisionByZero);
+        }
+        if op == '^' {
+            return self.pow(rhs);
+        }
+        let value = match (self, rhs) {
+            (Self::Exact(a), Self::Exact(b)) => {
+                return Ok(Self::Exact(match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    _ => a % b,
+                }));
+            }
+            _ => {
+                let (a, b) = (self.to_f64(), rhs.to_f64());
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    _ => a % b,
+                }
+            }
+        };
+        approx(value)
+    }
+
+    /// 累乗（負の指数は逆数、分数の指数は累乗根）
+    ///
+    /// 厳密な数どうしで根が割り切れれば厳密な結果（`(-8)^(1/3)` = -2、`(4/9)^(-1/2)` = 3/2）、
+    /// 割り切れなければ近似値を返す。負の数の偶数乗根は `NotReal`。
+    pub fn pow(&self, exponent: &Number) -> Result<Number, NumberError> {
+        if let (Self::Exact(base), Self::Exact(exp)) = (self, exponent)
+            && let Some(result) = pow_exact(base, exp)?
+        {
+            return Ok(Self::Exact(result));
+        }
+        approx(pow_f64(self.to_f64(), exponent.to_f64()))
+    }
+}
+
+/// f64 の結果を確かめて Number にする
+fn approx(value: f64) -> Result<Number, NumberError> {
+    if value.is_nan() {
+        Err(NumberError::NotReal)
+    } else if value.is_infinite() {
+        Err(NumberError::TooLarge)
+    } else {
+        Ok(Number::Approx(value))
+    }
+}
+
+/// f64 の累乗（負の数の奇数乗根は実数として求める: `(-8)^(1/3)` = -2）
+pub fn pow_f64(base: f64, exponent: f64) -> f64 {
+    if base < 0.0 && exponent.fract() != 0.0 {
+        // 指数が p/q（q は奇数）とみなせるなら、符号は p の偶奇で決まる
+        let numerator = (1..=MAX_ODD_ROOT).step_by(2).find_map(|q| {
+            let p = exponent * q as f64;
+            ((p - p.round()).abs() < 1e-9).then(|| p.round() as i64)
+        });
+        return match numerator {
+            Some(p) if p % 2 == 0 => (-base).powf(exponent),
+            Some(_) => -(-base).powf(exponent),
+            None => f64::NAN,
+        };
+    }
+    base.powf(exponent)
+}
+
+/// 厳密な累乗（割り切れない根など、近似に任せるときは None）
+fn pow_exact(base: &BigRational, exp: &BigRational) -> Result<Option<BigRational>, NumberError> {
+    let (p, q) = (exp.numer(), exp.denom());
+    if base.is_zero() {
+        return match p.sign() {
+            num_bigint::Sign::Minus => Err(NumberError::DivisionByZero),
+            num_bigint::Sign::NoSign => Ok(Some(BigRational::one())),
+            num_bigint::Sign::Plus => Ok(Some(BigRational::zero())),
+        };
+    }
+    let Some(q) = q.to_u32().filter(|q| *q <= MAX_EXACT_ROOT) else {
+        return Ok(None);
+    };
+    let negative = base.is_negative();
+    if negative && q % 2 == 0 {
+        return Err(NumberError::NotReal);
+    }
+    let odd_power = p.bit(0);
+
+    // ±1 は指数がどれだけ大きくても ±1
+    let magnitude = if base.abs().is_one() {
+        BigRational::one()
+    } else {
+        // p/q も base も既約なので、base^(p/q) が有理数になるのは base の q 乗根が有理数のときだけ。
+        // 先に根を取れば、途中の数が結果より大きくなることはない
+        let Some(root) = exact_root(&base.abs(), q) else {
+            return Ok(None);
+        };
+        let p_abs = p.abs().to_u32().ok_or(NumberError::TooLarge)?;
+        let bits = (root.numer().bits() + root.denom().bits()) * u64::from(p_abs);
+        if bits > MAX_BITS {
+            return Err(NumberError::TooLarge);
+        }
+        let powered = BigRational::new(root.numer().pow(p_abs), root.denom().pow(p_abs));
+        if p.is_negative() {
+            powered.recip()
+        } else {
+            powered
+        }
+    };
+    Ok(Some(if negative && odd_power {
+        -magnitude
+    } else {
+        magnitude
+    }))
+}
+
+/// 0 以上の有理数の n 乗根が有理数になるならその値（`exact_root(9/4, 2)` = 3/2）
+pub fn exact_root(value: &BigRational, n: u32) -> Option<BigRational> {
+    if value.is_negative() || n == 0 {
+        return None;
+    }
+    let (numer, denom) = (value.numer().nth_root(n), value.denom().nth_root(n));
+    (&numer.pow(n) == value.numer() && &denom.pow(n) == value.denom())
+        .then(|| BigRational::new(numer, denom))
+}
+
+/// `1.25` `-3` `2e-3` のような 10 進表記を誤差なく有理数にする
+///
+/// 形式が違うか、指数が大きすぎるときは None。
+pub fn parse_decimal(text: &str) -> Option<BigRational> {
+    let text = text.trim();
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (negative, mantissa) = match mantissa.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let scale = exponent.checked_sub(frac.len() as i64)?;
+    if scale.unsigned_abs() > MAX_DECIMAL_EXPONENT {
+        return None;
+    }
+    let numer: BigInt = digits.parse().ok()?;
+    let power = BigInt::from(10).pow(scale.unsigned_abs() as u32);
+    let value = if scale >= 0 {
+        BigRational::from_integer(numer * power)
+    } else {
+        BigRational::new(numer, power)
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// 表示桁数（0〜MAX_PRECISION）を読む
+fn parse_precision(text: &str) -> Result<usize, String> {
+    let invalid = || {
+        format!(
+            "桁数は 0〜{} の整数で指定してください: {}",
+            MAX_PRECISION,
+            text.trim()
+        )
+    };
+    let precision: usize = text.trim().parse().map_err(|_| invalid())?;
+    if precision > MAX_PRECISION {
+        return Err(invalid());
+    }
+    Ok(precision)
+}
+
+/// 小数点以下 `precision` 桁（MAX_PRECISION まで）に四捨五入した 10 進表記（末尾の 0 は省く）
+fn to_decimal(value: &BigRational, precision: usize) -> String {
+    let precision = precision.min(MAX_PRECISION);
+    let exponent = u32::try_from(precision).unwrap_or(u32::MAX);
+    let scale = BigRational::from_integer(BigInt::from(10).pow(exponent));
+    let scaled = (value.abs() * scale).round().to_integer();
+    let digits = format!("{:0>width$}", scaled.to_string(), width = precision + 1);
+    let (int, frac) = digits.split_at(digits.len() - precision);
+    let frac = frac.trim_end_matches('0');
+    let sign = if value.is_negative() && !scaled.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    if frac.is_empty() {
+        format!("{}{}", sign, int)
+    } else {
+        format!("{}{}.{}", sign, int, frac)
+    }
+}
+
+fn format_rational(value: &BigRational, precision: usize) -> String {
+    if value.is_integer() {
+        return value.numer().to_string();
+    }
+    let decimal = to_decimal(value, precision);
+    if parse_decimal(&decimal).as_ref() == Some(value) {
+        decimal
+    } else {
+        format!("{} ≈ {}", value, decimal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXACT: NumberMode = NumberMode::Exact { precision: 20 };
+
+    fn exact(text: &str) -> Number {
+        EXACT.literal(text).unwrap()
+    }
+
+    fn ratio(numer: i64, denom: i64) -> Number {
+        Number::Exact(BigRational::new(numer.into(), denom.into()))
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(exact("0.1"), ratio(1, 10));
+        assert_eq!(exact("-1.25"), ratio(-5, 4));
+        assert_eq!(exact(".5"), ratio(1, 2));
+        assert_eq!(exact("2e-3"), ratio(1, 500));
+        assert_eq!(exact("1.5E2"), ratio(150, 1));
+        assert_eq!(parse_decimal("1.2.3"), None);
+        assert_eq!(parse_decimal("."), None);
+        assert_eq!(parse_decimal("1e999999"), None);
+    }
+
+    #[test]
+    fn test_parse_mode_round_trips() {
+        assert_eq!(NumberMode::parse("float"), Ok(NumberMode::Float));
+        assert_eq!(
+            NumberMode::parse(" Exact "),
+            Ok(NumberMode::Exact {
+                precision: DEFAULT_PRECISION
+            })
+        );
+        assert_eq!(
+            NumberMode::parse("exact:30"),
+            Ok(NumberMode::Exact { precision: 30 })
+        );
+        assert!(NumberMode::parse("exact:many").is_err());
+        assert!(NumberMode::parse("float:3").is_err());
+        assert!(NumberMode::parse("decimal").is_err());
+        assert_eq!(
+            NumberMode::parse("exact:1000"),
+            Ok(NumberMode::Exact { precision: 1000 })
+        );
+        assert!(NumberMode::parse("exact:1001").is_err());
+        assert!(NumberMode::parse("exact:99999999999999999999").is_err());
+        for mode in [NumberMode::Float, EXACT] {
+            assert_eq!(NumberMode::parse(&mode.to_string()), Ok(mode));
+        }
+    }
+
+    #[test]
+    fn test_to_decimal_caps_precision() {
+        let third = BigRational::new(BigInt::from(1), BigInt::from(3));
+        let decimal = to_decimal(&third, usize::MAX);
+        assert_eq!(decimal.len(), "0.".len() + MAX_PRECISION);
+    }
+
+    #[test]
+    fn test_number_args_keep_the_written_digits() {
+        #[derive(Deserialize)]
+        struct Args {
+            a: NumberArg,
+        }
+        let read = |json: &str| serde_json::from_str::<Args>(json).map(|args| args.a);
+
+        let big = read(r#"{"a": 12345678901234567890123}"#).unwrap();
+        assert_eq!(big.as_str(), "12345678901234567890123");
+        assert_eq!(
+            EXACT.argument(&big).unwrap(),
+            Number::Exact(BigRational::from_integer(
+                "12345678901234567890123".parse().unwrap()
+            ))
+        );
+        let tiny = read(r#"{"a": 0.10000000000000000001}"#).unwrap();
+        assert_eq!(
+            EXACT.format(&EXACT.argument(&tiny).unwrap()),
+            "0.10000000000000000001"
+        );
+        assert_eq!(tiny.to_f64(), 0.1);
+
+        let huge = read(r#"{"a": 1e400}"#).unwrap();
+        assert_eq!(
+            NumberMode::Float.argument(&huge),
+            Err(NumberError::TooLarge)
+        );
+        assert!(read(r#"{"a": "1"}"#).is_err());
+        assert!(read(r#"{"a": null}"#).is_err());
+    }
+
+    #[test]
+    fn test_exact_arithmetic_has_no_float_artifacts() {
+        let sum = exact("0.1").binary('+', &exact("0.2")).unwrap();
+        assert_eq!(sum, ratio(3, 10));
+        assert_eq!(EXACT.format(&sum), "0.3");
+
+        let float = NumberMode::Float;
+        let (a, b) = (float.literal("0.1").unwrap(), float.literal("0.2").unwrap());
+        let sum = a.binary('+', &b).unwrap();
+        assert_eq!(float.format(&sum), "0.30000000000000004");
+
+        assert_eq!(exact("7").binary('%', &exact("3")).unwrap(), ratio(1, 1));
+        assert_eq!(
+            exact("1").binary('/', &exact("0")),
+            Err(NumberError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_big_powers_are_exact() {
+        let value = exact("2").pow(&exact("100")).unwrap();
+        assert_eq!(EXACT.format(&value), "1267650600228229401496703205376");
+        // f64 では桁が落ちる
+        let float = Number::Approx(2.0).pow(&Number::Approx(100.0)).unwrap();
+        assert_eq!(float.to_f64(), 1.2676506002282294e30);
+
+        assert_eq!(
+            exact("10").pow(&exact("10000000")),
+            Err(NumberError::TooLarge)
+        );
+        assert_eq!(exact("-1").pow(&exact("1000000001")).unwrap(), ratio(-1, 1));
+    }
+
+    #[test]
+    fn test_negative_and_fractional_exponents() {
+        assert_eq!(exact("2").pow(&exact("-2")).unwrap(), ratio(1, 4));
+        assert_eq!(exact("0.25").pow(&exact("0.5")).unwrap(), ratio(1, 2));
+        assert_eq!(ratio(4, 9).pow(&ratio(-1, 2)).unwrap(), ratio(3, 2));
+        assert_eq!(exact("-8").pow(&ratio(1, 3)).unwrap(), ratio(-2, 1));
+        assert_eq!(exact("-8").pow(&ratio(-2, 3)).unwrap(), ratio(1, 4));
+        assert_eq!(exact("-4").pow(&exact("0.5")), Err(NumberError::NotReal));
+        assert_eq!(
+            exact("0").pow(&exact("-1")),
+            Err(NumberError::DivisionByZero)
+        );
+
+        // 割り切れない根は近似値
+        let root = exact("2").pow(&exact("0.5")).unwrap();
+        assert_eq!(root, Number::Approx(std::f64::consts::SQRT_2));
+        assert_eq!(EXACT.format(&root), "≈ 1.4142135623730951");
+
+        // f64 でも負の数の奇数乗根は実数になる
+        assert_eq!(pow_f64(-8.0, 1.0 / 3.0), -2.0);
+        assert!(pow_f64(-8.0, 0.5).is_nan());
+        assert_eq!(pow_f64(2.0, -1.0), 0.5);
+    }
+
+    #[test]
+    fn test_fractional_exponents_with_large_numerators_stay_fast() {
+        let start = std::time::Instant::now();
+        // 根が割り切れないものは、大きな累乗を作らずに近似へ回す
+        let value = exact("3").pow(&ratio(99_999, 1000)).unwrap();
+        assert!(matches!(value, Number::Approx(_)));
+        assert_eq!(
+            exact("3").pow(&ratio(999_999, 1000)),
+            Err(NumberError::TooLarge)
+        );
+        assert_eq!(
+            exact("3").pow(&exact("9999.999")),
+            Err(NumberError::TooLarge)
+        );
+        // 割り切れる根は根を取ってから累乗する
+        assert_eq!(
+            exact("8").pow(&ratio(1_000_001, 3)),
+            Err(NumberError::TooLarge)
+        );
+        assert_eq!(exact("8").pow(&ratio(40, 3)).unwrap(), ratio(1 << 40, 1));
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_format_rational_with_precision() {
+        assert_eq!(EXACT.format(&ratio(1, 3)), "1/3 ≈ 0.33333333333333333333");
+        assert_eq!(
+            EXACT.format(&ratio(-2, 3)),
+            "-2/3 ≈ -0.66666666666666666667"
+        );
+        assert_eq!(EXACT.format(&ratio(1, 8)), "0.125");
+        let short = NumberMode::Exact { precision: 2 };
+        assert_eq!(short.format(&ratio(1, 8)), "1/8 ≈ 0.13");
+        assert_eq!(short.format(&ratio(1, 1000)), "1/1000 ≈ 0");
+        assert_eq!(
+            NumberMode::Exact { precision: 0 }.format(&ratio(5, 2)),
+            "5/2 ≈ 3"
+        );
+    }
+}
@@ -1,213 +1,459 @@
-use std::collections::HashMap;
-use std::env;
-use std::io;
+use std::future::{Ready, ready};
+use std::io::{self, Write};
+use std::sync::Arc;
 
 use dotenv::dotenv;
 
-use crate::function_call::api::send_chat_completion; // 以前の build_* 系は使わず自前で messages ベクタを構築
-use crate::function_call::tools::NumFunc; // execute_first_tool_call は単発用なのでループ版では不使用
-use crate::function_call::types::{
-    ChatResponse, JsonSchemaObject, SumArgs, ToolDefinition, ToolFunctionDefinition,
-};
-use serde_json::{Value, json};
+use crate::function_call::agent::{Agent, AgentConfig, AgentEvent, StopReason};
+use crate::function_call::api::{ChatTransport, EndpointConfig};
+use crate::function_call::expr;
+use crate::function_call::number::{Number, NumberArg, NumberError, NumberMode};
+use crate::function_call::registry::ToolRegistry;
+use crate::function_call::replay::transport_from_env;
+use crate::function_call::schema::ToolArgs;
+use crate::function_call::session::SessionStore;
+use crate::function_call::types::{ChatMessage, ExprArgs, PowArgs, SumArgs};
+use crate::function_call::usage::UsageTracker;
 
-pub fn calc_sum(a: f64, b: f64) -> f64 {
-    a + b
+const SYSTEM_PROMPT: &str = "あなたは計算を手伝う厳密モードのアシスタントです。ルール: (1) 不要な 0 との加算や 1 との乗算は行わない。 (2) 必要なら足し算は calc_sum、掛け算は calc_times、べき乗は calc_pow を使う。 (3) べき乗が直接表現できる場合は calc_pow で 1 回で求める。 (4) 2 つ以上の演算や括弧・関数を含む式は evaluate_expression で 1 回で計算する。 (5) 途中の説明は簡潔に。";
+
+pub fn calc_sum(a: &Number, b: &Number) -> Result<Number, NumberError> {
+    a.binary('+', b)
+}
+pub fn calc_times(a: &Number, b: &Number) -> Result<Number, NumberError> {
+    a.binary('*', b)
+}
+pub fn calc_pow(a: &Number, b: &Number) -> Result<Number, NumberError> {
+    // 負の指数は逆数、小数の指数は累乗根 ((-8)^(1/3) = -2)。実数にならなければエラー
+    a.pow(b)
 }
-pub fn calc_times(a: f64, b: f64) -> f64 {
-    a * b
+
+/// calc_* の型（引数と結果は計算モードの数）
+type NumberOp = fn(&Number, &Number) -> Result<Number, NumberError>;
+
+/// 2 引数の計算をツールのハンドラとして包む（実行内容を表示する）
+fn number_tool<A>(
+    name: &'static str,
+    func: NumberOp,
+    mode: NumberMode,
+) -> impl Fn(A) -> Ready<Result<String, String>> + Send + Sync + 'static
+where
+    A: ToolArgs + Into<(NumberArg, NumberArg)>,
+{
+    move |args: A| {
+        let (a, b) = args.into();
+        let result = match (mode.argument(&a), mode.argument(&b)) {
+            (Ok(a), Ok(b)) => func(&a, &b).map(|value| mode.format(&value)),
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        };
+        let (a, b) = (a.as_str(), b.as_str());
+        match &result {
+            Ok(value) => println!("実行: {name}({a} , {b}) = {value}"),
+            Err(e) => println!("実行: {name}({a} , {b}) → {e}"),
+        }
+        ready(result.map_err(|e| e.to_string()))
+    }
 }
-pub fn calc_pow(a: f64, b: f64) -> f64 {
-    // b は 0 以上の整数として扱う (小数部があれば切り捨て)
-    if b < 0.0 {
-        return f64::NAN;
+
+/// 式をまとめて計算する（誤りは場所を示してモデルに返し、直させる）
+fn evaluate_expression(args: ExprArgs, mode: NumberMode) -> Ready<Result<String, String>> {
+    let result = expr::evaluate_with(&args.expression, mode).map(|value| mode.format(&value));
+    match &result {
+        Ok(value) => println!("実行: evaluate_expression({}) = {}", args.expression, value),
+        Err(e) => println!("実行: evaluate_expression({}) → {}", args.expression, e),
     }
-    let mut exp = b.floor() as u32;
-    let mut result = 1.0;
-    let mut base = a;
-    // 簡単で十分: 2 分累乗 (高速 & 安定)
-    while exp > 0 {
-        if exp & 1 == 1 {
-            result *= base;
+    ready(result.map_err(|e| e.render(&args.expression)))
+}
+
+/// 計算ツール（calc_sum / calc_times / calc_pow / evaluate_expression）を登録した ToolRegistry
+fn build_registry(mode: NumberMode) -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    registry
+        .register(
+            "calc_sum",
+            "2つの数 a と b の合計を計算して返す",
+            number_tool::<SumArgs>("calc_sum", calc_sum, mode),
+        )
+        // 積
+        .register(
+            "calc_times",
+            "2つの数 a と b の積 (掛け算) を計算して返す",
+            number_tool::<SumArgs>("calc_times", calc_times, mode),
+        )
+        // べき乗: a^b, b は負の数や小数も可
+        .register(
+            "calc_pow",
+            "a の b 乗を計算して返す (b が負の数なら逆数、小数なら累乗根)",
+            number_tool::<PowArgs>("calc_pow", calc_pow, mode),
+        )
+        // 式: 括弧や関数を含む計算を 1 回で
+        .register(
+            "evaluate_expression",
+            "数式を計算して返す。+ - * / % ^、括弧、単項マイナス、関数 (sqrt, abs, sin, cos, tan, asin, acos, atan, exp, ln, log, log2, floor, ceil, round, min, max)、定数 (pi, e, tau) が使える。三角関数はラジアン",
+            move |args: ExprArgs| evaluate_expression(args, mode),
+        );
+    registry
+}
+
+/// 計算アプリ用の Agent（ツールの失敗と打ち切りを端末に出す）
+fn build_agent(model: &str, transport: Box<dyn ChatTransport>, mode: NumberMode) -> Agent {
+    let config = AgentConfig {
+        model: model.to_string(),
+        system_prompt: SYSTEM_PROMPT.to_string(),
+        ..Default::default()
+    };
+    Agent::new(config, build_registry(mode), transport).on_event(|event| match event {
+        AgentEvent::ToolFinished {
+            name,
+            result: Err(e),
+        } => eprintln!("ツール呼び出し失敗 tool={name} error={e}"),
+        AgentEvent::MaxIterations { max_iter } => {
+            eprintln!("最大反復 {max_iter} に到達。打ち切り")
+        }
+        AgentEvent::Retrying {
+            attempt,
+            max_retries,
+            wait,
+            error,
+        } => eprintln!(
+            "⏳ {error}。{:.1} 秒後に再試行します ({attempt}/{max_retries})",
+            wait.as_secs_f64()
+        ),
+        _ => {}
+    })
+}
+
+/// 会話に保存する計算モードの設定名
+const MODE_SETTING: &str = "number_mode";
+
+/// `calc` の起動オプション
+#[derive(Debug, Default, PartialEq)]
+struct CalcOptions {
+    resume: Option<String>,
+    mode: Option<NumberMode>,
+}
+
+/// `--resume <id>` と `--mode float|exact|exact:<桁数>` を読む
+fn parse_options(args: &[String]) -> Result<CalcOptions, String> {
+    let mut options = CalcOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("{} の値を指定してください", arg))
+        };
+        match arg.as_str() {
+            "--resume" => options.resume = Some(value()?.clone()),
+            "--mode" => options.mode = Some(NumberMode::parse(value()?)?),
+            _ => return Err(format!("不明な引数: {}", arg)),
         }
-        base *= base;
-        exp >>= 1;
     }
-    result
+    Ok(options)
 }
 
-#[tokio::main]
-pub async fn play() -> Result<(), Box<dyn std::error::Error>> {
+/// 計算アプリの CLI エントリーポイント（`cargo run -- calc [--resume <id>] [--mode exact]`）
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
     dotenv().ok();
-    let api_key = env::var("OPENAI_API_KEY")?;
-
-    println!("足し算したい内容を自然文で入力してください (例: 3と8を足して)");
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let user_text = input.trim();
-
-    // calc_sum 用ツール定義
-    let tool_schema_sum = ToolDefinition {
-        r#type: "function",
-        function: ToolFunctionDefinition {
-            name: "calc_sum",
-            description: "2つの数 a と b の合計を計算して返す",
-            parameters: JsonSchemaObject {
-                r#type: "object",
-                properties: json!({
-                    "a": { "type": "number", "description": "最初の数" },
-                    "b": { "type": "number", "description": "次の数" }
-                }),
-                required: vec!["a", "b"],
-            },
-        },
-    };
-    // calc_times 用ツール定義 (積)
-    let tool_schema_times = ToolDefinition {
-        r#type: "function",
-        function: ToolFunctionDefinition {
-            name: "calc_times",
-            description: "2つの数 a と b の積 (掛け算) を計算して返す",
-            parameters: JsonSchemaObject {
-                r#type: "object",
-                properties: json!({
-                    "a": { "type": "number", "description": "最初の数" },
-                    "b": { "type": "number", "description": "次の数" }
-                }),
-                required: vec!["a", "b"],
-            },
-        },
-    };
+    play_with(options.resume, options.mode).map_err(|e| e.to_string())
+}
 
-    // calc_pow 用ツール定義 (べき乗: a^b, b は非負整数想定)
-    let tool_schema_pow = ToolDefinition {
-        r#type: "function",
-        function: ToolFunctionDefinition {
-            name: "calc_pow",
-            description: "a の b 乗 (b は 0 以上の整数として解釈) を計算して返す",
-            parameters: JsonSchemaObject {
-                r#type: "object",
-                properties: json!({
-                    "a": { "type": "number", "description": "底 (base)" },
-                    "b": { "type": "number", "description": "指数 (0 以上 の整数と見なす)" }
-                }),
-                required: vec!["a", "b"],
-            },
-        },
+/// 環境変数の接続先で起動
+pub fn play() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    play_with(None, None)
+}
+
+/// 計算アプリを起動する（`resume` を指定すると保存済みの会話の続きから）
+///
+/// 計算モードは `mode`、再開した会話に保存されたモード、CALC_NUMBER_MODE の順に決め、
+/// 会話中も `/mode` で切り替えられる。会話とモードは 1 ターンごとに data/sessions/<id>.json に保存する。
+#[tokio::main]
+pub async fn play_with(
+    resume: Option<String>,
+    mode: Option<NumberMode>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = SessionStore::default();
+    let resumed = resume
+        .map(|id| store.resume(&id, "calc", SYSTEM_PROMPT))
+        .transpose()?;
+    let saved_mode = resumed
+        .as_ref()
+        .and_then(|session| session.settings.get(MODE_SETTING))
+        .map(|text| NumberMode::parse(text))
+        .transpose()?;
+    // CALC_NUMBER_MODE=exact で有理数による厳密計算（CALC_PRECISION で表示桁数）
+    let mut mode = mode.or(saved_mode).unwrap_or_else(NumberMode::from_env);
+    println!("🔢 計算モード: {}", mode.label());
+
+    // 接続先は CALC_BASE_URL / CALC_MODEL / CALC_API_KEY（なければ OPENAI_*）
+    // LLM_CASSETTE を指定すると記録・再生（replay.rs）
+    let endpoint = EndpointConfig::from_env("CALC", "gpt-4o-mini");
+    let model = endpoint.model.clone();
+    let usage = Arc::new(UsageTracker::new("calc"));
+    // ツールはモードを持つので、モードを変えたら Agent を作り直す
+    let new_agent = |mode: NumberMode| -> Result<Agent, String> {
+        let transport = transport_from_env(endpoint.clone())?;
+        Ok(build_agent(&model, transport, mode).with_usage_tracker(Arc::clone(&usage)))
+    };
+    let mut agent = new_agent(mode)?;
+    let (mut session, mut messages) = match resumed {
+        Some(session) => {
+            println!(
+                "📂 会話 {} を再開しました（{} 発言）",
+                session.id,
+                session.turns()
+            );
+            let messages = session.messages.clone();
+            (session, messages)
+        }
+        None => (store.create("calc"), agent.initial_messages()),
     };
+    session
+        .settings
+        .insert(MODE_SETTING.to_string(), mode.to_string());
 
-    let mut registry: HashMap<String, NumFunc> = HashMap::new();
-    registry.insert("calc_sum".into(), calc_sum as NumFunc);
-    registry.insert("calc_times".into(), calc_times as NumFunc);
-    registry.insert("calc_pow".into(), calc_pow as NumFunc);
-
-    // --- ここから複数回 tool 呼び出し対応ループ版 ---
-    // OpenAI へ渡す完全な履歴 (tool 呼び出しを含む) を Value で保持
-    let mut messages: Vec<Value> = vec![
-        json!({"role": "system", "content": "あなたは計算を手伝う厳密モードのアシスタントです。ルール: (1) 不要な 0 との加算や 1 との乗算は行わない。 (2) 必要なら足し算は calc_sum、掛け算は calc_times、べき乗は calc_pow を使う。 (3) べき乗が直接表現できる場合は calc_pow で 1 回で求める。 (4) 途中の説明は簡潔に。"}),
-        json!({"role": "user", "content": user_text}),
-    ];
-
-    let tools = vec![tool_schema_sum, tool_schema_times, tool_schema_pow];
-    let client = reqwest::Client::new();
-    let model = "gpt-4o-mini";
-    const MAX_ITER: usize = 5; // 無限ループ安全弁
-
-    for iter in 0..MAX_ITER {
-        let req_body = json!({
-            "model": model,
-            "messages": messages,
-            "tools": tools,
-            "temperature": 0.0,
-        });
+    println!("計算したい内容を自然文で入力してください (例: 3と8を足して)");
+    println!("コマンド: /mode float|exact|exact:<桁数> 計算モードを切り替え, /exit 終了");
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
 
-        let raw = match send_chat_completion(&client, &api_key, &req_body).await {
-            Ok(t) => t,
-            Err(e) => {
-                eprintln!("API 呼び出し失敗(iter={iter}): {e}");
-                break;
+        let mut input = String::new();
+        // 0 バイト = EOF（Ctrl-D）
+        if io::stdin().read_line(&mut input)? == 0 {
+            break;
+        }
+        let user_text = match input.trim() {
+            "" => continue,
+            "/exit" | "/quit" => break,
+            "/mode" => {
+                println!("🔢 計算モード: {}", mode.label());
+                continue;
             }
+            text if text.starts_with("/mode ") => {
+                match NumberMode::parse(&text["/mode ".len()..]) {
+                    Ok(next) => {
+                        agent = new_agent(next)?;
+                        mode = next;
+                        session
+                            .settings
+                            .insert(MODE_SETTING.to_string(), mode.to_string());
+                        println!("🔢 計算モード: {}", mode.label());
+                        if session.turns() > 0
+                            && let Err(e) = store.save(&mut session)
+                        {
+                            eprintln!("⚠️ 会話を保存できません: {e}");
+                        }
+                    }
+                    Err(e) => eprintln!("❌ {e}"),
+                }
+                continue;
+            }
+            text => text,
         };
 
-        let parsed: ChatResponse = match serde_json::from_str(&raw) {
-            Ok(v) => v,
+        // 途中で失敗した発言は tool_calls が宙に浮くので巻き戻す
+        let checkpoint = messages.clone();
+        messages.push(ChatMessage::user(user_text));
+        let completed = match agent.run(&mut messages).await {
+            Ok(run) => {
+                if let Some(text) = run.final_text {
+                    println!("最終回答: {text}");
+                }
+                run.stop == StopReason::Completed
+            }
             Err(e) => {
-                eprintln!("JSON パース失敗(iter={iter}): {e}\nraw: {raw}");
-                break;
+                eprintln!("{e}");
+                false
             }
         };
+        if !completed {
+            messages = checkpoint;
+            continue;
+        }
+        session.messages = messages.clone();
+        if let Err(e) = store.save(&mut session) {
+            eprintln!("⚠️ 会話を保存できません: {e}");
+        }
+    }
 
-        let choice = match parsed.choices.first() {
-            Some(c) => c,
-            None => {
-                eprintln!("choices 空(iter={iter})");
-                break;
-            }
-        };
-        let msg = &choice.message;
+    println!("{}", usage.format_summary());
+    if session.turns() > 0 {
+        println!(
+            "💾 会話 {} を保存しました（calc --resume {} で再開）",
+            session.id, session.id
+        );
+    }
+    Ok(())
+}
 
-        if msg.tool_calls.is_empty() {
-            // これが最終回答
-            println!("最終回答: {}", msg.content.clone().unwrap_or_default());
-            break;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
 
-        // assistant メッセージ (tool_calls 含む) を履歴に追加
-        let assistant_msg = {
-            let mut tool_calls_json = Vec::new();
-            for tc in &msg.tool_calls {
-                tool_calls_json.push(json!({
-                    "id": tc.id,
-                    "type": "function",
-                    "function": {"name": tc.function.name, "arguments": tc.function.arguments}
-                }));
-            }
+    use crate::function_call::replay::ReplayTransport;
+
+    #[test]
+    fn test_parse_options() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse_options(&[]), Ok(CalcOptions::default()));
+        let options = args(&["--mode", "exact:30", "--resume", "20250101-120000"]);
+        assert_eq!(
+            parse_options(&options),
+            Ok(CalcOptions {
+                resume: Some("20250101-120000".to_string()),
+                mode: Some(NumberMode::Exact { precision: 30 }),
+            })
+        );
+        assert!(parse_options(&args(&["--mode"])).is_err());
+        assert!(parse_options(&args(&["--mode", "decimal"])).is_err());
+        assert!(parse_options(&args(&["--model", "x"])).is_err());
+    }
+
+    #[test]
+    fn test_generated_schemas_match_hand_written() {
+        let registry = build_registry(NumberMode::Float);
+        let schemas: Vec<Value> = registry
+            .definitions()
+            .iter()
+            .map(|d| serde_json::to_value(&d.function.parameters).unwrap())
+            .collect();
+
+        let two_numbers = json!({
+            "type": "object",
+            "properties": {
+                "a": { "type": "number", "description": "最初の数" },
+                "b": { "type": "number", "description": "次の数" }
+            },
+            "required": ["a", "b"]
+        });
+        assert_eq!(schemas[0], two_numbers);
+        assert_eq!(schemas[1], two_numbers);
+        assert_eq!(
+            schemas[2],
             json!({
-                "role": "assistant",
-                // content があればそのまま残す (思考説明など)
-                "content": msg.content.clone().unwrap_or_default(),
-                "tool_calls": tool_calls_json,
+                "type": "object",
+                "properties": {
+                    "a": { "type": "number", "description": "底 (base)" },
+                    "b": { "type": "number", "description": "指数 (負の数や小数も可)" }
+                },
+                "required": ["a", "b"]
             })
-        };
-        messages.push(assistant_msg);
-
-        // 各 tool_call をローカル実行し tool メッセージを追加
-        for tc in &msg.tool_calls {
-            let func_name = &tc.function.name;
-            let Some(func) = registry.get(func_name) else {
-                eprintln!("未知のツール呼び出し: {func_name}");
-                continue; // 未知ならスキップ (本来は break でも良い)
-            };
-            // 今は a,b の2引数数値関数のみ対応
-            match serde_json::from_str::<SumArgs>(&tc.function.arguments) {
-                Ok(args) => {
-                    let result = func(args.a, args.b);
-                    println!("実行: {func_name}({} , {}) = {result}", args.a, args.b);
-                    messages.push(json!({
-                        "role": "tool",
-                        "tool_call_id": tc.id,
-                        "content": result.to_string(),
-                    }));
-                }
-                Err(e) => {
-                    eprintln!(
-                        "引数パース失敗 tool_call_id={} error={e} raw={}",
-                        tc.id, tc.function.arguments
-                    );
-                    messages.push(json!({
-                        "role": "tool",
-                        "tool_call_id": tc.id,
-                        "content": format!("引数エラー: {e}"),
-                    }));
-                }
-            }
-        }
+        );
+    }
 
-        // ループ継続→ 次 API 呼び出しでこれらの tool 結果を踏まえて次の指示または最終回答を得る
-        if iter == MAX_ITER - 1 {
-            eprintln!("最大反復 {MAX_ITER} に到達。打ち切り");
-        }
+    /// 記録した会話（calc_pow → calc_sum → 回答）をオフラインで再生する
+    #[tokio::test]
+    async fn test_agent_replays_recorded_conversation() {
+        let cassette =
+            serde_json::from_str(include_str!("testdata/calc_pow_then_sum.json")).unwrap();
+        let agent = build_agent(
+            "gpt-4o-mini",
+            Box::new(ReplayTransport::new(cassette)),
+            NumberMode::Float,
+        );
+
+        let run = agent.ask("2の10乗に3を足して").await.unwrap();
+
+        let results: Vec<_> = run
+            .tool_trace
+            .iter()
+            .map(|t| (t.name.as_str(), t.result.clone()))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ("calc_pow", Ok("1024".to_string())),
+                ("calc_sum", Ok("1027".to_string()))
+            ]
+        );
+        assert_eq!(
+            run.final_text.as_deref(),
+            Some("2 の 10 乗は 1024 で、3 を足すと 1027 です。")
+        );
+        assert_eq!(run.usage.total_tokens, 854);
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn test_calc_tools_via_registry() {
+        let registry = build_registry(NumberMode::Float);
+        let result = registry
+            .call("calc_pow", r#"{"a": 2, "b": 10}"#)
+            .await
+            .unwrap();
+        assert_eq!(result, "1024");
+
+        let result = registry
+            .call("evaluate_expression", r#"{"expression": "(3+4)*2-1/5"}"#)
+            .await
+            .unwrap();
+        assert_eq!(result, "13.8");
+        // 誤りは場所を示してモデルに返す
+        let err = registry
+            .call("evaluate_expression", r#"{"expression": "1/(2-2)"}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ツール実行失敗: 1/(2-2)\n  ^^^^^\n3 文字目: 0 で割っています"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calc_pow_accepts_negative_and_fractional_exponents() {
+        let registry = build_registry(NumberMode::Float);
+        let pow = |args: &'static str| registry.call("calc_pow", args);
+        assert_eq!(pow(r#"{"a": 2, "b": -2}"#).await.unwrap(), "0.25");
+        assert_eq!(pow(r#"{"a": 9, "b": 0.5}"#).await.unwrap(), "3");
+        assert_eq!(
+            pow(r#"{"a": -8, "b": 0.3333333333333333}"#).await.unwrap(),
+            "-2"
+        );
+        // NaN ではなく理由を返す
+        let err = pow(r#"{"a": -4, "b": 0.5}"#).await.unwrap_err();
+        assert_eq!(err.to_string(), "ツール実行失敗: 結果が実数になりません");
+    }
+
+    #[tokio::test]
+    async fn test_exact_mode_tools() {
+        let registry = build_registry(NumberMode::Exact { precision: 10 });
+        let call = |name: &'static str, args: &'static str| registry.call(name, args);
+
+        assert_eq!(
+            call("calc_sum", r#"{"a": 0.1, "b": 0.2}"#).await.unwrap(),
+            "0.3"
+        );
+        // JSON の数は f64 を経由せず、書かれた桁のまま受け取る
+        assert_eq!(
+            call("calc_sum", r#"{"a": 98765432109876543210, "b": 1}"#)
+                .await
+                .unwrap(),
+            "98765432109876543211"
+        );
+        assert_eq!(
+            call("calc_times", r#"{"a": 12345678901234567.5, "b": 2}"#)
+                .await
+                .unwrap(),
+            "24691357802469135"
+        );
+        assert_eq!(
+            call("calc_pow", r#"{"a": 3, "b": 50}"#).await.unwrap(),
+            "717897987691852588770249"
+        );
+        assert_eq!(
+            call("calc_pow", r#"{"a": 3, "b": -2}"#).await.unwrap(),
+            "1/9 ≈ 0.1111111111"
+        );
+        assert_eq!(
+            call(
+                "evaluate_expression",
+                r#"{"expression": "1/3 + 1/6 + 2^-1"}"#
+            )
+            .await
+            .unwrap(),
+            "1"
+        );
+    }
 }
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::function_call::errors::ToolCallError;
+use crate::function_call::schema::{ToolArgs, tool_args};
+use crate::function_call::types::{ToolDefinition, ToolFunctionDefinition};
+use crate::function_call::validate::{format_errors, validate};
+
+/// ハンドラが返す Future（型を消して HashMap に入れるため Box 化）
+type ToolFuture = Pin<Box<dyn Future<Output = Result<String, ToolCallError>> + Send>>;
+
+/// 生の arguments 文字列を受け取って実行するハンドラ
+type Handler = Box<dyn Fn(&str) -> ToolFuture + Send + Sync>;
+
+tool_args! {
+    /// 引数のないツール用（`{}` を受け付ける）
+    #[derive(Debug, Deserialize)]
+    pub struct NoArgs {}
+}
+
+/// ツール名 → (定義, ハンドラ) の登録簿
+///
+/// 登録した順に `ToolDefinition` を並べて API に渡し、
+/// 返ってきた tool_call は名前で引いて引数をパースしてから実行する。
+#[derive(Default)]
+pub struct ToolRegistry {
+    definitions: Vec<ToolDefinition<'static>>,
+    handlers: HashMap<&'static str, Handler>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ツールを登録する
+    ///
+    /// パラメータの JSON Schema は `A::schema()` から作る。
+    /// `handler` は arguments を `A` にパースした値を受け取る。
+    /// arguments はまず Schema で検証し、違反があれば `ToolCallError::InvalidArgs`
+    /// （違反箇所をすべて列挙）、パースに失敗すれば `ToolCallError::ArgParse` を返す。
+    /// どちらの場合も `handler` は呼ばない。
+    ///
+    /// # Panics
+    /// 同じ名前を 2 回登録した場合（定義の書き間違い）
+    pub fn register<A, F, Fut>(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        handler: F,
+    ) -> &mut Self
+    where
+        A: ToolArgs + Send + 'static,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        assert!(
+            !self.handlers.contains_key(name),
+            "ツール {name} が二重に登録されています"
+        );
+
+        let parameters = A::schema();
+        let schema = serde_json::to_value(&parameters).unwrap_or(Value::Null);
+        self.definitions.push(ToolDefinition {
+            r#type: "function",
+            function: ToolFunctionDefinition {
+                name,
+                description,
+                parameters,
+            },
+        });
+
+        let handler: Handler = Box::new(move |raw: &str| match parse_args::<A>(raw, &schema) {
+            Ok(args) => {
+                let fut = handler(args);
+                Box::pin(async move { fut.await.map_err(ToolCallError::Failed) })
+            }
+            Err(e) => Box::pin(async move { Err(e) }),
+        });
+        self.handlers.insert(name, handler);
+        self
+    }
+
+    /// API の `tools` に渡す定義一覧（登録順）
+    pub fn definitions(&self) -> &[ToolDefinition<'static>] {
+        &self.definitions
+    }
+
+    /// 名前でツールを探し、arguments をパースして実行する
+    pub async fn call(&self, name: &str, raw_args: &str) -> Result<String, ToolCallError> {
+        let handler = self
+            .handlers
+            .get(name)
+            .ok_or_else(|| ToolCallError::UnknownTool(name.to_string()))?;
+        handler(raw_args).await
+    }
+}
+
+/// arguments を Schema で検証してからパース（空文字は `{}` として扱う）
+fn parse_args<A: DeserializeOwned>(raw: &str, schema: &Value) -> Result<A, ToolCallError> {
+    let raw = if raw.trim().is_empty() { "{}" } else { raw };
+    let value: Value = serde_json::from_str(raw)
+        .map_err(|e| ToolCallError::ArgParse(format!("JSON として読めません: {e}; raw: {raw}")))?;
+    validate(&value, schema)
+        .map_err(|errors| ToolCallError::InvalidArgs(format_errors(&errors)))?;
+    // Value を経由すると数が f64 になるので、元の文字列から読む（NumberArg の桁を残す）
+    serde_json::from_str(raw).map_err(|e| ToolCallError::ArgParse(format!("{e}; raw: {raw}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    tool_args! {
+        #[derive(Debug, Deserialize)]
+        struct EchoArgs {
+            text: String,
+        }
+    }
+
+    fn registry() -> ToolRegistry {
+        let mut registry = ToolRegistry::new();
+        registry
+            .register(
+                "echo",
+                "文字列をそのまま返す",
+                |args: EchoArgs| async move {
+                    if args.text.is_empty() {
+                        return Err("空です".to_string());
+                    }
+                    Ok(args.text)
+                },
+            )
+            .register("ping", "pong を返す", |_: NoArgs| async {
+                Ok("pong".to_string())
+            });
+        registry
+    }
+
+    #[test]
+    fn test_definitions_in_registration_order() {
+        let registry = registry();
+        let value = serde_json::to_value(registry.definitions()).unwrap();
+
+        assert_eq!(value[0]["type"], "function");
+        assert_eq!(value[0]["function"]["name"], "echo");
+        assert_eq!(
+            value[0]["function"]["parameters"]["required"],
+            json!(["text"])
+        );
+        assert_eq!(value[1]["function"]["name"], "ping");
+    }
+
+    #[tokio::test]
+    async fn test_call_parses_args_and_runs_handler() {
+        let registry = registry();
+        assert_eq!(
+            registry.call("echo", r#"{"text": "やあ"}"#).await.unwrap(),
+            "やあ"
+        );
+        assert_eq!(registry.call("ping", "").await.unwrap(), "pong");
+        assert_eq!(registry.call("ping", "{}").await.unwrap(), "pong");
+    }
+
+    #[tokio::test]
+    async fn test_call_errors() {
+        let registry = registry();
+
+        let err = registry.call("nope", "{}").await.unwrap_err();
+        assert!(matches!(err, ToolCallError::UnknownTool(ref n) if n == "nope"));
+
+        // Schema 違反は違反箇所を列挙して返す（ハンドラは呼ばない）
+        let err = registry.call("echo", r#"{"txt": 1}"#).await.unwrap_err();
+        assert!(matches!(err, ToolCallError::InvalidArgs(_)));
+        assert_eq!(
+            err.to_string(),
+            "引数がスキーマに合いません: $.text: 必須の項目がありません"
+        );
+        let err = registry.call("echo", r#"{"text": 1}"#).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("$.text: string が必要です（実際: integer）")
+        );
+
+        let err = registry.call("echo", "{oops").await.unwrap_err();
+        assert!(matches!(err, ToolCallError::ArgParse(_)));
+        assert!(err.is_argument_error());
+
+        let err = registry.call("echo", r#"{"text": ""}"#).await.unwrap_err();
+        assert_eq!(err.to_string(), "ツール実行失敗: 空です");
+    }
+
+    #[test]
+    #[should_panic(expected = "二重に登録")]
+    fn test_duplicate_registration_panics() {
+        let mut registry = registry();
+        registry.register("ping", "", |_: NoArgs| async { Ok(String::new()) });
+    }
+}
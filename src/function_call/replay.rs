@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::function_call::api::{ChatTransport, EndpointConfig, OnDelta, OnRetry, OpenAiTransport};
+use crate::function_call::errors::OpenAiCallError;
+use crate::function_call::types::{ChatRequest, ChatResponse};
+
+/// 記録したリクエストとレスポンスの組（1 ファイル = 1 つの会話）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+/// API 呼び出し 1 回分
+///
+/// レスポンスはストリーミングで受信した場合も組み立て後の JSON で保存する。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: Value,
+    pub response: Value,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("{} を読めません: {}", path.display(), e))?;
+        serde_json::from_str(&text)
+            .map_err(|e| format!("{} の形式が不正です: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// 再生時に照合するリクエストの要点（モデル・各メッセージの role・ツールの一覧）
+///
+/// ツールの実行結果や日時は実行環境で変わるので、本文までは比べない。
+fn request_shape(request: &Value) -> Value {
+    let roles: Vec<&Value> = request["messages"]
+        .as_array()
+        .map(|messages| messages.iter().map(|m| &m["role"]).collect())
+        .unwrap_or_default();
+    let tools: Vec<&Value> = request["tools"]
+        .as_array()
+        .map(|tools| tools.iter().map(|t| &t["function"]["name"]).collect())
+        .unwrap_or_default();
+    serde_json::json!({"model": request["model"], "roles": roles, "tools": tools})
+}
+
+fn request_value(request: &ChatRequest<'_>) -> Result<Value, OpenAiCallError> {
+    serde_json::to_value(request).map_err(|e| OpenAiCallError::JsonBuild(e.to_string()))
+}
+
+/// 実際に送信しつつ、やり取りをカセットに保存する送信先
+///
+/// 途中で終了しても残るよう、1 回ごとにファイル全体を書き直す。
+pub struct RecordingTransport {
+    inner: Box<dyn ChatTransport>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Box<dyn ChatTransport>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    fn record(&self, request: &ChatRequest<'_>, raw: &str) -> Result<(), OpenAiCallError> {
+        let response: Value = serde_json::from_str(raw)
+            .map_err(|e| OpenAiCallError::JsonParse(format!("{e}; raw: {raw}")))?;
+        let interaction = Interaction {
+            request: request_value(request)?,
+            response,
+        };
+        let mut cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+        cassette.interactions.push(interaction);
+        if let Err(e) = cassette.save(&self.path) {
+            eprintln!("⚠️ カセットを保存できません: {}", e);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChatTransport for RecordingTransport {
+    async fn send(&self, request: &ChatRequest<'_>) -> Result<String, OpenAiCallError> {
+        let raw = self.inner.send(request).await?;
+        self.record(request, &raw)?;
+        Ok(raw)
+    }
+
+    async fn send_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<String, OpenAiCallError> {
+        let raw = self.inner.send_stream(request, on_delta).await?;
+        self.record(request, &raw)?;
+        Ok(raw)
+    }
+
+    fn set_on_retry(&mut self, on_retry: Arc<OnRetry<'static>>) {
+        self.inner.set_on_retry(on_retry);
+    }
+}
+
+/// カセットのレスポンスを順に返す送信先（ネットワークを使わない）
+///
+/// リクエストが記録時と違う流れになっていたら（role の並びやモデルが違う、
+/// 記録より多く呼ばれた）`OpenAiCallError::Replay` を返す。
+pub struct ReplayTransport {
+    interactions: Mutex<VecDeque<Interaction>>,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            interactions: Mutex::new(cassette.interactions.into()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Cassette::load(path).map(Self::new)
+    }
+
+    /// まだ返していないやり取りの数
+    pub fn remaining(&self) -> usize {
+        self.interactions.lock().map(|i| i.len()).unwrap_or(0)
+    }
+
+    fn next(&self, request: &ChatRequest<'_>) -> Result<Value, OpenAiCallError> {
+        let request = request_value(request)?;
+        let mut interactions = self.interactions.lock().unwrap_or_else(|e| e.into_inner());
+        let interaction = interactions.pop_front().ok_or_else(|| {
+            OpenAiCallError::Replay("記録より多くリクエストが送られました".to_string())
+        })?;
+
+        let (expected, actual) = (request_shape(&interaction.request), request_shape(&request));
+        if expected != actual {
+            return Err(OpenAiCallError::Replay(format!(
+                "記録と異なるリクエストです: 記録 {expected} / 実際 {actual}"
+            )));
+        }
+        Ok(interaction.response)
+    }
+}
+
+#[async_trait]
+impl ChatTransport for ReplayTransport {
+    async fn send(&self, request: &ChatRequest<'_>) -> Result<String, OpenAiCallError> {
+        self.next(request).map(|response| response.to_string())
+    }
+
+    /// 本文があればまとめて 1 回の差分として通知する
+    async fn send_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<String, OpenAiCallError> {
+        let response = self.next(request)?;
+        let text = serde_json::from_value::<ChatResponse>(response.clone())
+            .ok()
+            .and_then(|r| r.choices.into_iter().next())
+            .and_then(|c| c.message.content)
+            .unwrap_or_default();
+        if !text.is_empty() {
+            on_delta(&text);
+        }
+        Ok(response.to_string())
+    }
+}
+
+/// `LLM_CASSETTE` の再生中か（再生した使用量はログに残さない）
+pub fn is_replaying() -> bool {
+    let cassette = std::env::var("LLM_CASSETTE").unwrap_or_default();
+    let mode = std::env::var("LLM_CASSETTE_MODE").unwrap_or_default();
+    !cassette.is_empty() && matches!(mode.trim().to_lowercase().as_str(), "" | "replay")
+}
+
+/// 環境変数に応じた送信先
+///
+/// * `LLM_CASSETTE` 未設定 - そのまま API に送る
+/// * `LLM_CASSETTE=<ファイル>`（`LLM_CASSETTE_MODE=replay`、既定） - ファイルから再生（API キー不要）
+/// * `LLM_CASSETTE=<ファイル>` と `LLM_CASSETTE_MODE=record` - 送信しつつファイルに記録
+pub fn transport_from_env(endpoint: EndpointConfig) -> Result<Box<dyn ChatTransport>, String> {
+    let Some(path) = std::env::var("LLM_CASSETTE").ok().filter(|p| !p.is_empty()) else {
+        endpoint.validate()?;
+        return Ok(Box::new(OpenAiTransport::new(endpoint)));
+    };
+
+    let mode = std::env::var("LLM_CASSETTE_MODE").unwrap_or_default();
+    match mode.trim().to_lowercase().as_str() {
+        "" | "replay" => {
+            println!("📼 {} を再生します", path);
+            Ok(Box::new(ReplayTransport::load(Path::new(&path))?))
+        }
+        "record" => {
+            endpoint.validate()?;
+            println!("⏺️ {} に記録します", path);
+            let inner = Box::new(OpenAiTransport::new(endpoint));
+            Ok(Box::new(RecordingTransport::new(inner, path)))
+        }
+        other => Err(format!(
+            "不明な LLM_CASSETTE_MODE です: {}（record / replay）",
+            other
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::function_call::testutil::TempPath;
+    use crate::function_call::types::ChatMessage;
+
+    /// 固定のレスポンスを返す（記録のテスト用）
+    struct FixedTransport(Value);
+
+    #[async_trait]
+    impl ChatTransport for FixedTransport {
+        async fn send(&self, _request: &ChatRequest<'_>) -> Result<String, OpenAiCallError> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    fn text_response(text: &str) -> Value {
+        json!({"choices": [{"message": {"role": "assistant", "content": text}}]})
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = TempPath::new("cassette.json");
+        let messages = [ChatMessage::system("係"), ChatMessage::user("こんにちは")];
+        let request = ChatRequest::new("gpt-4o-mini", &messages);
+
+        let recorder = RecordingTransport::new(
+            Box::new(FixedTransport(text_response("やあ"))),
+            path.to_path_buf(),
+        );
+        recorder.send(&request).await.unwrap();
+
+        let replay = ReplayTransport::load(&path).unwrap();
+        assert_eq!(replay.remaining(), 1);
+
+        let deltas = Mutex::new(Vec::new());
+        let on_delta = |text: &str| deltas.lock().unwrap().push(text.to_string());
+        let raw = replay.send_stream(&request, &on_delta).await.unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&raw).unwrap(),
+            text_response("やあ")
+        );
+        assert_eq!(deltas.into_inner().unwrap(), vec!["やあ"]);
+
+        // 記録より多く呼ぶとエラー
+        let err = replay.send(&request).await.unwrap_err();
+        assert!(matches!(err, OpenAiCallError::Replay(_)));
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejects_different_flow() {
+        let recorded = [ChatMessage::system("係"), ChatMessage::user("こんにちは")];
+        let cassette = Cassette {
+            interactions: vec![Interaction {
+                request: serde_json::to_value(ChatRequest::new("gpt-4o-mini", &recorded)).unwrap(),
+                response: text_response("やあ"),
+            }],
+        };
+
+        // 本文が違うだけなら再生できる
+        let replay = ReplayTransport::new(cassette.clone());
+        let reworded = [ChatMessage::system("係"), ChatMessage::user("こんばんは")];
+        assert!(
+            replay
+                .send(&ChatRequest::new("gpt-4o-mini", &reworded))
+                .await
+                .is_ok()
+        );
+
+        // role の並びが違えばエラー
+        let replay = ReplayTransport::new(cassette);
+        let err = replay
+            .send(&ChatRequest::new("gpt-4o-mini", &recorded[1..]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("記録と異なる"), "{err}");
+    }
+}
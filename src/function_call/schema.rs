@@ -0,0 +1,336 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::function_call::number::NumberArg;
+use crate::function_call::types::JsonSchemaObject;
+
+/// ツールの引数型（パラメータの JSON Schema を型から作れる）
+///
+/// 通常は `tool_args!` で構造体を定義すると実装される。
+pub trait ToolArgs: DeserializeOwned {
+    fn schema() -> JsonSchemaObject<'static>;
+}
+
+/// フィールドの型に対応する JSON Schema
+pub trait SchemaType {
+    /// 省略可能な型か（Option は required に入れない）
+    const OPTIONAL: bool = false;
+
+    fn schema() -> Value;
+}
+
+macro_rules! impl_schema_type {
+    ($json_type:literal: $($ty:ty),*) => {
+        $(
+            impl SchemaType for $ty {
+                fn schema() -> Value {
+                    json!({"type": $json_type})
+                }
+            }
+        )*
+    };
+}
+
+impl_schema_type!("string": String);
+impl_schema_type!("number": f32, f64, NumberArg);
+impl_schema_type!("integer": u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+impl_schema_type!("boolean": bool);
+
+impl<T: SchemaType> SchemaType for Vec<T> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl<T: SchemaType> SchemaType for Option<T> {
+    const OPTIONAL: bool = true;
+
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+/// 文字列 enum の Schema（値は serde のシリアライズ結果を使うので rename とずれない）
+pub fn enum_schema<T: Serialize>(variants: &[T]) -> Value {
+    let values: Vec<Value> = variants
+        .iter()
+        .map(|v| serde_json::to_value(v).unwrap_or(Value::Null))
+        .collect();
+    json!({"type": "string", "enum": values})
+}
+
+/// 1 フィールド分の Schema（doc コメントを description に）
+pub fn property<T: SchemaType>(docs: &[&str]) -> Value {
+    let mut schema = T::schema();
+    let description = docs
+        .iter()
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !description.is_empty() {
+        schema["description"] = Value::String(description);
+    }
+    schema
+}
+
+/// ツールの引数構造体を定義し、`ToolArgs` を実装する
+///
+/// * 各フィールドの `///` コメントが description になる（他の属性より前に書く）
+/// * `Option<T>` と `#[serde(default)]` / `#[serde(default = "..")]` 付きのフィールドは required に入れない
+/// * フィールドの `#[serde(rename = "..")]` はプロパティ名に反映する
+/// * 名前を変える他の指定（構造体の `rename_all`、`rename(deserialize = ..)`）は
+///   スキーマとずれるのでコンパイルエラーにする
+///
+/// ```ignore
+/// tool_args! {
+///     #[derive(Debug, Deserialize)]
+///     pub struct FetchWeatherArgs {
+///         /// 都市名
+///         pub city: String,
+///     }
+/// }
+/// ```
+macro_rules! tool_args {
+    // doc 属性だけを集める
+    (@docs [$($acc:expr),*]) => {
+        &[$($acc),*]
+    };
+    (@docs [$($acc:expr),*] [doc = $doc:literal] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@docs [$($acc,)* $doc] $($rest)*)
+    };
+    (@docs [$($acc:expr),*] [$($other:tt)*] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@docs [$($acc),*] $($rest)*)
+    };
+
+    // #[serde(default)] / #[serde(default = "..")] が付いているか
+    (@has_default) => {
+        false
+    };
+    (@has_default [serde($($arg:tt)*)] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@serde_default [$($rest)*] $($arg)*)
+    };
+    (@has_default [$($other:tt)*] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@has_default $($rest)*)
+    };
+    (@serde_default [$($rest:tt)*]) => {
+        $crate::function_call::schema::tool_args!(@has_default $($rest)*)
+    };
+    (@serde_default [$($rest:tt)*] default $($ignored:tt)*) => {
+        true
+    };
+    (@serde_default [$($rest:tt)*] $other:tt $($arg:tt)*) => {
+        $crate::function_call::schema::tool_args!(@serde_default [$($rest)*] $($arg)*)
+    };
+
+    // プロパティ名（#[serde(rename = "..")] があればその名前）
+    (@name $field:ident) => {
+        stringify!($field)
+    };
+    (@name $field:ident [serde($($arg:tt)*)] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@serde_rename $field [$($rest)*] $($arg)*)
+    };
+    (@name $field:ident [$($other:tt)*] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@name $field $($rest)*)
+    };
+    (@serde_rename $field:ident [$($rest:tt)*]) => {
+        $crate::function_call::schema::tool_args!(@name $field $($rest)*)
+    };
+    (@serde_rename $field:ident [$($rest:tt)*] rename = $name:literal $($ignored:tt)*) => {
+        $name
+    };
+    (@serde_rename $field:ident [$($rest:tt)*] rename $($ignored:tt)*) => {
+        compile_error!("tool_args! は rename = \"..\" 以外の名前の指定に対応していません")
+    };
+    (@serde_rename $field:ident [$($rest:tt)*] $other:tt $($arg:tt)*) => {
+        $crate::function_call::schema::tool_args!(@serde_rename $field [$($rest)*] $($arg)*)
+    };
+
+    // 構造体の #[serde(rename_all)] は拒否する
+    (@struct_attrs) => {};
+    (@struct_attrs [serde($($arg:tt)*)] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@struct_serde $($arg)*);
+        $crate::function_call::schema::tool_args!(@struct_attrs $($rest)*);
+    };
+    (@struct_attrs [$($other:tt)*] $($rest:tt)*) => {
+        $crate::function_call::schema::tool_args!(@struct_attrs $($rest)*);
+    };
+    (@struct_serde) => {};
+    (@struct_serde rename_all $($ignored:tt)*) => {
+        compile_error!(
+            "tool_args! は #[serde(rename_all)] に対応していません（フィールドごとに #[serde(rename = \"..\")] を付けてください）"
+        );
+    };
+    (@struct_serde $other:tt $($arg:tt)*) => {
+        $crate::function_call::schema::tool_args!(@struct_serde $($arg)*);
+    };
+
+    (
+        $(#[$($sattr:tt)*])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$($fattr:tt)*])*
+                $fvis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $crate::function_call::schema::tool_args!(@struct_attrs $([$($sattr)*])*);
+
+        $(#[$($sattr)*])*
+        $vis struct $name {
+            $(
+                $(#[$($fattr)*])*
+                $fvis $field: $ty,
+            )*
+        }
+
+        impl $crate::function_call::schema::ToolArgs for $name {
+            fn schema() -> $crate::function_call::types::JsonSchemaObject<'static> {
+                #[allow(unused_mut)]
+                let mut properties = serde_json::Map::new();
+                #[allow(unused_mut)]
+                let mut required: Vec<&'static str> = Vec::new();
+                $(
+                    let docs: &[&str] =
+                        $crate::function_call::schema::tool_args!(@docs [] $([$($fattr)*])*);
+                    let name: &'static str =
+                        $crate::function_call::schema::tool_args!(@name $field $([$($fattr)*])*);
+                    properties.insert(
+                        name.to_string(),
+                        $crate::function_call::schema::property::<$ty>(docs),
+                    );
+                    let optional =
+                        <$ty as $crate::function_call::schema::SchemaType>::OPTIONAL
+                            || $crate::function_call::schema::tool_args!(@has_default $([$($fattr)*])*);
+                    if !optional {
+                        required.push(name);
+                    }
+                )*
+
+                $crate::function_call::types::JsonSchemaObject {
+                    r#type: "object",
+                    properties: serde_json::Value::Object(properties),
+                    required,
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use tool_args;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Unit {
+        Celsius,
+        #[serde(rename = "F")]
+        Fahrenheit,
+    }
+
+    impl SchemaType for Unit {
+        fn schema() -> Value {
+            enum_schema(&[Unit::Celsius, Unit::Fahrenheit])
+        }
+    }
+
+    tool_args! {
+        /// テスト用の引数
+        #[derive(Debug, Deserialize)]
+        struct SampleArgs {
+            /// 都市名
+            city: String,
+            /// 複数行の
+            /// 説明
+            #[serde(default)]
+            days: u32,
+            tags: Vec<String>,
+            /// 単位
+            #[serde(default)]
+            unit: Option<Unit>,
+        }
+    }
+
+    tool_args! {
+        #[derive(Debug, Deserialize)]
+        struct EmptyArgs {}
+    }
+
+    fn default_limit() -> u32 {
+        10
+    }
+
+    tool_args! {
+        /// serde の名前と既定値の指定
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct RenamedArgs {
+            /// 種類
+            #[serde(rename = "type")]
+            kind: String,
+            /// 件数
+            #[serde(default = "default_limit")]
+            limit: u32,
+            #[serde(alias = "max", default, rename = "maxTemp")]
+            max_temp: f64,
+        }
+    }
+
+    #[test]
+    fn test_generated_schema() {
+        let schema = SampleArgs::schema();
+        assert_eq!(schema.r#type, "object");
+        assert_eq!(schema.required, vec!["city", "tags"]);
+        assert_eq!(
+            schema.properties,
+            json!({
+                "city": {"type": "string", "description": "都市名"},
+                "days": {"type": "integer", "description": "複数行の 説明"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "unit": {"type": "string", "enum": ["celsius", "F"], "description": "単位"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_generated_struct_deserializes() {
+        let args: SampleArgs =
+            serde_json::from_str(r#"{"city": "東京", "tags": [], "unit": "F"}"#).unwrap();
+        assert_eq!(args.city, "東京");
+        assert_eq!(args.days, 0);
+        assert!(args.tags.is_empty());
+        assert!(matches!(args.unit, Some(Unit::Fahrenheit)));
+    }
+
+    #[test]
+    fn test_serde_rename_and_default_fn() {
+        let schema = RenamedArgs::schema();
+        assert_eq!(schema.required, vec!["type"]);
+        assert_eq!(
+            schema.properties,
+            json!({
+                "type": {"type": "string", "description": "種類"},
+                "limit": {"type": "integer", "description": "件数"},
+                "maxTemp": {"type": "number"}
+            })
+        );
+
+        let args: RenamedArgs = serde_json::from_str(r#"{"type": "雨", "maxTemp": 30}"#).unwrap();
+        assert_eq!(args.kind, "雨");
+        assert_eq!(args.limit, 10);
+        assert_eq!(args.max_temp, 30.0);
+    }
+
+    #[test]
+    fn test_empty_schema() {
+        let schema = EmptyArgs::schema();
+        assert_eq!(schema.properties, json!({}));
+        assert!(schema.required.is_empty());
+        let _: EmptyArgs = serde_json::from_str("{}").unwrap();
+    }
+}
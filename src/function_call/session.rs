@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::function_call::types::ChatMessage;
+
+/// 会話の保存先（1 セッション = 1 ファイル）
+pub const SESSIONS_DIR: &str = "data/sessions";
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 一覧で表示する最初の発言の長さ
+const TITLE_CHARS: usize = 30;
+
+/// 保存された会話
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub app: String, // "weather" / "calc" / "chat"
+    pub created_at: String,
+    pub updated_at: String,
+    pub messages: Vec<ChatMessage>,
+    /// アプリごとの設定（calc の計算モードなど、再開したときに引き継ぐ）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub settings: BTreeMap<String, String>,
+}
+
+impl Session {
+    /// 新しいセッション（ID は作成日時、保存するまでファイルは作らない）
+    pub fn new(app: &str) -> Self {
+        let now = Local::now();
+        Self {
+            id: now.format("%Y%m%d-%H%M%S").to_string(),
+            app: app.to_string(),
+            created_at: now.format(TIMESTAMP_FORMAT).to_string(),
+            updated_at: now.format(TIMESTAMP_FORMAT).to_string(),
+            messages: Vec::new(),
+            settings: BTreeMap::new(),
+        }
+    }
+
+    /// ユーザーの発言数
+    pub fn turns(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|m| matches!(m, ChatMessage::User { .. }))
+            .count()
+    }
+
+    /// 最初のユーザー発言（一覧の見出し用）
+    pub fn title(&self) -> String {
+        let first = self.messages.iter().find_map(|m| match m {
+            ChatMessage::User { content } => Some(content.as_str()),
+            _ => None,
+        });
+        let Some(first) = first else {
+            return "（発言なし）".to_string();
+        };
+        let flat = first.replace('\n', " ");
+        if flat.chars().count() <= TITLE_CHARS {
+            return flat;
+        }
+        let head: String = flat.chars().take(TITLE_CHARS).collect();
+        format!("{}…", head)
+    }
+
+    /// Markdown の書き起こし（system メッセージは含めない）
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# 会話 {}\n\n- アプリ: {}\n- 開始: {}\n- 更新: {}\n",
+            self.id, self.app, self.created_at, self.updated_at
+        );
+        for m in &self.messages {
+            match m {
+                ChatMessage::System { .. } => {}
+                ChatMessage::User { content } => {
+                    out.push_str(&format!("\n## 🧑 ユーザー\n\n{}\n", content));
+                }
+                ChatMessage::Assistant {
+                    content,
+                    tool_calls,
+                } => {
+                    if let Some(text) = content.as_deref().filter(|t| !t.is_empty()) {
+                        out.push_str(&format!("\n## 🤖 アシスタント\n\n{}\n", text));
+                    }
+                    for tc in tool_calls {
+                        out.push_str(&format!(
+                            "\n> 🔧 `{}({})`\n",
+                            tc.function.name, tc.function.arguments
+                        ));
+                    }
+                }
+                ChatMessage::Tool { content, .. } => {
+                    out.push_str(&format!("\n```text\n{}\n```\n", content.trim_end()));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// 一覧表示用の要約
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub id: String,
+    pub app: String,
+    pub updated_at: String,
+    pub turns: usize,
+    pub title: String,
+}
+
+/// `data/sessions/<id>.json` の読み書き
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::new(SESSIONS_DIR)
+    }
+}
+
+impl SessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// ID からファイルの場所（ディレクトリの外を指す ID は拒否）
+    fn path(&self, id: &str) -> Result<PathBuf, String> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("不正なセッション ID です: {}", id));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    /// 保存（更新日時を今にする）
+    pub fn save(&self, session: &mut Session) -> Result<(), String> {
+        session.updated_at = Local::now().format(TIMESTAMP_FORMAT).to_string();
+        let path = self.path(&session.id)?;
+        fs::create_dir_all(&self.dir).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+        let json = serde_json::to_string_pretty(session)
+            .map_err(|e| format!("JSON シリアライズエラー: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("ファイル書き込みエラー: {}", e))
+    }
+
+    /// 新しいセッションを作る（同じ秒に作られた ID とは重ならないようにする）
+    pub fn create(&self, app: &str) -> Session {
+        let mut session = Session::new(app);
+        let base = session.id.clone();
+        let mut n = 2;
+        while self.path(&session.id).is_ok_and(|p| p.exists()) {
+            session.id = format!("{}-{}", base, n);
+            n += 1;
+        }
+        session
+    }
+
+    pub fn load(&self, id: &str) -> Result<Session, String> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Err(format!("セッション {} が見つかりません", id));
+        }
+        let text =
+            fs::read_to_string(&path).map_err(|e| format!("ファイル読み込みエラー: {}", e))?;
+        serde_json::from_str(&text).map_err(|e| format!("JSON パースエラー: {}", e))
+    }
+
+    /// 保存済みの会話を続きから使えるように読み込む
+    ///
+    /// 別のアプリの会話は拒否し、system メッセージは今のプロンプトに差し替える。
+    pub fn resume(&self, id: &str, app: &str, system_prompt: &str) -> Result<Session, String> {
+        let mut session = self.load(id)?;
+        if session.app != app {
+            return Err(format!(
+                "セッション {} は {} の会話です（{} の会話を指定してください）",
+                id, session.app, app
+            ));
+        }
+        match session.messages.first_mut() {
+            Some(first @ ChatMessage::System { .. }) => *first = ChatMessage::system(system_prompt),
+            _ => session
+                .messages
+                .insert(0, ChatMessage::system(system_prompt)),
+        }
+        Ok(session)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Err(format!("セッション {} が見つかりません", id));
+        }
+        fs::remove_file(&path).map_err(|e| format!("ファイル削除エラー: {}", e))
+    }
+
+    /// 更新日時の新しい順（読めないファイルは飛ばす）
+    pub fn list(&self) -> Result<Vec<SessionSummary>, String> {
+        if !Path::new(&self.dir).exists() {
+            return Ok(Vec::new());
+        }
+        let entries =
+            fs::read_dir(&self.dir).map_err(|e| format!("ディレクトリ読み込みエラー: {}", e))?;
+
+        let mut summaries: Vec<SessionSummary> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| {
+                let text = fs::read_to_string(entry.path()).ok()?;
+                serde_json::from_str::<Session>(&text).ok()
+            })
+            .map(|s| SessionSummary {
+                turns: s.turns(),
+                title: s.title(),
+                id: s.id,
+                app: s.app,
+                updated_at: s.updated_at,
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then(b.id.cmp(&a.id)));
+        Ok(summaries)
+    }
+}
+
+/// `--resume <id>` だけを受け付ける起動引数（calc / chat）から再開する会話の ID を取り出す
+pub fn parse_resume_args(args: &[String]) -> Result<Option<String>, String> {
+    match args {
+        [] => Ok(None),
+        [flag, id] if flag == "--resume" => Ok(Some(id.clone())),
+        [flag] if flag == "--resume" => Err("--resume の値を指定してください".to_string()),
+        [arg, ..] => Err(format!("不明な引数: {}", arg)),
+    }
+}
+
+/// セッション一覧の表示
+pub fn format_session_list(summaries: &[SessionSummary]) -> String {
+    if summaries.is_empty() {
+        return "📭 保存された会話はありません".to_string();
+    }
+    let mut lines = vec!["💾 保存された会話:".to_string()];
+    for s in summaries {
+        lines.push(format!(
+            "[{}] {} | {} | {} 発言 | {}",
+            s.id, s.updated_at, s.app, s.turns, s.title
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function_call::testutil::TempPath;
+    use crate::function_call::types::{CalledFunction, ToolCall};
+
+    fn sample(app: &str) -> Session {
+        let mut session = Session::new(app);
+        session.messages = vec![
+            ChatMessage::system("係"),
+            ChatMessage::user("東京の天気は？"),
+            ChatMessage::assistant_tool_calls(
+                None,
+                vec![ToolCall {
+                    id: "c1".to_string(),
+                    call_type: "function".to_string(),
+                    function: CalledFunction {
+                        name: "fetch_weather".to_string(),
+                        arguments: r#"{"city":"東京"}"#.to_string(),
+                    },
+                }],
+            ),
+            ChatMessage::tool("c1", "晴れ 20℃"),
+            ChatMessage::assistant("東京は晴れです。"),
+        ];
+        session
+    }
+
+    #[test]
+    fn test_save_load_list_delete() {
+        let dir = TempPath::new("sessions");
+        let store = SessionStore::new(dir.to_path_buf());
+
+        let mut first = store.create("weather");
+        first.messages = sample("weather").messages;
+        store.save(&mut first).unwrap();
+
+        // 同じ秒に作っても ID は重ならない
+        let mut second = store.create("calc");
+        assert_ne!(first.id, second.id);
+        second.messages = vec![ChatMessage::user("1+1")];
+        store.save(&mut second).unwrap();
+
+        assert_eq!(store.load(&first.id).unwrap(), first);
+        let list = store.list().unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].id, second.id);
+        assert_eq!(list[1].turns, 1);
+        assert_eq!(list[1].title, "東京の天気は？");
+
+        store.delete(&first.id).unwrap();
+        assert!(store.load(&first.id).is_err());
+        assert!(store.delete(&first.id).is_err());
+    }
+
+    #[test]
+    fn test_resume_replaces_system_prompt_and_checks_app() {
+        let dir = TempPath::new("sessions_resume");
+        let store = SessionStore::new(dir.to_path_buf());
+
+        let mut session = store.create("weather");
+        session.messages = sample("weather").messages;
+        store.save(&mut session).unwrap();
+
+        let resumed = store.resume(&session.id, "weather", "新しい係").unwrap();
+        assert_eq!(resumed.messages[0], ChatMessage::system("新しい係"));
+        assert_eq!(resumed.messages[1..], session.messages[1..]);
+        let err = store.resume(&session.id, "calc", "係").unwrap_err();
+        assert!(err.contains("weather の会話です"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_resume_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse_resume_args(&[]), Ok(None));
+        assert_eq!(
+            parse_resume_args(&args(&["--resume", "20250101-120000"])),
+            Ok(Some("20250101-120000".to_string()))
+        );
+        assert!(parse_resume_args(&args(&["--resume"])).is_err());
+        assert!(parse_resume_args(&args(&["--model", "x"])).is_err());
+    }
+
+    #[test]
+    fn test_rejects_path_like_ids() {
+        let dir = TempPath::new("sessions_ids");
+        let store = SessionStore::new(dir.to_path_buf());
+        assert!(store.load("../weather_memos").is_err());
+        assert!(store.load("").is_err());
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let md = sample("weather").to_markdown();
+        assert!(md.starts_with("# 会話 "));
+        assert!(md.contains("- アプリ: weather"));
+        assert!(md.contains("## 🧑 ユーザー\n\n東京の天気は？"));
+        assert!(md.contains(r#"> 🔧 `fetch_weather({"city":"東京"})`"#));
+        assert!(md.contains("```text\n晴れ 20℃\n```"));
+        assert!(md.contains("## 🤖 アシスタント\n\n東京は晴れです。"));
+        assert!(!md.contains("係"));
+    }
+}
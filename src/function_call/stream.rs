@@ -0,0 +1,315 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::function_call::errors::OpenAiCallError;
+use crate::function_call::types::Usage;
+
+/// SSE（server-sent events）のバイト列を受け取り、イベントの data を取り出す
+///
+/// チャンクの境界は行や UTF-8 の途中に来ることがあるので、
+/// 改行までのバイト列をためてから 1 行ずつ解釈する。
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buf: Vec<u8>,
+    data: Vec<String>, // 組み立て中のイベントの data 行
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 受信したチャンクを追加し、完成したイベントの data を返す
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.handle_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// ストリーム終端で、空行で閉じられていない最後のイベントを返す
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buf);
+        let rest = String::from_utf8_lossy(&rest);
+        let rest = rest.trim_end_matches(['\n', '\r']);
+        if !rest.is_empty() {
+            self.handle_line(rest);
+        }
+        self.take_event()
+    }
+
+    fn handle_line(&mut self, line: &str) -> Option<String> {
+        // 空行 = イベントの区切り
+        if line.is_empty() {
+            return self.take_event();
+        }
+        // ":" で始まる行はコメント（keep-alive）、data 以外のフィールドは使わない
+        if let Some(value) = line.strip_prefix("data:") {
+            self.data
+                .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+        None
+    }
+
+    fn take_event(&mut self) -> Option<String> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = self.data.join("\n");
+        self.data.clear();
+        Some(data)
+    }
+}
+
+/// ストリームの 1 チャンク（chat.completion.chunk）
+#[derive(Debug, Deserialize)]
+pub struct ChatChunk {
+    #[serde(default)]
+    pub choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    pub usage: Option<Usage>, // stream_options.include_usage のときだけ最後に届く
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChunkChoice {
+    #[serde(default)]
+    pub delta: Delta,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Delta {
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallDelta>,
+}
+
+/// tool_calls の断片（index で同じ呼び出しの続きを判別する）
+#[derive(Debug, Deserialize)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub function: Option<FunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FunctionDelta {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+/// 組み立て中の tool_call
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// チャンクを順に受け取り、通常のレスポンスと同じ形に組み立てる
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    role: Option<String>,
+    content: String,
+    tool_calls: Vec<PartialToolCall>,
+    finish_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// チャンクを取り込み、本文の差分があれば返す（表示用）
+    pub fn apply(&mut self, chunk: ChatChunk) -> Option<String> {
+        if let Some(usage) = chunk.usage {
+            self.usage = Some(usage);
+        }
+
+        // n=1 しか使わないので先頭の choice だけ見る
+        let choice = chunk.choices.into_iter().next()?;
+        if let Some(reason) = choice.finish_reason {
+            self.finish_reason = Some(reason);
+        }
+        let delta = choice.delta;
+        if let Some(role) = delta.role {
+            self.role = Some(role);
+        }
+
+        for tc in delta.tool_calls {
+            if self.tool_calls.len() <= tc.index {
+                self.tool_calls
+                    .resize_with(tc.index + 1, PartialToolCall::default);
+            }
+            let partial = &mut self.tool_calls[tc.index];
+            if let Some(id) = tc.id {
+                partial.id = id;
+            }
+            if let Some(function) = tc.function {
+                if let Some(name) = function.name {
+                    partial.name.push_str(&name);
+                }
+                if let Some(arguments) = function.arguments {
+                    partial.arguments.push_str(&arguments);
+                }
+            }
+        }
+
+        match delta.content {
+            Some(text) if !text.is_empty() => {
+                self.content.push_str(&text);
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+
+    /// SSE の data を 1 件取り込む（`[DONE]` は無視）
+    pub fn apply_data(&mut self, data: &str) -> Result<Option<String>, OpenAiCallError> {
+        if data.trim() == "[DONE]" {
+            return Ok(None);
+        }
+        let chunk: ChatChunk = serde_json::from_str(data)
+            .map_err(|e| OpenAiCallError::JsonParse(format!("{e}; chunk: {data}")))?;
+        Ok(self.apply(chunk))
+    }
+
+    /// 非ストリームのレスポンスと同じ形の JSON にする
+    ///
+    /// `ChatResponse` としてパースできるので、呼び出し側は経路を区別しなくてよい。
+    pub fn finish(self) -> Value {
+        let content = if self.content.is_empty() && !self.tool_calls.is_empty() {
+            Value::Null
+        } else {
+            Value::String(self.content)
+        };
+
+        let mut message = json!({
+            "role": self.role.unwrap_or_else(|| "assistant".to_string()),
+            "content": content,
+        });
+        if !self.tool_calls.is_empty() {
+            let tool_calls: Vec<Value> = self
+                .tool_calls
+                .into_iter()
+                .map(|tc| {
+                    json!({
+                        "id": tc.id,
+                        "type": "function",
+                        "function": {"name": tc.name, "arguments": tc.arguments},
+                    })
+                })
+                .collect();
+            message["tool_calls"] = Value::Array(tool_calls);
+        }
+
+        let mut response = json!({
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": message,
+                "finish_reason": self.finish_reason,
+            }],
+        });
+        if let Some(usage) = self.usage {
+            response["usage"] = json!(usage);
+        }
+        response
+    }
+}
+
+/// SSE の本文全体を一度に組み立てる（テストや保存済みの記録用）
+pub fn assemble_sse(body: &[u8], mut on_delta: impl FnMut(&str)) -> Result<Value, OpenAiCallError> {
+    let mut parser = SseParser::new();
+    let mut acc = StreamAccumulator::new();
+    let events = parser.feed(body).into_iter().chain(parser.finish());
+    for data in events {
+        if let Some(text) = acc.apply_data(&data)? {
+            on_delta(&text);
+        }
+    }
+    Ok(acc.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function_call::types::ChatResponse;
+
+    const TEXT_STREAM: &str = include_str!("testdata/stream_text.sse");
+    const TOOL_CALLS_STREAM: &str = include_str!("testdata/stream_tool_calls.sse");
+
+    #[test]
+    fn test_parser_handles_split_chunks() {
+        let mut parser = SseParser::new();
+        let body = "data: {\"a\":\"東京\"}\r\n\r\n: keep-alive\n\ndata: 1\ndata: 2\n\ndata: [DONE]";
+        let bytes = body.as_bytes();
+
+        // 1 バイトずつ流しても（UTF-8 の途中で切れても）同じ結果になる
+        let mut events = Vec::new();
+        for b in bytes.chunks(1) {
+            events.extend(parser.feed(b));
+        }
+        events.extend(parser.finish());
+
+        assert_eq!(events, vec!["{\"a\":\"東京\"}", "1\n2", "[DONE]"]);
+    }
+
+    #[test]
+    fn test_text_stream_yields_deltas() {
+        let mut deltas = Vec::new();
+        let value = assemble_sse(TEXT_STREAM.as_bytes(), |d| deltas.push(d.to_string())).unwrap();
+
+        assert_eq!(deltas, vec!["東京は", "晴れ", "です。"]);
+        let response: ChatResponse = serde_json::from_value(value).unwrap();
+        let msg = &response.choices[0].message;
+        assert_eq!(msg.content.as_deref(), Some("東京は晴れです。"));
+        assert!(msg.tool_calls.is_empty());
+        assert_eq!(response.usage.unwrap().total_tokens, 18);
+    }
+
+    #[test]
+    fn test_tool_call_fragments_are_reassembled() {
+        let mut deltas = Vec::new();
+        let value =
+            assemble_sse(TOOL_CALLS_STREAM.as_bytes(), |d| deltas.push(d.to_string())).unwrap();
+
+        assert!(deltas.is_empty());
+        let response: ChatResponse = serde_json::from_value(value).unwrap();
+        let msg = &response.choices[0].message;
+        assert_eq!(msg.content, None);
+        assert_eq!(msg.tool_calls.len(), 2);
+
+        assert_eq!(msg.tool_calls[0].id, "call_tokyo");
+        assert_eq!(msg.tool_calls[0].call_type, "function");
+        assert_eq!(msg.tool_calls[0].function.name, "fetch_weather");
+        assert_eq!(msg.tool_calls[0].function.arguments, r#"{"city":"東京"}"#);
+
+        assert_eq!(msg.tool_calls[1].id, "call_osaka");
+        assert_eq!(msg.tool_calls[1].function.arguments, r#"{"city":"大阪"}"#);
+        assert_eq!(response.usage.unwrap().prompt_tokens, 120);
+    }
+
+    #[test]
+    fn test_broken_chunk_is_error() {
+        let err = assemble_sse(b"data: {oops\n\n", |_| {}).unwrap_err();
+        assert!(matches!(err, OpenAiCallError::JsonParse(_)));
+    }
+}
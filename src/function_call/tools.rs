@@ -1,36 +1,84 @@
-use std::collections::HashMap;
+use std::future::Future;
 
-use crate::function_call::errors::{ToolCallError, ToolCallOutcome};
-use crate::function_call::types::{ChatResponse, SumArgs};
+use futures::stream::{self, StreamExt};
 
-pub type NumFunc = fn(f64, f64) -> f64;
+use crate::function_call::types::ToolCall;
 
-pub fn execute_first_tool_call(
-    parsed: &ChatResponse,
-    registry: &HashMap<String, NumFunc>,
-) -> Result<ToolCallOutcome, ToolCallError> {
-    let first_choice = parsed.choices.first().ok_or(ToolCallError::EmptyChoices)?;
-    let msg = &first_choice.message;
-    if msg.tool_calls.is_empty() {
-        return Ok(ToolCallOutcome::NoToolCall);
+/// 1 ターン分の tool_calls を最大 `limit` 件ずつ並行実行する
+///
+/// 結果は完了順ではなく `tool_calls` と同じ順で返す
+/// （tool メッセージを tool_call_id の順に積み直すため）。
+pub async fn run_tool_calls<'a, F, Fut, T>(
+    tool_calls: &'a [ToolCall],
+    limit: usize,
+    run: F,
+) -> Vec<T>
+where
+    F: Fn(&'a ToolCall) -> Fut,
+    Fut: Future<Output = T>,
+{
+    stream::iter(tool_calls.iter().map(run))
+        .buffered(limit.max(1))
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use crate::function_call::types::CalledFunction;
+
+    fn tool_call(id: &str, delay_ms: u64) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: CalledFunction {
+                name: "sleep".to_string(),
+                arguments: delay_ms.to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_keeps_order_and_runs_concurrently() {
+        // 後ろほど早く終わる
+        let calls = vec![tool_call("a", 120), tool_call("b", 80), tool_call("c", 40)];
+
+        let start = Instant::now();
+        let results = run_tool_calls(&calls, 4, |tc| async move {
+            let ms: u64 = tc.function.arguments.parse().unwrap();
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            tc.id.clone()
+        })
+        .await;
+
+        assert_eq!(results, vec!["a", "b", "c"]);
+        // 直列なら 240ms かかる
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_respects_limit() {
+        let calls: Vec<ToolCall> = (0..6).map(|i| tool_call(&i.to_string(), 20)).collect();
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let results = run_tool_calls(&calls, 2, |tc| {
+            let (running, peak) = (&running, &peak);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                tc.id.clone()
+            }
+        })
+        .await;
+
+        assert_eq!(results.len(), 6);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
     }
-    let tc = &msg.tool_calls[0];
-    println!(
-        "tool_calls 検出: {} (arguments raw JSON string)",
-        tc.function.name
-    );
-    let func_name = &tc.function.name;
-    let func = registry
-        .get(func_name)
-        .ok_or_else(|| ToolCallError::UnknownTool(func_name.clone()))?;
-    let raw = &tc.function.arguments;
-    let args: SumArgs = serde_json::from_str(raw)
-        .map_err(|e| ToolCallError::ArgParse(format!("{e}; raw: {raw}")))?;
-    let result = func(args.a, args.b);
-    Ok(ToolCallOutcome::Executed {
-        tool_name: func_name.clone(),
-        result_number: result,
-    tool_call_id: tc.id.clone(),
-    raw_arguments: raw.clone(),
-    })
 }
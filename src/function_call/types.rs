@@ -1,9 +1,37 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::function_call::number::NumberArg;
+use crate::function_call::schema::{ToolArgs, tool_args};
+
 #[derive(Debug, Deserialize)]
 pub struct ChatResponse {
     pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// トークン使用量（レスポンスの usage）
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -20,7 +48,7 @@ pub struct IncomingMessage {
     pub tool_calls: Vec<ToolCall>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCall {
     pub id: String,
     #[serde(rename = "type")]
@@ -28,33 +56,295 @@ pub struct ToolCall {
     pub function: CalledFunction,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CalledFunction {
     pub name: String,
     pub arguments: String,
 }
 
-#[derive(Debug, Serialize)]
-pub struct OutgoingMessage<'a> {
-    pub role: &'a str,
-    pub content: &'a str,
+/// 会話の 1 メッセージ（role ごとに持てる項目が違う）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum ChatMessage {
+    System {
+        content: String,
+    },
+    User {
+        content: String,
+    },
+    /// モデルの発言（tool_calls だけのときは content が null）
+    Assistant {
+        #[serde(default)]
+        content: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tool_calls: Vec<ToolCall>,
+    },
+    /// ツールの実行結果（どの呼び出しへの応答かを tool_call_id で示す）
+    Tool {
+        tool_call_id: String,
+        content: String,
+    },
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        ChatMessage::System {
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        ChatMessage::User {
+            content: content.into(),
+        }
+    }
+
+    /// 最終回答など、本文だけの assistant メッセージ
+    pub fn assistant(content: impl Into<String>) -> Self {
+        ChatMessage::Assistant {
+            content: Some(content.into()),
+            tool_calls: Vec::new(),
+        }
+    }
+
+    /// tool_calls を含む assistant メッセージ（次のリクエストでそのまま送り返す）
+    pub fn assistant_tool_calls(content: Option<String>, tool_calls: Vec<ToolCall>) -> Self {
+        ChatMessage::Assistant {
+            content,
+            tool_calls,
+        }
+    }
+
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        ChatMessage::Tool {
+            tool_call_id: tool_call_id.into(),
+            content: content.into(),
+        }
+    }
+
+    pub fn role(&self) -> &'static str {
+        match self {
+            ChatMessage::System { .. } => "system",
+            ChatMessage::User { .. } => "user",
+            ChatMessage::Assistant { .. } => "assistant",
+            ChatMessage::Tool { .. } => "tool",
+        }
+    }
+
+    /// 本文（assistant の tool_calls だけのメッセージは None）
+    pub fn content(&self) -> Option<&str> {
+        match self {
+            ChatMessage::System { content }
+            | ChatMessage::User { content }
+            | ChatMessage::Tool { content, .. } => Some(content),
+            ChatMessage::Assistant { content, .. } => content.as_deref(),
+        }
+    }
+}
+
+/// Chat Completions のリクエスト
+///
+/// `ChatRequest::new(model, &messages)` に続けて必要な項目だけ設定する。
+/// 履歴とツール定義は借用するので、毎回のリクエストで複製しない。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatRequest<'a> {
+    pub model: String,
+    pub messages: Cow<'a, [ChatMessage]>,
+    #[serde(borrow, default, skip_serializing_if = "<[_]>::is_empty")]
+    pub tools: Cow<'a, [ToolDefinition<'a>]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+impl<'a> ChatRequest<'a> {
+    pub fn new(model: impl Into<String>, messages: &'a [ChatMessage]) -> Self {
+        Self {
+            model: model.into(),
+            messages: Cow::Borrowed(messages),
+            tools: Cow::Borrowed(&[]),
+            tool_choice: None,
+            parallel_tool_calls: None,
+            temperature: None,
+            max_tokens: None,
+            response_format: None,
+            stream: None,
+            stream_options: None,
+        }
+    }
+
+    /// 使えるツール（空なら tools 自体を送らない）
+    pub fn tools(mut self, tools: &'a [ToolDefinition<'a>]) -> Self {
+        self.tools = Cow::Borrowed(tools);
+        self
+    }
+
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn parallel_tool_calls(mut self, parallel: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel);
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
+    }
+
+    /// ストリーミングで受け取る（最後のチャンクで usage も受け取る）
+    pub fn streaming(mut self) -> Self {
+        self.stream = Some(true);
+        self.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+        self
+    }
+}
+
+/// ツールを使うかどうかの指定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ToolChoiceRepr", into = "ToolChoiceRepr")]
+pub enum ToolChoice {
+    Auto,
+    None,
+    Required,
+    /// 指定したツールを必ず呼ばせる
+    Function(String),
+}
+
+/// API 上の表現（"auto" などの文字列か、関数名を指定するオブジェクト）
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ToolChoiceRepr {
+    Mode(String),
+    Function {
+        r#type: String,
+        function: ToolChoiceFunction,
+    },
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Serialize, Deserialize)]
+struct ToolChoiceFunction {
+    name: String,
+}
+
+impl From<ToolChoice> for ToolChoiceRepr {
+    fn from(choice: ToolChoice) -> Self {
+        match choice {
+            ToolChoice::Auto => ToolChoiceRepr::Mode("auto".to_string()),
+            ToolChoice::None => ToolChoiceRepr::Mode("none".to_string()),
+            ToolChoice::Required => ToolChoiceRepr::Mode("required".to_string()),
+            ToolChoice::Function(name) => ToolChoiceRepr::Function {
+                r#type: "function".to_string(),
+                function: ToolChoiceFunction { name },
+            },
+        }
+    }
+}
+
+/// 知らない指定は Auto に読み替えず、デシリアライズの失敗にする
+impl TryFrom<ToolChoiceRepr> for ToolChoice {
+    type Error = String;
+
+    fn try_from(repr: ToolChoiceRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ToolChoiceRepr::Mode(mode) => match mode.as_str() {
+                "auto" => Ok(ToolChoice::Auto),
+                "none" => Ok(ToolChoice::None),
+                "required" => Ok(ToolChoice::Required),
+                _ => Err(format!(
+                    "不明な tool_choice です: {}（auto / none / required）",
+                    mode
+                )),
+            },
+            ToolChoiceRepr::Function { r#type, function } if r#type == "function" => {
+                Ok(ToolChoice::Function(function.name))
+            }
+            ToolChoiceRepr::Function { r#type, .. } => {
+                Err(format!("不明な tool_choice の type です: {}", r#type))
+            }
+        }
+    }
+}
+
+/// 応答の形式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    /// 何らかの JSON オブジェクト
+    JsonObject,
+    /// 指定した JSON Schema に沿った JSON
+    JsonSchema {
+        json_schema: JsonSchemaFormat,
+    },
+}
+
+impl ResponseFormat {
+    /// `tool_args!` で定義した構造体の形の JSON で答えさせる
+    pub fn json_schema<T: ToolArgs>(name: &str) -> Self {
+        Self::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.to_string(),
+                schema: serde_json::to_value(T::schema()).unwrap_or_default(),
+                strict: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    pub schema: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StreamOptions {
+    pub include_usage: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolDefinition<'a> {
     #[serde(rename = "type")]
     pub r#type: &'a str,
     pub function: ToolFunctionDefinition<'a>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolFunctionDefinition<'a> {
     pub name: &'a str,
     pub description: &'a str,
     pub parameters: JsonSchemaObject<'a>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JsonSchemaObject<'a> {
     #[serde(rename = "type")]
     pub r#type: &'a str,
@@ -62,8 +352,173 @@ pub struct JsonSchemaObject<'a> {
     pub required: Vec<&'a str>,
 }
 
-#[derive(serde::Deserialize)]
-pub struct SumArgs {
-    pub a: f64,
-    pub b: f64,
+tool_args! {
+    /// 2 つの数を受け取る計算ツール（calc_sum / calc_times）の引数
+    #[derive(serde::Deserialize)]
+    pub struct SumArgs {
+        /// 最初の数
+        pub a: NumberArg,
+        /// 次の数
+        pub b: NumberArg,
+    }
+}
+
+tool_args! {
+    /// calc_pow の引数（a の b 乗）
+    #[derive(serde::Deserialize)]
+    pub struct PowArgs {
+        /// 底 (base)
+        pub a: NumberArg,
+        /// 指数 (負の数や小数も可)
+        pub b: NumberArg,
+    }
+}
+
+tool_args! {
+    /// evaluate_expression の引数
+    #[derive(serde::Deserialize)]
+    pub struct ExprArgs {
+        /// 計算する式 (例: (3+4)*2-1/5、sqrt(2)*sin(pi/4))
+        pub expression: String,
+    }
+}
+
+impl From<SumArgs> for (NumberArg, NumberArg) {
+    fn from(args: SumArgs) -> Self {
+        (args.a, args.b)
+    }
+}
+
+impl From<PowArgs> for (NumberArg, NumberArg) {
+    fn from(args: PowArgs) -> Self {
+        (args.a, args.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::function_call::schema::ToolArgs;
+
+    fn tool_call() -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: CalledFunction {
+                name: "calc_sum".to_string(),
+                arguments: r#"{"a":1,"b":2}"#.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_chat_message_wire_format() {
+        let messages = [
+            ChatMessage::system("計算係"),
+            ChatMessage::user("1+2"),
+            ChatMessage::assistant_tool_calls(None, vec![tool_call()]),
+            ChatMessage::tool("call_1", "3"),
+            ChatMessage::assistant("3 です"),
+        ];
+
+        let value = serde_json::to_value(&messages).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                {"role": "system", "content": "計算係"},
+                {"role": "user", "content": "1+2"},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1", "type": "function",
+                    "function": {"name": "calc_sum", "arguments": "{\"a\":1,\"b\":2}"}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "3"},
+                {"role": "assistant", "content": "3 です"}
+            ])
+        );
+
+        let back: Vec<ChatMessage> = serde_json::from_value(value).unwrap();
+        assert_eq!(back, messages);
+    }
+
+    #[test]
+    fn test_chat_request_round_trip() {
+        let tools = [ToolDefinition {
+            r#type: "function",
+            function: ToolFunctionDefinition {
+                name: "calc_sum",
+                description: "足し算",
+                parameters: SumArgs::schema(),
+            },
+        }];
+        let messages = [ChatMessage::user("1+2")];
+        let request = ChatRequest::new("gpt-4o-mini", &messages)
+            .tools(&tools)
+            .tool_choice(ToolChoice::Function("calc_sum".to_string()))
+            .parallel_tool_calls(false)
+            .temperature(0.2)
+            .max_tokens(256)
+            .response_format(ResponseFormat::JsonSchema {
+                json_schema: JsonSchemaFormat {
+                    name: "answer".to_string(),
+                    schema: json!({"type": "object"}),
+                    strict: Some(true),
+                },
+            })
+            .streaming();
+
+        let text = serde_json::to_string(&request).unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            value["tool_choice"],
+            json!({"type": "function", "function": {"name": "calc_sum"}})
+        );
+        assert_eq!(value["response_format"]["type"], "json_schema");
+        assert_eq!(value["response_format"]["json_schema"]["name"], "answer");
+        assert_eq!(value["stream_options"], json!({"include_usage": true}));
+        assert_eq!(
+            value["tools"][0]["function"]["parameters"]["required"],
+            json!(["a", "b"])
+        );
+
+        let back: ChatRequest = serde_json::from_str(&text).unwrap();
+        assert_eq!(back, request);
+    }
+
+    #[test]
+    fn test_minimal_request_omits_unset_fields() {
+        let messages = [ChatMessage::user("こんにちは")];
+        let value = serde_json::to_value(ChatRequest::new("local", &messages)).unwrap();
+        assert_eq!(
+            value,
+            json!({"model": "local", "messages": [{"role": "user", "content": "こんにちは"}]})
+        );
+    }
+
+    #[test]
+    fn test_tool_choice_modes() {
+        for (choice, wire) in [
+            (ToolChoice::Auto, json!("auto")),
+            (ToolChoice::None, json!("none")),
+            (ToolChoice::Required, json!("required")),
+        ] {
+            assert_eq!(serde_json::to_value(&choice).unwrap(), wire);
+            assert_eq!(serde_json::from_value::<ToolChoice>(wire).unwrap(), choice);
+        }
+        let function = json!({"type": "function", "function": {"name": "calc_sum"}});
+        assert_eq!(
+            serde_json::from_value::<ToolChoice>(function).unwrap(),
+            ToolChoice::Function("calc_sum".to_string())
+        );
+        // 知らない指定は Auto にせずエラー
+        let err = serde_json::from_value::<ToolChoice>(json!("sometimes")).unwrap_err();
+        assert!(err.to_string().contains("sometimes"), "{err}");
+        let other = json!({"type": "custom", "function": {"name": "calc_sum"}});
+        assert!(serde_json::from_value::<ToolChoice>(other).is_err());
+        assert_eq!(
+            serde_json::to_value(ResponseFormat::JsonObject).unwrap(),
+            json!({"type": "json_object"})
+        );
+    }
 }
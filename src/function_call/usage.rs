@@ -0,0 +1,458 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::function_call::replay::is_replaying;
+use crate::function_call::types::Usage;
+
+/// 使用量の記録先（1 行 1 レコードの JSON Lines）
+pub const USAGE_LOG_FILE: &str = "data/usage_log.jsonl";
+
+/// 料金表の上書き（`MODEL_PRICES_FILE` がなければこのファイルがあれば読む）
+const DEFAULT_PRICE_FILE: &str = "data/model_prices.json";
+
+/// 組み込みの料金表（USD / 100 万トークン）
+const BUILTIN_PRICES: [(&str, f64, f64); 6] = [
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("o4-mini", 1.10, 4.40),
+];
+
+/// モデル 1 つ分の料金（USD / 100 万トークン）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_1m: f64,
+    pub output_per_1m: f64,
+}
+
+/// モデル名 → 料金
+#[derive(Debug, Clone)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        let prices = BUILTIN_PRICES
+            .iter()
+            .map(|&(model, input, output)| {
+                let price = ModelPrice {
+                    input_per_1m: input,
+                    output_per_1m: output,
+                };
+                (model.to_string(), price)
+            })
+            .collect();
+        Self { prices }
+    }
+}
+
+impl PriceTable {
+    /// 組み込みの料金表に、設定ファイルの内容を上書きしたもの
+    ///
+    /// ファイルは `MODEL_PRICES_FILE`（なければ data/model_prices.json）。
+    /// 形式: `{"モデル名": {"input_per_1m": 0.15, "output_per_1m": 0.6}}`
+    /// ローカルのモデルは 0 を指定すれば「無料」として集計できる。
+    pub fn from_env() -> Self {
+        let table = Self::default();
+        let path =
+            std::env::var("MODEL_PRICES_FILE").unwrap_or_else(|_| DEFAULT_PRICE_FILE.to_string());
+        if !Path::new(&path).exists() {
+            return table;
+        }
+        let result = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| table.clone().with_overrides(&text));
+        match result {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!(
+                    "⚠️ 料金表 {} を読めません（組み込みの料金を使います）: {}",
+                    path, e
+                );
+                table
+            }
+        }
+    }
+
+    /// JSON の料金表で上書き・追加する
+    pub fn with_overrides(mut self, json: &str) -> Result<Self, String> {
+        let overrides: HashMap<String, ModelPrice> =
+            serde_json::from_str(json).map_err(|e| format!("料金表の形式が不正です: {}", e))?;
+        self.prices.extend(overrides);
+        Ok(self)
+    }
+
+    /// モデルの料金（"gpt-4o-mini-2024-07-18" のような日付付きの名前は前方一致で探す）
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        if let Some(price) = self.prices.get(model) {
+            return Some(*price);
+        }
+        self.prices
+            .iter()
+            .filter(|(name, _)| {
+                model
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|rest| rest.starts_with('-'))
+            })
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// 推定料金（USD）。料金表にないモデルは None
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        let price = self.price(model)?;
+        Some(
+            (usage.prompt_tokens as f64 * price.input_per_1m
+                + usage.completion_tokens as f64 * price.output_per_1m)
+                / 1_000_000.0,
+        )
+    }
+}
+
+/// 使用量ログの 1 レコード（API 呼び出し 1 回分）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: String,
+    pub app: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageRecord {
+    /// "YYYY-MM"（月別集計のキー）
+    fn month(&self) -> &str {
+        self.timestamp.get(..7).unwrap_or(&self.timestamp)
+    }
+
+    fn usage(&self) -> Usage {
+        Usage {
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            total_tokens: self.total_tokens,
+        }
+    }
+}
+
+/// 集計値（リクエスト数・トークン数・料金）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub usage: Usage,
+    pub cost_usd: f64,
+    /// 料金表にないモデルの呼び出し数（cost_usd に含まれない）
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: Usage, cost: Option<f64>) {
+        self.requests += 1;
+        self.usage += usage;
+        match cost {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+
+    fn format(&self) -> String {
+        let mut line = format!(
+            "{} 回 / 入力 {} / 出力 {} / 合計 {} トークン / 推定 ${:.4}",
+            self.requests,
+            self.usage.prompt_tokens,
+            self.usage.completion_tokens,
+            self.usage.total_tokens,
+            self.cost_usd
+        );
+        if self.unpriced_requests > 0 {
+            line.push_str(&format!("（料金不明 {} 回）", self.unpriced_requests));
+        }
+        line
+    }
+}
+
+/// 1 つのアプリの会話中の使用量を集計し、ログに追記する
+pub struct UsageTracker {
+    app: String,
+    prices: PriceTable,
+    log_path: Option<PathBuf>,
+    session: Mutex<BTreeMap<String, UsageTotals>>, // モデル名 → 合計
+}
+
+impl UsageTracker {
+    /// 料金表は `PriceTable::from_env`、ログは data/usage_log.jsonl
+    /// （`LLM_CASSETTE` の再生中は集計だけしてログには書かない）
+    pub fn new(app: &str) -> Self {
+        let log_path = (!is_replaying()).then(|| PathBuf::from(USAGE_LOG_FILE));
+        Self::with_settings(app, PriceTable::from_env(), log_path)
+    }
+
+    /// 料金表とログの場所を指定（ログが None なら記録しない）
+    pub fn with_settings(app: &str, prices: PriceTable, log_path: Option<PathBuf>) -> Self {
+        Self {
+            app: app.to_string(),
+            prices,
+            log_path,
+            session: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// API 呼び出し 1 回分を記録する（ログの書き込み失敗は警告のみ）
+    pub fn record(&self, model: &str, usage: Usage) {
+        let cost = self.prices.cost(model, &usage);
+        if let Ok(mut session) = self.session.lock() {
+            session
+                .entry(model.to_string())
+                .or_default()
+                .add(usage, cost);
+        }
+
+        let Some(path) = &self.log_path else {
+            return;
+        };
+        let record = UsageRecord {
+            timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+            app: self.app.clone(),
+            model: model.to_string(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            cost_usd: cost,
+        };
+        if let Err(e) = append_record(path, &record) {
+            eprintln!("⚠️ 使用量を記録できません: {}", e);
+        }
+    }
+
+    /// この会話の合計
+    pub fn totals(&self) -> UsageTotals {
+        let session = self.session.lock().map(|s| s.clone()).unwrap_or_default();
+        let mut totals = UsageTotals::default();
+        for t in session.values() {
+            totals.requests += t.requests;
+            totals.usage += t.usage;
+            totals.cost_usd += t.cost_usd;
+            totals.unpriced_requests += t.unpriced_requests;
+        }
+        totals
+    }
+
+    /// 会話終了時に表示する集計
+    pub fn format_summary(&self) -> String {
+        let session = self.session.lock().map(|s| s.clone()).unwrap_or_default();
+        if session.is_empty() {
+            return "📊 トークン使用量: API 呼び出しはありませんでした".to_string();
+        }
+        let mut lines = vec![format!("📊 トークン使用量: {}", self.totals().format())];
+        if session.len() > 1 {
+            for (model, totals) in &session {
+                lines.push(format!("  {}: {}", model, totals.format()));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+fn append_record(path: &Path, record: &UsageRecord) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// 使用量ログを読む（ファイルがなければ空）
+pub fn load_records(path: &Path) -> Result<Vec<UsageRecord>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("{} を読めません: {}", path.display(), e))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("{} 行目: {}", i + 1, e)))
+        .collect()
+}
+
+/// 月別・アプリ別・モデル別の集計（`month` を指定するとその月だけ）
+pub fn format_report(records: &[UsageRecord], month: Option<&str>) -> String {
+    let mut by_month: BTreeMap<&str, BTreeMap<(&str, &str), UsageTotals>> = BTreeMap::new();
+    for record in records {
+        if month.is_some_and(|m| record.month() != m) {
+            continue;
+        }
+        by_month
+            .entry(record.month())
+            .or_default()
+            .entry((record.app.as_str(), record.model.as_str()))
+            .or_default()
+            .add(record.usage(), record.cost_usd);
+    }
+
+    if by_month.is_empty() {
+        return "📭 使用量の記録はありません".to_string();
+    }
+
+    let mut lines = Vec::new();
+    for (month, rows) in by_month {
+        let mut total = UsageTotals::default();
+        lines.push(format!("📅 {}", month));
+        for ((app, model), totals) in &rows {
+            lines.push(format!("  {} / {}: {}", app, model, totals.format()));
+            total.requests += totals.requests;
+            total.usage += totals.usage;
+            total.cost_usd += totals.cost_usd;
+            total.unpriced_requests += totals.unpriced_requests;
+        }
+        lines.push(format!("  合計: {}", total.format()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function_call::testutil::TempPath;
+
+    fn usage(prompt: u64, completion: u64) -> Usage {
+        Usage {
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            total_tokens: prompt + completion,
+        }
+    }
+
+    fn record(timestamp: &str, app: &str, model: &str, cost: Option<f64>) -> UsageRecord {
+        UsageRecord {
+            timestamp: timestamp.to_string(),
+            app: app.to_string(),
+            model: model.to_string(),
+            prompt_tokens: 100,
+            completion_tokens: 10,
+            total_tokens: 110,
+            cost_usd: cost,
+        }
+    }
+
+    #[test]
+    fn test_price_lookup_and_cost() {
+        let table = PriceTable::default();
+
+        let cost = table
+            .cost("gpt-4o-mini", &usage(1_000_000, 1_000_000))
+            .unwrap();
+        assert!((cost - 0.75).abs() < 1e-9);
+
+        // 日付付きの名前は一番長く一致するモデルの料金
+        assert_eq!(
+            table.price("gpt-4o-mini-2024-07-18"),
+            table.price("gpt-4o-mini")
+        );
+        assert_eq!(table.price("gpt-4o-2024-08-06"), table.price("gpt-4o"));
+        assert_eq!(table.price("gpt-4oo"), None);
+        assert_eq!(table.cost("llama3", &usage(10, 10)), None);
+    }
+
+    #[test]
+    fn test_price_overrides() {
+        let table = PriceTable::default()
+            .with_overrides(
+                r#"{"llama3": {"input_per_1m": 0, "output_per_1m": 0},
+                    "gpt-4o-mini": {"input_per_1m": 1, "output_per_1m": 2}}"#,
+            )
+            .unwrap();
+
+        assert_eq!(table.cost("llama3", &usage(10, 10)), Some(0.0));
+        let cost = table.cost("gpt-4o-mini", &usage(500_000, 250_000)).unwrap();
+        assert!((cost - 1.0).abs() < 1e-9);
+
+        assert!(PriceTable::default().with_overrides("[]").is_err());
+    }
+
+    #[test]
+    fn test_tracker_accumulates_and_logs() {
+        let path = TempPath::new("usage_log.jsonl");
+
+        let tracker =
+            UsageTracker::with_settings("test", PriceTable::default(), Some(path.to_path_buf()));
+        tracker.record("gpt-4o-mini", usage(1000, 200));
+        tracker.record("gpt-4o-mini", usage(500, 100));
+        tracker.record("llama3", usage(50, 5));
+
+        let totals = tracker.totals();
+        assert_eq!(totals.requests, 3);
+        assert_eq!(totals.usage, usage(1550, 305));
+        assert_eq!(totals.unpriced_requests, 1);
+        let summary = tracker.format_summary();
+        assert!(summary.contains("3 回"), "{summary}");
+        assert!(summary.contains("料金不明 1 回"), "{summary}");
+        assert!(summary.contains("llama3:"), "{summary}");
+
+        let records = load_records(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].app, "test");
+        assert_eq!(records[0].prompt_tokens, 1000);
+        assert!(records[0].cost_usd.is_some());
+        assert_eq!(records[2].cost_usd, None);
+    }
+
+    #[test]
+    fn test_format_report_groups_by_month() {
+        let records = vec![
+            record(
+                "2026-09-30T23:00:00+09:00",
+                "weather",
+                "gpt-4o-mini",
+                Some(0.5),
+            ),
+            record(
+                "2026-10-01T09:00:00+09:00",
+                "weather",
+                "gpt-4o-mini",
+                Some(0.25),
+            ),
+            record(
+                "2026-10-02T09:00:00+09:00",
+                "weather",
+                "gpt-4o-mini",
+                Some(0.25),
+            ),
+            record("2026-10-03T09:00:00+09:00", "calc", "llama3", None),
+        ];
+
+        let report = format_report(&records, None);
+        assert!(report.contains("📅 2026-09"));
+        assert!(report.contains("weather / gpt-4o-mini: 2 回"), "{report}");
+        assert!(report.contains("calc / llama3: 1 回"), "{report}");
+        assert!(report.contains("推定 $0.5000（料金不明 1 回）"), "{report}");
+
+        let october = format_report(&records, Some("2026-10"));
+        assert!(!october.contains("2026-09"));
+        assert!(format_report(&records, Some("2025-01")).contains("記録はありません"));
+    }
+
+    #[test]
+    fn test_load_records_reports_bad_line() {
+        let path = TempPath::new("usage_log_bad.jsonl");
+        fs::write(&path, "{}\n").unwrap();
+        let err = load_records(&path).unwrap_err();
+        assert!(err.starts_with("1 行目"), "{err}");
+    }
+}
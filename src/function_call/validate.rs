@@ -0,0 +1,260 @@
+use std::fmt::{Display, Formatter};
+
+use serde_json::{Map, Value};
+
+/// スキーマ違反 1 件（どこが・なぜ）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// `$.city` や `$.items[0]` のような場所
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// 複数の違反を 1 つの文字列に（モデルへ返すエラー用）
+pub fn format_errors(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(ValidationError::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// JSON Schema のサブセットで値を検証し、違反をすべて返す
+///
+/// 対応するキーワード: type（配列も可）, enum, properties, required,
+/// additionalProperties（false のみ）, items, minimum, maximum,
+/// minLength, maxLength, minItems, maxItems。
+/// required でないプロパティの null は「省略」とみなして受け付ける
+/// （strict モードのモデルは省略の代わりに null を送ることがあるため）。
+pub fn validate(value: &Value, schema: &Value) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    check(value, schema, "$", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        // 1.0 のような整数値の小数も integer として扱う
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn check(value: &Value, schema: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let mut push = |message: String| {
+        errors.push(ValidationError {
+            path: path.to_string(),
+            message,
+        })
+    };
+
+    // 型が違えば、それ以上のキーワードは見ない
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| matches_type(value, t)) {
+        push(format!(
+            "{} が必要です（実際: {}）",
+            types.join(" / "),
+            type_name(value)
+        ));
+        return;
+    }
+
+    if let Some(allowed) = schema["enum"].as_array()
+        && !allowed.contains(value)
+    {
+        let list: Vec<String> = allowed.iter().map(Value::to_string).collect();
+        push(format!(
+            "{} のいずれかが必要です（実際: {}）",
+            list.join(", "),
+            value
+        ));
+    }
+
+    if let Some(n) = value.as_f64() {
+        if let Some(min) = schema["minimum"].as_f64()
+            && n < min
+        {
+            push(format!("{} 以上が必要です（実際: {}）", min, n));
+        }
+        if let Some(max) = schema["maximum"].as_f64()
+            && n > max
+        {
+            push(format!("{} 以下が必要です（実際: {}）", max, n));
+        }
+    }
+
+    if let Some(s) = value.as_str() {
+        let len = s.chars().count() as u64;
+        if let Some(min) = schema["minLength"].as_u64()
+            && len < min
+        {
+            push(format!("{} 文字以上が必要です（実際: {} 文字）", min, len));
+        }
+        if let Some(max) = schema["maxLength"].as_u64()
+            && len > max
+        {
+            push(format!("{} 文字以下が必要です（実際: {} 文字）", max, len));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        let len = items.len() as u64;
+        if let Some(min) = schema["minItems"].as_u64()
+            && len < min
+        {
+            push(format!("{} 個以上が必要です（実際: {} 個）", min, len));
+        }
+        if let Some(max) = schema["maxItems"].as_u64()
+            && len > max
+        {
+            push(format!("{} 個以下が必要です（実際: {} 個）", max, len));
+        }
+        if schema["items"].is_object() {
+            for (i, item) in items.iter().enumerate() {
+                check(item, &schema["items"], &format!("{}[{}]", path, i), errors);
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        check_object(object, schema, path, errors);
+    }
+}
+
+fn check_object(
+    object: &Map<String, Value>,
+    schema: &Value,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let properties = schema["properties"].as_object();
+
+    for name in &required {
+        if object.get(*name).is_none_or(Value::is_null) {
+            errors.push(ValidationError {
+                path: format!("{}.{}", path, name),
+                message: "必須の項目がありません".to_string(),
+            });
+        }
+    }
+
+    for (name, value) in object {
+        let child = format!("{}.{}", path, name);
+        match properties.and_then(|p| p.get(name)) {
+            Some(_) if value.is_null() && !required.contains(&name.as_str()) => {}
+            Some(property) => check(value, property, &child, errors),
+            None if schema["additionalProperties"] == Value::Bool(false) => {
+                errors.push(ValidationError {
+                    path: child,
+                    message: "定義されていない項目です".to_string(),
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "city": {"type": "string", "minLength": 1},
+                "days": {"type": "integer", "minimum": 1, "maximum": 7},
+                "unit": {"type": "string", "enum": ["c", "f"]},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2},
+                "lat": {"type": "number"}
+            },
+            "required": ["city", "days"],
+            "additionalProperties": false
+        })
+    }
+
+    fn messages(value: Value) -> Vec<String> {
+        validate(&value, &schema())
+            .unwrap_err()
+            .iter()
+            .map(ValidationError::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_values() {
+        assert!(validate(&json!({"city": "東京", "days": 3}), &schema()).is_ok());
+        // required でない項目の null は省略扱い、整数値の小数は integer
+        let value = json!({"city": "東京", "days": 2.0, "lat": null, "tags": ["a"]});
+        assert!(validate(&value, &schema()).is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_violation_with_path() {
+        let errors = messages(json!({
+            "days": "3",
+            "unit": "k",
+            "tags": ["a", 1, "c"],
+            "extra": true
+        }));
+        assert_eq!(
+            errors,
+            vec![
+                "$.city: 必須の項目がありません",
+                "$.days: integer が必要です（実際: string）",
+                "$.extra: 定義されていない項目です",
+                "$.tags: 2 個以下が必要です（実際: 3 個）",
+                "$.tags[1]: string が必要です（実際: integer）",
+                r#"$.unit: "c", "f" のいずれかが必要です（実際: "k"）"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ranges_and_lengths() {
+        let errors = messages(json!({"city": "", "days": 10}));
+        assert_eq!(
+            errors,
+            vec![
+                "$.city: 1 文字以上が必要です（実際: 0 文字）",
+                "$.days: 7 以下が必要です（実際: 10）",
+            ]
+        );
+        assert_eq!(
+            messages(json!([])),
+            vec!["$: object が必要です（実際: array）"]
+        );
+    }
+}
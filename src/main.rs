@@ -1,19 +1,40 @@
-mod openai;
+pub mod openai;
 
 pub mod function_call {
+    pub mod agent;
     pub mod api;
+    pub mod context;
     pub mod errors;
+    pub mod expr;
+    pub mod number;
     pub mod play;
+    pub mod registry;
+    pub mod replay;
+    pub mod schema;
+    pub mod session;
+    pub mod stream;
+    #[cfg(test)]
+    pub mod testutil;
     pub mod tools;
     pub mod types;
+    pub mod usage;
+    pub mod validate;
 }
 
 pub mod memo;
 pub mod weather;
 
 fn main() {
-    // メモアプリを起動
-    if let Err(e) = memo::cli::run() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // `weather` は天気アプリ、`calc` は計算アプリ、`chat` はチャット、それ以外はメモアプリを起動
+    let result = match args.get(1).map(String::as_str) {
+        Some("weather") => weather::cli::run(&args[2..]),
+        Some("calc") => function_call::play::run(&args[2..]),
+        Some("chat") => openai::run(&args[2..]),
+        _ => memo::cli::run(),
+    };
+    if let Err(e) = result {
         eprintln!("エラー: {}", e);
         std::process::exit(1);
     }
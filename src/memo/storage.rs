@@ -45,10 +45,10 @@ fn save_memos_to_path(memo_list: &MemoList, file_path: &str) -> Result<(), Strin
     let path = Path::new(file_path);
 
     // data/ ディレクトリがなければ作成
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
-        }
+    if let Some(parent) = path.parent()
+        && !parent.exists()
+    {
+        fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
     }
 
     // MemoList を JSON 文字列に変換（整形付き）
@@ -15,6 +15,12 @@ pub struct MemoList {
     pub memos: Vec<Memo>, // ← pub を追加
 }
 
+impl Default for MemoList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MemoList {
     /// 空のメモリストを作成
     pub fn new() -> Self {
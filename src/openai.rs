@@ -1,76 +1,203 @@
 use dotenv::dotenv;
 // use openai::openai_function_call;
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use serde::Deserialize;
-use serde_json::json;
-use std::env;
-use std::io;
-
-#[derive(Debug, Deserialize)]
-struct AiResponse {
-    _id: String,
-    _object: String,
-    _model: String,
-    choices: Vec<Choice>,
-    _usage: Usage,
-    _service_tier: String,
-    _system_fingerprint: String,
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use crate::function_call::api::{ChatTransport, EndpointConfig, RetryNotice};
+use crate::function_call::replay::transport_from_env;
+use crate::function_call::session::{SessionStore, parse_resume_args};
+use crate::function_call::types::{ChatMessage, ChatRequest, ChatResponse};
+use crate::function_call::usage::UsageTracker;
+
+const SYSTEM_PROMPT: &str = "あなたは親切なアシスタントです";
+
+/// 1 ターン分を送信して返答の本文を返す（使用量は `usage` に記録する）
+async fn send_turn(
+    transport: &dyn ChatTransport,
+    model: &str,
+    messages: &[ChatMessage],
+    usage: &UsageTracker,
+) -> Result<String, String> {
+    let raw = transport
+        .send(&ChatRequest::new(model, messages))
+        .await
+        .map_err(|e| e.to_string())?;
+    let response: ChatResponse =
+        serde_json::from_str(&raw).map_err(|e| format!("JSON パースエラー: {}", e))?;
+    if let Some(u) = response.usage {
+        // 実際に応答したモデル名（日付付き）があればそちらで記録する
+        usage.record(response.model.as_deref().unwrap_or(model), u);
+    }
+    reply_content(response)
 }
 
-#[derive(Debug, Deserialize)]
-struct Choice {
-    _index: u32,
-    message: Message,
-    _finish_reason: String,
+/// レスポンスから返答の本文を取り出す
+///
+/// `content` が null（拒否や tool_calls のみの応答）のときもエラーとして扱う。
+fn reply_content(response: ChatResponse) -> Result<String, String> {
+    let choice = response
+        .choices
+        .into_iter()
+        .next()
+        .ok_or("choices が空です")?;
+    choice
+        .message
+        .content
+        .ok_or_else(|| "応答に本文がありません".to_string())
 }
 
-#[derive(Debug, Deserialize)]
-struct Message {
-    _role: String,
-    content: String,
+/// チャットアプリの CLI エントリーポイント（`cargo run -- chat [--resume <id>]`）
+pub fn run(args: &[String]) -> Result<(), String> {
+    let resume = parse_resume_args(args)?;
+    dotenv().ok();
+    play_with(resume).map_err(|e| e.to_string())
 }
 
-#[derive(Debug, Deserialize)]
-struct Usage {
-    _prompt_tokens: u32,
-    _completion_tokens: u32,
-    _total_tokens: u32,
+pub fn play() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok(); // .env を読み込む
+    play_with(None)
 }
 
+/// 会話は 1 ターンごとに data/sessions/ に保存（weather sessions list で確認できる）
 #[tokio::main]
-pub async fn play() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv().ok(); // .env を読み込む
-    let api_key = env::var("OPENAI_API_KEY")?;
-
-    println!("伝えたいことを入力してください");
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).expect("入力エラー");
-
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .header(AUTHORIZATION, format!("Bearer {}", api_key))
-        .header(CONTENT_TYPE, "application/json")
-        .json(&json!({
-            "model": "gpt-4o-mini",
-            "messages": [
-                {"role": "system", "content": "あなたは親切なアシスタントです"},
-                {"role": "user", "content": input}
-            ]
-        }))
-        .send()
-        .await?;
-
-    let text = response.text().await?;
-    let json_value: AiResponse = serde_json::from_str(&text)?;
-    // contentを安全に取り出す
-    if let Some(first_choice) = json_value.choices.first() {
-        println!("content: {}", first_choice.message.content);
-    } else {
-        println!("choices が空です");
+pub async fn play_with(resume: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let store = SessionStore::default();
+    let (mut session, mut messages) = match resume {
+        Some(id) => {
+            let session = store.resume(&id, "chat", SYSTEM_PROMPT)?;
+            println!(
+                "📂 会話 {} を再開しました（{} 発言）",
+                session.id,
+                session.turns()
+            );
+            let messages = session.messages.clone();
+            (session, messages)
+        }
+        None => (
+            store.create("chat"),
+            vec![ChatMessage::system(SYSTEM_PROMPT)],
+        ),
+    };
+
+    // 接続先は CHAT_BASE_URL / CHAT_MODEL / CHAT_API_KEY（なければ OPENAI_*）
+    let endpoint = EndpointConfig::from_env("CHAT", "gpt-4o-mini");
+    let model = endpoint.model.clone();
+    let mut transport = transport_from_env(endpoint)?;
+    transport.set_on_retry(Arc::new(|notice: &RetryNotice| {
+        eprintln!(
+            "⏳ {}。{:.1} 秒後に再試行します ({}/{})",
+            notice.error,
+            notice.wait.as_secs_f64(),
+            notice.attempt,
+            notice.max_retries
+        )
+    }));
+    let usage = UsageTracker::new("chat");
+
+    println!("伝えたいことを入力してください（/exit で終了）");
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        // 0 バイト = EOF（Ctrl-D）
+        if io::stdin().read_line(&mut input)? == 0 {
+            break;
+        }
+        let text = match input.trim() {
+            "" => continue,
+            "/exit" | "/quit" => break,
+            text => text,
+        };
+
+        messages.push(ChatMessage::user(text));
+        let reply = send_turn(transport.as_ref(), &model, &messages, &usage).await;
+        match reply {
+            Ok(content) => {
+                println!("content: {}", content);
+                messages.push(ChatMessage::assistant(content));
+            }
+            Err(e) => {
+                eprintln!("エラー: {}", e);
+                messages.pop();
+                continue;
+            }
+        }
+
+        session.messages = messages.clone();
+        if let Err(e) = store.save(&mut session) {
+            eprintln!("⚠️ 会話を保存できません: {}", e);
+        }
     }
     // println!("レスポンス: {}", message);
     // println!("{}", text);
 
+    println!("{}", usage.format_summary());
+    if session.turns() > 0 {
+        println!(
+            "💾 会話 {} を保存しました（chat --resume {} で再開）",
+            session.id, session.id
+        );
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function_call::replay::ReplayTransport;
+    use crate::function_call::usage::PriceTable;
+
+    fn parse(raw: &str) -> ChatResponse {
+        serde_json::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn test_reply_content() {
+        let raw = r#"{"choices": [{"message": {"role": "assistant", "content": "やあ"}}]}"#;
+        assert_eq!(reply_content(parse(raw)).unwrap(), "やあ");
+
+        // content が null でもパースには失敗しない
+        let raw = r#"{"choices": [{"message": {"role": "assistant", "content": null}}]}"#;
+        assert_eq!(
+            reply_content(parse(raw)).unwrap_err(),
+            "応答に本文がありません"
+        );
+
+        assert_eq!(
+            reply_content(parse(r#"{"choices": []}"#)).unwrap_err(),
+            "choices が空です"
+        );
+    }
+
+    /// 記録した 2 ターンの会話をオフラインで再生する（2 ターン目は履歴付きで送る）
+    #[tokio::test]
+    async fn test_send_turn_replays_recorded_conversation() {
+        let cassette = serde_json::from_str(include_str!("testdata/chat_two_turns.json")).unwrap();
+        let transport = ReplayTransport::new(cassette);
+        let usage = UsageTracker::with_settings("chat", PriceTable::default(), None);
+        let mut messages = vec![ChatMessage::system(SYSTEM_PROMPT)];
+
+        let mut replies = Vec::new();
+        for text in ["こんにちは", "さっき何と言いましたか"] {
+            messages.push(ChatMessage::user(text));
+            let reply = send_turn(&transport, "gpt-4o-mini", &messages, &usage)
+                .await
+                .unwrap();
+            messages.push(ChatMessage::assistant(&reply));
+            replies.push(reply);
+        }
+
+        assert_eq!(
+            replies,
+            vec![
+                "こんにちは！今日はどんなお手伝いをしましょうか？",
+                "「こんにちは！今日はどんなお手伝いをしましょうか？」とお返事しました。"
+            ]
+        );
+        assert_eq!(transport.remaining(), 0);
+        let totals = usage.totals();
+        assert_eq!(totals.requests, 2);
+        assert_eq!(totals.usage.total_tokens, 113);
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+
+use chrono::{Days, NaiveDate};
+
+use crate::weather::api::{fetch_forecast_by_coords, fetch_weather_by_coords, geocode_city};
+use crate::weather::storage::WeatherStore;
+use crate::weather::types::{
+    AlertCondition, AlertMetric, AlertRule, AlertStorage, AlertWhen, Comparison, ForecastEntry,
+    WeatherInfo,
+};
+
+/// 英語の天気キーワードと日本語の説明に含まれる文字の対応
+// なぜ: API は lang=ja で説明を返すので "rain" のままだと一致しない
+const WEATHER_SYNONYMS: &[(&str, &[&str])] = &[
+    ("rain", &["雨"]),
+    ("snow", &["雪"]),
+    ("clear", &["晴"]),
+    ("sunny", &["晴"]),
+    ("cloud", &["雲", "曇"]),
+    ("thunder", &["雷"]),
+    ("storm", &["雷", "嵐"]),
+    ("fog", &["霧"]),
+];
+
+/// ルール文字列を解析
+///
+/// 対応する書き方:
+/// * `東京 temp > 30` / `東京 気温 > 30 明日` / `大阪 humidity < 40`
+/// * `rain in 大阪 tomorrow` / `雪 in 札幌`
+/// * `New York temp > 30` / `rain in San Francisco`（都市名に空白を含んでもよい）
+///
+/// 時点（now / tomorrow / 今 / 明日）を省略した場合は現在の天気で判定する
+pub fn parse_rule(text: &str) -> Result<AlertRule, String> {
+    let invalid = || {
+        format!(
+            "ルールを解釈できません: 「{}」（例: \"東京 temp > 30\", \"rain in 大阪 tomorrow\"）",
+            text
+        )
+    };
+
+    // "temp>30" のように詰めて書かれても分割できるよう記号の前後に空白を入れる
+    let normalized = text.replace('>', " > ").replace('<', " < ");
+    let mut tokens: Vec<&str> = normalized.split_whitespace().collect();
+
+    let when = match tokens.last().copied().and_then(parse_when) {
+        Some(when) => {
+            tokens.pop();
+            when
+        }
+        None => AlertWhen::Now,
+    };
+
+    // なぜ: 都市名は "New York" のように空白を含みうるので、条件部分を先に切り出す
+    let (city, condition) = match tokens.as_slice() {
+        [city @ .., metric, op, value] if !city.is_empty() && parse_metric(metric).is_some() => {
+            let metric = parse_metric(metric).ok_or_else(invalid)?;
+            let op = match *op {
+                ">" => Comparison::Above,
+                "<" => Comparison::Below,
+                _ => return Err(invalid()),
+            };
+            let value: f64 = value.parse().map_err(|_| invalid())?;
+            (
+                city.join(" "),
+                AlertCondition::Threshold { metric, op, value },
+            )
+        }
+        _ => {
+            let at = tokens.iter().position(|t| *t == "in").ok_or_else(invalid)?;
+            let (keyword, city) = (&tokens[..at], &tokens[at + 1..]);
+            if keyword.is_empty() || city.is_empty() {
+                return Err(invalid());
+            }
+            (
+                city.join(" "),
+                AlertCondition::Weather {
+                    keyword: keyword.join(" "),
+                },
+            )
+        }
+    };
+
+    Ok(AlertRule {
+        id: 0,
+        city,
+        condition,
+        when,
+    })
+}
+
+fn parse_when(token: &str) -> Option<AlertWhen> {
+    match token.to_lowercase().as_str() {
+        "now" | "今" | "現在" => Some(AlertWhen::Now),
+        "tomorrow" | "明日" => Some(AlertWhen::Tomorrow),
+        _ => None,
+    }
+}
+
+fn parse_metric(token: &str) -> Option<AlertMetric> {
+    match token.to_lowercase().as_str() {
+        "temp" | "temperature" | "気温" => Some(AlertMetric::Temp),
+        "humidity" | "湿度" => Some(AlertMetric::Humidity),
+        _ => None,
+    }
+}
+
+/// 天気の説明がキーワードに当てはまるか（英語キーワードは日本語にも対応）
+pub fn weather_matches(keyword: &str, description: &str) -> bool {
+    let keyword = keyword.to_lowercase();
+    if description.to_lowercase().contains(&keyword) {
+        return true;
+    }
+    WEATHER_SYNONYMS
+        .iter()
+        .filter(|(en, _)| keyword.contains(en))
+        .any(|(_, ja)| ja.iter().any(|j| description.contains(j)))
+}
+
+fn compare(op: Comparison, actual: f64, threshold: f64) -> bool {
+    match op {
+        Comparison::Above => actual > threshold,
+        Comparison::Below => actual < threshold,
+    }
+}
+
+fn metric_value(metric: AlertMetric, temperature: f64, humidity: u32) -> f64 {
+    match metric {
+        AlertMetric::Temp => temperature,
+        AlertMetric::Humidity => humidity as f64,
+    }
+}
+
+fn metric_unit(metric: AlertMetric) -> &'static str {
+    match metric {
+        AlertMetric::Temp => "℃",
+        AlertMetric::Humidity => "%",
+    }
+}
+
+/// 1 つのルールを判定し、発火したら通知文を返す
+///
+/// * `current` - 現在の天気（now のルールで使用）
+/// * `forecast` - 予報（tomorrow のルールで使用）
+/// * `tomorrow` - 「明日」とみなす日付
+pub fn evaluate_rule(
+    rule: &AlertRule,
+    current: Option<&WeatherInfo>,
+    forecast: &[ForecastEntry],
+    tomorrow: NaiveDate,
+) -> Option<String> {
+    let detail = match rule.when {
+        AlertWhen::Now => evaluate_now(&rule.condition, current?),
+        AlertWhen::Tomorrow => {
+            let entries: Vec<&ForecastEntry> = forecast
+                .iter()
+                .filter(|e| e.time.date() == tomorrow)
+                .collect();
+            evaluate_tomorrow(&rule.condition, &entries)
+        }
+    }?;
+
+    Some(format!("🔔 [id:{}] {}: {}", rule.id, rule, detail))
+}
+
+fn evaluate_now(condition: &AlertCondition, info: &WeatherInfo) -> Option<String> {
+    match condition {
+        AlertCondition::Threshold { metric, op, value } => {
+            let actual = metric_value(*metric, info.temperature, info.humidity);
+            compare(*op, actual, *value).then(|| format!("現在 {}{}", actual, metric_unit(*metric)))
+        }
+        AlertCondition::Weather { keyword } => weather_matches(keyword, &info.weather)
+            .then(|| format!("現在の天気は「{}」", info.weather)),
+    }
+}
+
+fn evaluate_tomorrow(condition: &AlertCondition, entries: &[&ForecastEntry]) -> Option<String> {
+    match condition {
+        AlertCondition::Threshold { metric, op, value } => {
+            let values = entries
+                .iter()
+                .map(|e| metric_value(*metric, e.temperature, e.humidity));
+            // なぜ: 「超える」なら最大値、「下回る」なら最小値が代表値
+            let extreme = match op {
+                Comparison::Above => values.fold(None, |acc: Option<f64>, v| {
+                    Some(acc.map_or(v, |a| a.max(v)))
+                }),
+                Comparison::Below => values.fold(None, |acc: Option<f64>, v| {
+                    Some(acc.map_or(v, |a| a.min(v)))
+                }),
+            }?;
+            let label = match op {
+                Comparison::Above => "最高",
+                Comparison::Below => "最低",
+            };
+            compare(*op, extreme, *value)
+                .then(|| format!("明日の予報で{} {}{}", label, extreme, metric_unit(*metric)))
+        }
+        AlertCondition::Weather { keyword } => entries
+            .iter()
+            .find(|e| weather_matches(keyword, &e.weather))
+            .map(|e| format!("明日 {} に「{}」の予報", e.time.format("%H:%M"), e.weather)),
+    }
+}
+
+/// ルール一覧を整形
+pub fn format_rules(storage: &AlertStorage) -> String {
+    if storage.rules.is_empty() {
+        return "📭 登録されているアラートはありません".to_string();
+    }
+
+    let mut output = String::from("🔔 アラート一覧:\n");
+    for rule in &storage.rules {
+        output.push_str(&format!("[id:{}] {}\n", rule.id, rule));
+    }
+    output
+}
+
+/// 登録済みのルールを全て判定し、発火したものを返す
+///
+/// 都市ごとに天気・予報を 1 回だけ取得する。
+/// 取得に失敗した都市はその旨を結果に含め、他の都市の判定は続ける
+pub async fn check_alerts(store: &WeatherStore) -> Result<String, String> {
+    let storage = store.load_alerts()?;
+    if storage.rules.is_empty() {
+        return Ok("📭 登録されているアラートはありません".to_string());
+    }
+
+    let mut by_city: BTreeMap<&str, Vec<&AlertRule>> = BTreeMap::new();
+    for rule in &storage.rules {
+        by_city.entry(rule.city.as_str()).or_default().push(rule);
+    }
+
+    let mut lines = Vec::new();
+    for (city, rules) in by_city {
+        match evaluate_city(city, &rules).await {
+            Ok(hits) => lines.extend(hits),
+            Err(e) => lines.push(format!("⚠️ {} の天気を取得できませんでした: {}", city, e)),
+        }
+    }
+
+    if lines.is_empty() {
+        return Ok("✅ 条件に当てはまるアラートはありません".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// 1 都市分のルールを判定
+///
+/// 「明日」はその都市の現地日付で数える（実行しているマシンの日付ではない）
+async fn evaluate_city(city: &str, rules: &[&AlertRule]) -> Result<Vec<String>, String> {
+    let candidates = geocode_city(city).await?;
+    let location = candidates
+        .first()
+        .ok_or_else(|| format!("「{}」に該当する地点が見つかりません", city))?;
+
+    // なぜ: 現地時刻を知るため、tomorrow のルールだけでも現在の天気は取得する
+    let current = fetch_weather_by_coords(location.lat, location.lon).await?;
+    let tomorrow = local_tomorrow(&current)?;
+    let forecast = if rules.iter().any(|r| r.when == AlertWhen::Tomorrow) {
+        fetch_forecast_by_coords(location.lat, location.lon).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(rules
+        .iter()
+        .filter_map(|rule| evaluate_rule(rule, Some(&current), &forecast, tomorrow))
+        .collect())
+}
+
+/// 観測地点の現地日付での「明日」
+fn local_tomorrow(info: &WeatherInfo) -> Result<NaiveDate, String> {
+    info.local_time
+        .date()
+        .checked_add_days(Days::new(1))
+        .ok_or_else(|| "日付の計算に失敗しました".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn info(weather: &str, temperature: f64, humidity: u32) -> WeatherInfo {
+        WeatherInfo {
+            city: "東京".to_string(),
+            weather: weather.to_string(),
+            temperature,
+            humidity,
+            lat: 35.68,
+            lon: 139.76,
+            local_time: NaiveDateTime::parse_from_str("2025-10-05 12:00", "%Y-%m-%d %H:%M")
+                .unwrap(),
+        }
+    }
+
+    fn entry(time: &str, weather: &str, temperature: f64) -> ForecastEntry {
+        ForecastEntry {
+            time: NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap(),
+            weather: weather.to_string(),
+            temperature,
+            humidity: 60,
+        }
+    }
+
+    fn tomorrow() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 10, 6).unwrap()
+    }
+
+    fn forecast() -> Vec<ForecastEntry> {
+        vec![
+            entry("2025-10-05 21:00", "大雨", 35.0), // 今日なので対象外
+            entry("2025-10-06 09:00", "晴天", 24.0),
+            entry("2025-10-06 15:00", "小雨", 29.5),
+            entry("2025-10-07 09:00", "雪", 2.0), // 明後日なので対象外
+        ]
+    }
+
+    #[test]
+    fn test_parse_threshold_rule() {
+        let rule = parse_rule("東京 temp > 30").unwrap();
+        assert_eq!(rule.city, "東京");
+        assert_eq!(
+            rule.condition,
+            AlertCondition::Threshold {
+                metric: AlertMetric::Temp,
+                op: Comparison::Above,
+                value: 30.0
+            }
+        );
+        assert_eq!(rule.when, AlertWhen::Now);
+    }
+
+    #[test]
+    fn test_parse_threshold_rule_japanese_without_spaces() {
+        let rule = parse_rule("札幌 湿度<40 明日").unwrap();
+        assert_eq!(
+            rule.condition,
+            AlertCondition::Threshold {
+                metric: AlertMetric::Humidity,
+                op: Comparison::Below,
+                value: 40.0
+            }
+        );
+        assert_eq!(rule.when, AlertWhen::Tomorrow);
+    }
+
+    #[test]
+    fn test_parse_weather_rule() {
+        let rule = parse_rule("rain in 大阪 tomorrow").unwrap();
+        assert_eq!(rule.city, "大阪");
+        assert_eq!(
+            rule.condition,
+            AlertCondition::Weather {
+                keyword: "rain".to_string()
+            }
+        );
+        assert_eq!(rule.when, AlertWhen::Tomorrow);
+    }
+
+    #[test]
+    fn test_parse_rules_with_multi_word_city() {
+        let rule = parse_rule("New York temp > 30").unwrap();
+        assert_eq!(rule.city, "New York");
+        assert_eq!(
+            rule.condition,
+            AlertCondition::Threshold {
+                metric: AlertMetric::Temp,
+                op: Comparison::Above,
+                value: 30.0
+            }
+        );
+
+        let rule = parse_rule("rain in San Francisco tomorrow").unwrap();
+        assert_eq!(rule.city, "San Francisco");
+        assert_eq!(
+            rule.condition,
+            AlertCondition::Weather {
+                keyword: "rain".to_string()
+            }
+        );
+        assert_eq!(rule.when, AlertWhen::Tomorrow);
+    }
+
+    #[test]
+    fn test_parse_rule_invalid() {
+        assert!(parse_rule("").is_err());
+        assert!(parse_rule("東京 wind > 10").is_err());
+        assert!(parse_rule("東京 temp = 30").is_err());
+        assert!(parse_rule("東京 temp > hot").is_err());
+        assert!(parse_rule("rain at 大阪").is_err());
+        assert!(parse_rule("temp > 30").is_err());
+        assert!(parse_rule("rain in").is_err());
+        assert!(parse_rule("in 大阪").is_err());
+    }
+
+    #[test]
+    fn test_weather_matches_synonyms() {
+        assert!(weather_matches("rain", "小雨"));
+        assert!(weather_matches("Rain", "適度な雨"));
+        assert!(weather_matches("雨", "小雨"));
+        assert!(weather_matches("cloud", "曇りがち"));
+        assert!(!weather_matches("rain", "晴天"));
+    }
+
+    #[test]
+    fn test_evaluate_now_threshold() {
+        let rule = parse_rule("東京 temp > 30").unwrap();
+
+        let hit = evaluate_rule(&rule, Some(&info("晴天", 31.5, 40)), &[], tomorrow());
+        assert!(hit.unwrap().contains("現在 31.5℃"));
+        assert!(evaluate_rule(&rule, Some(&info("晴天", 30.0, 40)), &[], tomorrow()).is_none());
+        assert!(evaluate_rule(&rule, None, &[], tomorrow()).is_none());
+    }
+
+    #[test]
+    fn test_evaluate_now_humidity_below() {
+        let rule = parse_rule("東京 humidity < 30").unwrap();
+        assert!(evaluate_rule(&rule, Some(&info("晴天", 20.0, 25)), &[], tomorrow()).is_some());
+        assert!(evaluate_rule(&rule, Some(&info("晴天", 20.0, 35)), &[], tomorrow()).is_none());
+    }
+
+    #[test]
+    fn test_evaluate_tomorrow_weather_only_checks_tomorrow() {
+        let rule = parse_rule("rain in 東京 tomorrow").unwrap();
+        let hit = evaluate_rule(&rule, None, &forecast(), tomorrow()).unwrap();
+        assert!(hit.contains("明日 15:00 に「小雨」"));
+
+        let snow = parse_rule("snow in 東京 tomorrow").unwrap();
+        assert!(evaluate_rule(&snow, None, &forecast(), tomorrow()).is_none());
+    }
+
+    #[test]
+    fn test_evaluate_tomorrow_threshold_uses_extreme() {
+        let hot = parse_rule("東京 temp > 29 tomorrow").unwrap();
+        let hit = evaluate_rule(&hot, None, &forecast(), tomorrow()).unwrap();
+        assert!(hit.contains("最高 29.5℃"));
+
+        let cold = parse_rule("東京 temp < 20 tomorrow").unwrap();
+        assert!(evaluate_rule(&cold, None, &forecast(), tomorrow()).is_none());
+
+        // 予報がない場合は発火しない
+        assert!(evaluate_rule(&hot, None, &[], tomorrow()).is_none());
+    }
+
+    #[test]
+    fn test_local_tomorrow_uses_city_date() {
+        // 実行マシンの日付に関係なく、観測地点の現地日付の翌日
+        let mut new_york = info("晴天", 15.0, 50);
+        new_york.local_time =
+            NaiveDateTime::parse_from_str("2025-10-05 23:30", "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(local_tomorrow(&new_york).unwrap(), tomorrow());
+    }
+
+    #[test]
+    fn test_format_rules() {
+        let mut storage = AlertStorage::new();
+        assert!(format_rules(&storage).contains("ありません"));
+
+        storage.add_rule(parse_rule("rain in 大阪 tomorrow").unwrap());
+        assert!(format_rules(&storage).contains("[id:1] 大阪 で「rain」（明日）"));
+    }
+}
@@ -1,91 +1,156 @@
-use reqwest::Client;
-use serde::Deserialize;
-use std::env;
-
-use crate::weather::types::WeatherInfo;
-
-/// OpenWeatherMap API のレスポンス構造（必要な部分のみ）
-#[derive(Debug, Deserialize)]
-struct OpenWeatherResponse {
-    name: String,
-    main: Main,
-    weather: Vec<Weather>,
-}
+use crate::weather::provider::provider_from_env;
+use crate::weather::types::{ForecastEntry, GeoLocation, WeatherInfo};
 
-#[derive(Debug, Deserialize)]
-struct Main {
-    temp: f64,
-    humidity: u32,
-}
+/// 候補を同一地点とみなす座標差（度）
+const SAME_PLACE_EPS: f64 = 0.05;
 
-#[derive(Debug, Deserialize)]
-struct Weather {
-    description: String,
+/// 都市名から地点候補を取得
+///
+/// 同名の地点が複数ある場合（例: 「府中」）はすべて返す。
+/// ほぼ同じ座標の重複候補はまとめる。
+/// 取得元は `WEATHER_PROVIDER` で選んだプロバイダ。
+///
+/// # Returns
+/// * `Ok(Vec<GeoLocation>)` - 候補一覧（見つからなければ空）
+/// * `Err(String)` - エラーメッセージ
+pub async fn geocode_city(city: &str) -> Result<Vec<GeoLocation>, String> {
+    let provider = provider_from_env()?;
+    let locations = provider.geocode(city).await?;
+    Ok(dedup_locations(locations))
 }
 
 /// 都市名から天気情報を取得
-/// 
+///
+/// ジオコーディングの先頭候補（最も関連度の高い地点）の座標で問い合わせる。
+/// 曖昧な地名を区別したい場合は先に `geocode_city` で候補を確認すること。
+///
 /// # Arguments
 /// * `city` - 都市名（日本語または英語）
-/// 
+///
 /// # Returns
 /// * `Ok(WeatherInfo)` - 天気情報
 /// * `Err(String)` - エラーメッセージ
 pub async fn fetch_weather(city: &str) -> Result<WeatherInfo, String> {
-    let api_key = env::var("OPENWEATHER_API_KEY")
-        .map_err(|_| "環境変数 OPENWEATHER_API_KEY が設定されていません".to_string())?;
-
-    let client = Client::new();
-    let url = "https://api.openweathermap.org/data/2.5/weather";
-
-    // リクエストパラメータ
-    let response = client
-        .get(url)
-        .query(&[
-            ("q", city),
-            ("appid", &api_key),
-            ("units", "metric"), // 摂氏
-            ("lang", "ja"),      // 日本語の説明
-        ])
-        .send()
-        .await
-        .map_err(|e| format!("API リクエストエラー: {}", e))?;
-
-    // ステータスコード確認
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "API エラー（ステータス: {}）: {}",
-            status, error_text
-        ));
-    }
+    let candidates = geocode_city(city).await?;
+    let Some(location) = candidates.first() else {
+        return Err(format!("「{}」に該当する地点が見つかりません", city));
+    };
+
+    let mut info = fetch_weather_by_coords(location.lat, location.lon).await?;
+    info.city = location.name.clone();
+    Ok(info)
+}
+
+/// 緯度・経度から天気情報を取得
+///
+/// `city` にはプロバイダが返す観測地点名が入る（地点名を返さないプロバイダでは座標）。
+pub async fn fetch_weather_by_coords(lat: f64, lon: f64) -> Result<WeatherInfo, String> {
+    provider_from_env()?.current(lat, lon).await
+}
 
-    // JSON パース
-    let data: OpenWeatherResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("JSON パースエラー: {}", e))?;
-
-    // WeatherInfo に変換
-    let weather_desc = data
-        .weather
-        .first()
-        .map(|w| w.description.clone())
-        .unwrap_or_else(|| "不明".to_string());
-
-    Ok(WeatherInfo {
-        city: data.name,
-        weather: weather_desc,
-        temperature: data.main.temp,
-        humidity: data.main.humidity,
-    })
+/// 緯度・経度から予報を取得
+///
+/// 各コマの時刻は都市の現地時刻で返す
+pub async fn fetch_forecast_by_coords(lat: f64, lon: f64) -> Result<Vec<ForecastEntry>, String> {
+    provider_from_env()?.forecast(lat, lon).await
+}
+
+/// 同じ地点を指す候補を取り除く（先に出てきた方を残す）
+fn dedup_locations(locations: Vec<GeoLocation>) -> Vec<GeoLocation> {
+    let mut result: Vec<GeoLocation> = Vec::new();
+    for loc in locations {
+        let duplicated = result.iter().any(|r| {
+            (r.lat - loc.lat).abs() < SAME_PLACE_EPS && (r.lon - loc.lon).abs() < SAME_PLACE_EPS
+        });
+        if !duplicated {
+            result.push(loc);
+        }
+    }
+    result
 }
 
 /// 天気情報を整形して表示用文字列に
 pub fn format_weather_info(info: &WeatherInfo) -> String {
     format!(
-        "🌤️ {} の天気: {}, 気温 {}℃, 湿度 {}%",
-        info.city, info.weather, info.temperature, info.humidity
+        "🌤️ {} の天気: {}, 気温 {}℃, 湿度 {}% (lat={:.4}, lon={:.4})",
+        info.city, info.weather, info.temperature, info.humidity, info.lat, info.lon
+    )
+}
+
+/// 地点候補を番号付きで整形（座標付き）
+pub fn format_locations(city: &str, locations: &[GeoLocation]) -> String {
+    if locations.is_empty() {
+        return format!("📍 「{}」に該当する地点は見つかりませんでした", city);
+    }
+
+    let mut output = format!("📍 「{}」の候補 {} 件:\n", city, locations.len());
+    for (i, loc) in locations.iter().enumerate() {
+        output.push_str(&format!("{}. {}\n", i + 1, format_location(loc)));
+    }
+    output
+}
+
+/// 1 地点を「名前 (州, 国) lat=.., lon=..」形式に
+pub fn format_location(loc: &GeoLocation) -> String {
+    let region = match &loc.state {
+        Some(state) => format!("{}, {}", state, loc.country),
+        None => loc.country.clone(),
+    };
+    format!(
+        "{} ({}) lat={:.4}, lon={:.4}",
+        loc.name, region, loc.lat, loc.lon
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(name: &str, state: Option<&str>, lat: f64, lon: f64) -> GeoLocation {
+        GeoLocation {
+            name: name.to_string(),
+            country: "JP".to_string(),
+            state: state.map(|s| s.to_string()),
+            lat,
+            lon,
+        }
+    }
+
+    #[test]
+    fn test_dedup_locations_keeps_distinct_places() {
+        let locations = vec![
+            location("府中市", Some("Tokyo"), 35.6689, 139.4776),
+            location("府中市", Some("Hiroshima"), 34.5683, 133.2366),
+            location("府中町", Some("Hiroshima"), 34.3940, 132.5044),
+        ];
+
+        assert_eq!(dedup_locations(locations).len(), 3);
+    }
+
+    #[test]
+    fn test_dedup_locations_removes_near_duplicates() {
+        let locations = vec![
+            location("東京", Some("Tokyo"), 35.6828, 139.7595),
+            location("東京都", Some("Tokyo"), 35.6850, 139.7514),
+        ];
+
+        let result = dedup_locations(locations);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "東京");
+    }
+
+    #[test]
+    fn test_format_locations_empty() {
+        let output = format_locations("どこか", &[]);
+        assert!(output.contains("見つかりませんでした"));
+    }
+
+    #[test]
+    fn test_format_locations_lists_coordinates() {
+        let locations = vec![location("府中市", Some("Tokyo"), 35.6689, 139.4776)];
+
+        let output = format_locations("府中", &locations);
+        assert!(output.contains("1. 府中市 (Tokyo, JP)"));
+        assert!(output.contains("lat=35.6689, lon=139.4776"));
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::function_call::session::{SessionStore, format_session_list};
+use crate::function_call::usage::{USAGE_LOG_FILE, format_report, load_records};
+use crate::weather::alerts;
+use crate::weather::api::{fetch_weather_by_coords, format_weather_info};
+use crate::weather::play;
+use crate::weather::stats;
+use crate::weather::storage::WeatherStore;
+use crate::weather::types::{MemoFilter, SavedMemo, WeatherInfo, parse_date};
+use crate::weather::watch;
+
+/// `--key value` 形式のオプションと位置引数に分けた引数
+struct CliArgs {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl CliArgs {
+    /// 引数を解析（`allowed` にないオプションはエラー）
+    fn parse(args: &[String], allowed: &[&str]) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let Some(key) = arg.strip_prefix("--") else {
+                positional.push(arg.clone());
+                continue;
+            };
+            if !allowed.contains(&key) {
+                return Err(format!("不明なオプション: --{}", key));
+            }
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("--{} の値を指定してください", key))?;
+            options.insert(key.to_string(), value.clone());
+        }
+
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+}
+
+/// 天気アプリの CLI エントリーポイント（`cargo run -- weather <コマンド>`）
+pub fn run(args: &[String]) -> Result<(), String> {
+    if args.is_empty() {
+        print_help();
+        return Ok(());
+    }
+
+    match args[0].as_str() {
+        "now" => handle_now(&args[1..]),
+        "save" => handle_save(&args[1..]),
+        "list" => handle_list(&args[1..]),
+        "search" => handle_search(&args[1..]),
+        "stats" => handle_stats(&args[1..]),
+        "watch" => handle_watch(&args[1..]),
+        "alerts" => handle_alerts(&args[1..]),
+        "chat" => handle_chat(&args[1..]),
+        "export" => handle_export(&args[1..]),
+        "import" => handle_import(&args[1..]),
+        "usage" => handle_usage(&args[1..]),
+        "sessions" => handle_sessions(&args[1..]),
+        _ => {
+            println!("❌ 不明なコマンド: {}", args[0]);
+            print_help();
+            Ok(())
+        }
+    }
+}
+
+fn print_help() {
+    println!("🌤️ 天気メモアプリ");
+    println!("使い方:");
+    println!("  cargo run -- weather now <都市> [--lat <緯度> --lon <経度>]   - 現在の天気を表示");
+    println!(
+        "  cargo run -- weather save <都市> <メモ> [--lat .. --lon ..]  - 天気を取得してメモを保存"
+    );
+    println!("  cargo run -- weather list                        - メモ一覧を表示");
+    println!(
+        "  cargo run -- weather search [<都市>] [--from ..] [--to ..] [--min-temp ..] [--max-temp ..] [--weather 雨,雪] [--note ..]  - メモを検索"
+    );
+    println!(
+        "  cargo run -- weather chat [--resume <id>] [--base-url <URL>] [--model <モデル>] [--api-key <キー>]  - アシスタントと会話（/exit で終了）"
+    );
+    println!(
+        "  cargo run -- weather stats [--city <都市>] [--from YYYY-MM-DD] [--to YYYY-MM-DD]  - メモの統計を表示"
+    );
+    println!(
+        "  cargo run -- weather watch --city <都市> [--every 1h] [--lat <緯度> --lon <経度>]  - 定期的に天気を記録"
+    );
+    println!(
+        "  cargo run -- weather export [--format csv|geojson] [--out <ファイル>] [--city ..] [--from ..] [--to ..]  - メモを書き出し"
+    );
+    println!("  cargo run -- weather import <ファイル.csv>        - CSV のメモを取り込み");
+    println!("  cargo run -- weather usage [--month YYYY-MM]     - API の使用量と推定料金を表示");
+    println!("  cargo run -- weather sessions list               - 保存された会話の一覧");
+    println!("  cargo run -- weather sessions show <id>          - 会話の内容を表示");
+    println!(
+        "  cargo run -- weather sessions export <id> [--out <ファイル.md>]  - 会話を Markdown で書き出し"
+    );
+    println!("  cargo run -- weather sessions delete <id>        - 会話を削除");
+    println!("  cargo run -- weather alerts list                 - アラート一覧を表示");
+    println!("  cargo run -- weather alerts add \"東京 temp > 30\" - アラートを追加");
+    println!("  cargo run -- weather alerts delete <id>          - アラートを削除");
+    println!("  cargo run -- weather alerts check                - アラートを判定して通知");
+}
+
+/// 接続先は環境変数（WEATHER_* / OPENAI_*）を読み、オプションで上書きする
+fn handle_chat(args: &[String]) -> Result<(), String> {
+    let args = CliArgs::parse(args, &["resume", "base-url", "model", "api-key"])?;
+    if !args.positional.is_empty() {
+        return Err("chat に位置引数は指定できません".to_string());
+    }
+
+    dotenv::dotenv().ok();
+    let endpoint = play::default_endpoint().with_overrides(
+        args.option("base-url"),
+        args.option("model"),
+        args.option("api-key"),
+    );
+    let resume = args.option("resume").map(|s| s.to_string());
+    play::play_with(endpoint, resume).map_err(|e| e.to_string())
+}
+
+/// data/usage_log.jsonl を月別・アプリ別・モデル別に集計して表示
+fn handle_usage(args: &[String]) -> Result<(), String> {
+    let args = CliArgs::parse(args, &["month"])?;
+    if let Some(extra) = args.positional.first() {
+        return Err(format!("余分な引数: {}", extra));
+    }
+
+    let records = load_records(Path::new(USAGE_LOG_FILE))?;
+    println!("{}", format_report(&records, args.option("month")));
+    Ok(())
+}
+
+fn handle_now(args: &[String]) -> Result<(), String> {
+    let args = CliArgs::parse(args, &["lat", "lon"])?;
+    let [city] = args.positional.as_slice() else {
+        return Err("都市名を 1 つ指定してください".to_string());
+    };
+
+    let info = fetch_current(city, parse_coords(&args)?)?;
+    println!("{}", format_weather_info(&info));
+    Ok(())
+}
+
+fn handle_save(args: &[String]) -> Result<(), String> {
+    let args = CliArgs::parse(args, &["lat", "lon"])?;
+    let Some((city, note)) = args.positional.split_first() else {
+        return Err("都市名とメモを指定してください".to_string());
+    };
+
+    let info = fetch_current(city, parse_coords(&args)?)?;
+    println!("{}", format_weather_info(&info));
+    println!(
+        "{}",
+        WeatherStore::default()
+            .add_and_save_memo(SavedMemo::from_weather(&info, note.join(" ")))?
+    );
+    Ok(())
+}
+
+/// 地点を 1 つに絞って現在の天気を取得（候補が複数なら --lat / --lon を求める）
+#[tokio::main]
+async fn fetch_current(city: &str, coords: Option<(f64, f64)>) -> Result<WeatherInfo, String> {
+    dotenv::dotenv().ok();
+
+    let location = watch::resolve_location(city, coords).await?;
+    let mut info = fetch_weather_by_coords(location.lat, location.lon).await?;
+    info.city = location.name;
+    Ok(info)
+}
+
+fn handle_list(args: &[String]) -> Result<(), String> {
+    if let Some(extra) = args.first() {
+        return Err(format!("余分な引数: {}", extra));
+    }
+    println!("{}", WeatherStore::default().format_all_memos()?.trim_end());
+    Ok(())
+}
+
+fn handle_search(args: &[String]) -> Result<(), String> {
+    let args = CliArgs::parse(
+        args,
+        &["from", "to", "min-temp", "max-temp", "weather", "note"],
+    )?;
+    let city = match args.positional.as_slice() {
+        [] => None,
+        [city] => Some(city.clone()),
+        [_, extra, ..] => return Err(format!("余分な引数: {}", extra)),
+    };
+
+    let filter = MemoFilter {
+        city,
+        from: args.option("from").map(parse_date).transpose()?,
+        to: args.option("to").map(parse_date).transpose()?,
+        min_temp: args.option("min-temp").map(parse_number).transpose()?,
+        max_temp: args.option("max-temp").map(parse_number).transpose()?,
+        weather_keywords: args
+            .option("weather")
+            .map(|w| w.split(',').map(|k| k.trim().to_string()).collect())
+            .unwrap_or_default(),
+        note: args.option("note").map(|s| s.to_string()),
+    };
+
+    let store = WeatherStore::default();
+    println!("{}", store.format_search_results(&filter)?.trim_end());
+    Ok(())
+}
+
+fn handle_stats(args: &[String]) -> Result<(), String> {
+    let args = CliArgs::parse(args, &["city", "from", "to"])?;
+    if let Some(extra) = args.positional.first() {
+        return Err(format!("余分な引数: {}", extra));
+    }
+
+    let filter = MemoFilter {
+        city: args.option("city").map(|s| s.to_string()),
+        from: args.option("from").map(parse_date).transpose()?,
+        to: args.option("to").map(parse_date).transpose()?,
+        ..Default::default()
+    };
+
+    let store = WeatherStore::default();
+    println!("{}", stats::format_stats(&store, &filter)?);
+    Ok(())
+}
+
+fn handle_export(args: &[String]) -> Result<(), String> {
+    let args = CliArgs::parse(args, &["format", "out", "city", "from", "to"])?;
+    if let Some(extra) = args.positional.first() {
+        return Err(format!("余分な引数: {}", extra));
+    }
+
+    let filter = MemoFilter {
+        city: args.option("city").map(|s| s.to_string()),
+        from: args.option("from").map(parse_date).transpose()?,
+        to: args.option("to").map(parse_date).transpose()?,
+        ..Default::default()
+    };
+    let out = args.option("out");
+    let store = WeatherStore::default();
+
+    let output = match args.option("format").unwrap_or("csv") {
+        "csv" => store.export_csv(&filter, out)?,
+        "geojson" => store.export_geojson(&filter, out)?,
+        other => return Err(format!("不明な形式: {}（csv / geojson）", other)),
+    };
+    // ファイル出力ならメッセージ、標準出力なら内容そのもの
+    println!("{}", output.trim_end());
+    Ok(())
+}
+
+fn handle_import(args: &[String]) -> Result<(), String> {
+    let [path] = args else {
+        return Err("取り込む CSV ファイルを 1 つ指定してください".to_string());
+    };
+    println!("{}", WeatherStore::default().import_csv(path)?);
+    Ok(())
+}
+
+#[tokio::main]
+async fn handle_watch(args: &[String]) -> Result<(), String> {
+    dotenv::dotenv().ok();
+
+    let args = CliArgs::parse(args, &["city", "every", "lat", "lon"])?;
+    let city = args
+        .option("city")
+        .ok_or_else(|| "--city を指定してください".to_string())?;
+    let every = watch::parse_interval(args.option("every").unwrap_or("1h"))?;
+
+    let location = watch::resolve_location(city, parse_coords(&args)?).await?;
+    watch::watch(location, every).await
+}
+
+/// --lat / --lon（両方指定したときのみ座標として使う）
+fn parse_coords(args: &CliArgs) -> Result<Option<(f64, f64)>, String> {
+    match (args.option("lat"), args.option("lon")) {
+        (Some(lat), Some(lon)) => Ok(Some((parse_number(lat)?, parse_number(lon)?))),
+        (None, None) => Ok(None),
+        _ => Err("--lat と --lon は両方指定してください".to_string()),
+    }
+}
+
+fn parse_number(s: &str) -> Result<f64, String> {
+    s.parse()
+        .map_err(|_| format!("数値で指定してください: {}", s))
+}
+
+fn handle_alerts(args: &[String]) -> Result<(), String> {
+    let Some(sub) = args.first() else {
+        print_help();
+        return Ok(());
+    };
+    let store = WeatherStore::default();
+
+    match sub.as_str() {
+        "list" => {
+            println!("{}", alerts::format_rules(&store.load_alerts()?));
+            Ok(())
+        }
+        "add" => {
+            if args.len() < 2 {
+                return Err("ルールを指定してください（例: \"東京 temp > 30\"）".to_string());
+            }
+            let rule = alerts::parse_rule(&args[1..].join(" "))?;
+            let mut storage = store.load_alerts()?;
+            let id = storage.add_rule(rule.clone());
+            store.save_alerts(&storage)?;
+            println!("✅ アラートを登録しました (id: {}): {}", id, rule);
+            Ok(())
+        }
+        "delete" => {
+            let id: u32 = args
+                .get(1)
+                .ok_or_else(|| "ID を指定してください".to_string())?
+                .parse()
+                .map_err(|_| "ID は数値で指定してください".to_string())?;
+            let mut storage = store.load_alerts()?;
+            if !storage.delete_rule(id) {
+                return Err(format!("❌ アラートが見つかりません (id: {})", id));
+            }
+            store.save_alerts(&storage)?;
+            println!("🗑️ アラートを削除しました (id: {})", id);
+            Ok(())
+        }
+        "check" => handle_alerts_check(),
+        _ => {
+            println!("❌ 不明なコマンド: alerts {}", sub);
+            print_help();
+            Ok(())
+        }
+    }
+}
+
+/// 保存された会話（data/sessions/）の一覧・表示・書き出し・削除
+fn handle_sessions(args: &[String]) -> Result<(), String> {
+    let Some(sub) = args.first() else {
+        print_help();
+        return Ok(());
+    };
+    let store = SessionStore::default();
+    let args = CliArgs::parse(&args[1..], &["out"])?;
+    let id = || {
+        args.positional
+            .first()
+            .map(String::as_str)
+            .ok_or_else(|| "セッション ID を指定してください".to_string())
+    };
+
+    match sub.as_str() {
+        "list" => println!("{}", format_session_list(&store.list()?)),
+        "show" => println!("{}", store.load(id()?)?.to_markdown()),
+        "export" => {
+            let session = store.load(id()?)?;
+            let default_out = format!("exports/session-{}.md", session.id);
+            let out = args.option("out").unwrap_or(&default_out);
+            if let Some(parent) = Path::new(out).parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+            }
+            fs::write(out, session.to_markdown())
+                .map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
+            println!("📤 {} に会話を書き出しました", out);
+        }
+        "delete" => {
+            let id = id()?;
+            store.delete(id)?;
+            println!("🗑️ 会話を削除しました (id: {})", id);
+        }
+        _ => {
+            println!("❌ 不明なコマンド: sessions {}", sub);
+            print_help();
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn handle_alerts_check() -> Result<(), String> {
+    dotenv::dotenv().ok();
+
+    println!("{}", alerts::check_alerts(&WeatherStore::default()).await?);
+    Ok(())
+}
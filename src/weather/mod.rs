@@ -1,5 +1,10 @@
+pub mod alerts;
 pub mod api;
+pub mod cli;
 pub mod play;
+pub mod provider;
+pub mod stats;
 pub mod storage;
 pub mod tools;
 pub mod types;
+pub mod watch;
@@ -1,227 +1,512 @@
-use std::env;
-use std::io;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use dotenv::dotenv;
-use serde_json::{Value, json};
 
-use crate::function_call::api::send_chat_completion;
-use crate::function_call::types::{ChatResponse, JsonSchemaObject, ToolDefinition, ToolFunctionDefinition};
+use crate::function_call::agent::{Agent, AgentConfig, AgentEvent, StopReason};
+use crate::function_call::api::{ChatTransport, EndpointConfig, OPENAI_BASE_URL};
+use crate::function_call::context::{ContextBudget, SUMMARY_PREFIX};
+use crate::function_call::registry::{NoArgs, ToolRegistry};
+use crate::function_call::replay::transport_from_env;
+use crate::function_call::session::SessionStore;
+use crate::function_call::types::ChatMessage;
+use crate::function_call::usage::UsageTracker;
+use crate::weather::storage::WeatherStore;
 use crate::weather::tools::{
-    tool_fetch_weather, tool_list_memos, tool_save_weather_memo, tool_search_memos,
+    CONFIRM_TOOLS, tool_create_alert, tool_delete_alert, tool_delete_weather_memo,
+    tool_fetch_weather, tool_list_alerts, tool_list_memos, tool_resolve_city,
+    tool_save_weather_memo, tool_search_memos, tool_update_weather_memo, tool_weather_stats,
 };
-use crate::weather::types::{FetchWeatherArgs, SaveMemoArgs, SearchMemosArgs};
 
-#[tokio::main]
-pub async fn play() -> Result<(), Box<dyn std::error::Error>> {
+const SYSTEM_PROMPT: &str = "あなたは天気情報を提供し、ユーザーのメモ管理を手伝うアシスタントです。必要に応じて適切なツールを使用してください。複数の都市について調べるときは fetch_weather を 1 回の応答でまとめて呼び出してください。地名が曖昧な場合は resolve_city で候補を調べてユーザーに確認し、確定した地点の lat / lon を fetch_weather に渡してください。メモを修正・削除するときは list_memos や search_memos で ID を確認してください。";
+
+/// 既定のモデル（WEATHER_MODEL / OPENAI_MODEL / --model で変更可）
+const MODEL: &str = "gpt-4o-mini";
+
+/// 1 回の発言に対するツール呼び出しの最大反復数
+const MAX_ITER: usize = 5;
+
+/// 1 ターン内のツール呼び出しを同時に実行する最大数
+const MAX_PARALLEL_TOOLS: usize = 4;
+
+/// 引数がスキーマに合わないとき、1 回の発言の中で直させる最大回数
+const MAX_CORRECTIONS: usize = 2;
+
+/// `/history` で 1 メッセージあたりに表示する最大文字数
+const HISTORY_PREVIEW_CHARS: usize = 60;
+
+/// 入力行の解釈結果
+#[derive(Debug, PartialEq)]
+enum ChatCommand {
+    Exit,
+    Reset,
+    History,
+    Help,
+    Empty,
+    Message(String),
+}
+
+/// 入力行をコマンドか発言に分ける
+fn parse_command(line: &str) -> ChatCommand {
+    let line = line.trim();
+    match line {
+        "" => ChatCommand::Empty,
+        "/exit" | "/quit" => ChatCommand::Exit,
+        "/reset" => ChatCommand::Reset,
+        "/history" => ChatCommand::History,
+        _ if line.starts_with('/') => ChatCommand::Help,
+        _ => ChatCommand::Message(line.to_string()),
+    }
+}
+
+/// 履歴を 1 行ずつ整形（長い内容は省略）
+fn format_history(messages: &[ChatMessage]) -> String {
+    let lines: Vec<String> = messages
+        .iter()
+        .filter_map(|m| match m {
+            ChatMessage::System { content } if content.starts_with(SUMMARY_PREFIX) => {
+                Some(format!("📝 {}", preview(content)))
+            }
+            ChatMessage::System { .. } => None,
+            ChatMessage::User { content } => Some(format!("🧑 {}", preview(content))),
+            ChatMessage::Tool { content, .. } => Some(format!("   🔧 → {}", preview(content))),
+            ChatMessage::Assistant { tool_calls, .. } if !tool_calls.is_empty() => {
+                let names: Vec<&str> = tool_calls
+                    .iter()
+                    .map(|c| c.function.name.as_str())
+                    .collect();
+                Some(format!("   🤖 ツール呼び出し: {}", names.join(", ")))
+            }
+            ChatMessage::Assistant { content, .. } => Some(format!(
+                "🤖 {}",
+                preview(content.as_deref().unwrap_or_default())
+            )),
+        })
+        .collect();
+
+    if lines.is_empty() {
+        return "📭 まだ会話はありません".to_string();
+    }
+    format!("📜 会話履歴（{} 件）:\n{}", lines.len(), lines.join("\n"))
+}
+
+/// 改行を詰めて先頭だけ残す
+fn preview(content: &str) -> String {
+    let flat = content.replace('\n', " ");
+    if flat.chars().count() <= HISTORY_PREVIEW_CHARS {
+        return flat;
+    }
+    let head: String = flat.chars().take(HISTORY_PREVIEW_CHARS).collect();
+    format!("{}…", head)
+}
+
+fn print_commands() {
+    println!("コマンド: /history 履歴を表示, /reset 会話をリセット, /exit 終了");
+}
+
+/// 環境変数の接続先で起動
+pub fn play() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
-    let api_key = env::var("OPENAI_API_KEY")?;
+    play_with(default_endpoint(), None)
+}
+
+/// 天気アプリの既定の接続先
+///
+/// WEATHER_BASE_URL / WEATHER_MODEL / WEATHER_API_KEY（なければ OPENAI_*）から読む
+pub fn default_endpoint() -> EndpointConfig {
+    EndpointConfig::from_env("WEATHER", MODEL)
+}
+
+/// 接続先を指定して起動（`weather chat --base-url ..` など）
+///
+/// 会話は 1 ターンごとに data/sessions/<id>.json に保存し、`resume` で続きから再開できる。
+#[tokio::main]
+pub async fn play_with(
+    endpoint: EndpointConfig,
+    resume: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = SessionStore::default();
+    let resumed = resume
+        .map(|id| store.resume(&id, "weather", SYSTEM_PROMPT))
+        .transpose()?;
+
+    // LLM_CASSETTE を指定すると記録・再生（function_call/replay.rs）
+    let model = endpoint.model.clone();
+    let base_url = endpoint.base_url.clone();
+    let transport = transport_from_env(endpoint)?;
 
     println!("🌤️ 天気メモアプリを起動しました！");
-    println!("何をしましょうか？（例: 東京の天気を教えて、メモを保存して、など）\n");
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    let user_text = input.trim();
-
-    // ツール定義
-    let tools = vec![
-        // ツール1: fetch_weather
-        ToolDefinition {
-            r#type: "function",
-            function: ToolFunctionDefinition {
-                name: "fetch_weather",
-                description: "指定された都市の現在の天気情報を取得する",
-                parameters: JsonSchemaObject {
-                    r#type: "object",
-                    properties: json!({
-                        "city": {
-                            "type": "string",
-                            "description": "都市名（日本語または英語）"
-                        }
-                    }),
-                    required: vec!["city"],
-                },
-            },
-        },
-        // ツール2: save_weather_memo
-        ToolDefinition {
-            r#type: "function",
-            function: ToolFunctionDefinition {
-                name: "save_weather_memo",
-                description: "天気情報とメモを保存する",
-                parameters: JsonSchemaObject {
-                    r#type: "object",
-                    properties: json!({
-                        "city": {
-                            "type": "string",
-                            "description": "都市名"
-                        },
-                        "weather": {
-                            "type": "string",
-                            "description": "天気の説明"
-                        },
-                        "temperature": {
-                            "type": "number",
-                            "description": "気温（摂氏）"
-                        },
-                        "note": {
-                            "type": "string",
-                            "description": "ユーザーのメモ"
-                        }
-                    }),
-                    required: vec!["city", "weather", "temperature", "note"],
-                },
-            },
-        },
-        // ツール3: list_memos
-        ToolDefinition {
-            r#type: "function",
-            function: ToolFunctionDefinition {
-                name: "list_memos",
-                description: "保存されている全ての天気メモを一覧表示する",
-                parameters: JsonSchemaObject {
-                    r#type: "object",
-                    properties: json!({}),
-                    required: vec![],
-                },
-            },
-        },
-        // ツール4: search_memos
-        ToolDefinition {
-            r#type: "function",
-            function: ToolFunctionDefinition {
-                name: "search_memos",
-                description: "特定の都市のメモを検索する",
-                parameters: JsonSchemaObject {
-                    r#type: "object",
-                    properties: json!({
-                        "city": {
-                            "type": "string",
-                            "description": "検索する都市名"
-                        }
-                    }),
-                    required: vec!["city"],
-                },
-            },
-        },
-    ];
-
-    // メッセージ履歴
-    let mut messages: Vec<Value> = vec![
-        json!({"role": "system", "content": "あなたは天気情報を提供し、ユーザーのメモ管理を手伝うアシスタントです。必要に応じて適切なツールを使用してください。"}),
-        json!({"role": "user", "content": user_text}),
-    ];
-
-    let client = reqwest::Client::new();
-    let model = "gpt-4o-mini";
-    const MAX_ITER: usize = 5;
-
-    for iter in 0..MAX_ITER {
-        let req_body = json!({
-            "model": model,
-            "messages": messages,
-            "tools": tools,
-            "temperature": 0.3,
-        });
-
-        let raw = match send_chat_completion(&client, &api_key, &req_body).await {
-            Ok(t) => t,
-            Err(e) => {
-                eprintln!("❌ API 呼び出し失敗(iter={iter}): {e}");
-                break;
-            }
-        };
+    println!("何をしましょうか？（例: 東京の天気を教えて、メモを保存して、など）");
+    print_commands();
+    println!();
 
-        let parsed: ChatResponse = match serde_json::from_str(&raw) {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("❌ JSON パース失敗(iter={iter}): {e}\nraw: {raw}");
-                break;
-            }
-        };
+    if base_url != OPENAI_BASE_URL {
+        println!("🔌 接続先: {} (model: {})", base_url, model);
+    }
+    let usage = Arc::new(UsageTracker::new("weather"));
+    let agent = build_agent(&model, transport, WeatherStore::default())
+        .with_usage_tracker(Arc::clone(&usage));
+    let (mut session, mut messages) = match resumed {
+        Some(session) => {
+            println!(
+                "📂 会話 {} を再開しました（{} 発言）",
+                session.id,
+                session.turns()
+            );
+            let messages = session.messages.clone();
+            (session, messages)
+        }
+        None => (store.create("weather"), agent.initial_messages()),
+    };
 
-        let choice = match parsed.choices.first() {
-            Some(c) => c,
-            None => {
-                eprintln!("❌ choices 空(iter={iter})");
-                break;
-            }
-        };
-        let msg = &choice.message;
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
 
-        // tool_calls がない = 最終回答
-        if msg.tool_calls.is_empty() {
-            println!("\n💬 {}", msg.content.clone().unwrap_or_default());
+        let mut input = String::new();
+        // 0 バイト = EOF（Ctrl-D）
+        if io::stdin().read_line(&mut input)? == 0 {
             break;
         }
 
-        // assistant メッセージを履歴に追加
-        let assistant_msg = {
-            let mut tool_calls_json = Vec::new();
-            for tc in &msg.tool_calls {
-                tool_calls_json.push(json!({
-                    "id": tc.id,
-                    "type": "function",
-                    "function": {"name": tc.function.name, "arguments": tc.function.arguments}
-                }));
+        match parse_command(&input) {
+            ChatCommand::Exit => break,
+            ChatCommand::Empty => continue,
+            ChatCommand::Help => print_commands(),
+            ChatCommand::Reset => {
+                messages = agent.initial_messages();
+                session = store.create("weather");
+                println!("🔄 会話をリセットしました");
             }
-            json!({
-                "role": "assistant",
-                "content": msg.content.clone().unwrap_or_default(),
-                "tool_calls": tool_calls_json,
-            })
-        };
-        messages.push(assistant_msg);
-
-        // 各 tool_call を実行
-        for tc in &msg.tool_calls {
-            let func_name = &tc.function.name;
-            let args_raw = &tc.function.arguments;
-
-            // ツール実行
-            let result = match func_name.as_str() {
-                "fetch_weather" => {
-                    match serde_json::from_str::<FetchWeatherArgs>(args_raw) {
-                        Ok(args) => tool_fetch_weather(args).await,
-                        Err(e) => Err(format!("引数パースエラー: {}", e)),
-                    }
-                }
-                "save_weather_memo" => {
-                    match serde_json::from_str::<SaveMemoArgs>(args_raw) {
-                        Ok(args) => tool_save_weather_memo(args).await,
-                        Err(e) => Err(format!("引数パースエラー: {}", e)),
+            ChatCommand::History => println!("{}", format_history(&messages)),
+            ChatCommand::Message(text) => {
+                // Agent が古い発言を要約・削除することがあるので、位置ではなく丸ごと控える
+                let checkpoint = messages.clone();
+                messages.push(ChatMessage::user(text));
+
+                // なぜ: 途中で失敗した発言を残すと tool_calls が宙に浮くので巻き戻す
+                let completed = match agent.run(&mut messages).await {
+                    Ok(run) => run.stop == StopReason::Completed,
+                    Err(e) => {
+                        eprintln!("❌ {e}");
+                        false
                     }
-                }
-                "list_memos" => tool_list_memos().await,
-                "search_memos" => {
-                    match serde_json::from_str::<SearchMemosArgs>(args_raw) {
-                        Ok(args) => tool_search_memos(args).await,
-                        Err(e) => Err(format!("引数パースエラー: {}", e)),
+                };
+                if !completed {
+                    messages = checkpoint;
+                } else {
+                    session.messages = messages.clone();
+                    if let Err(e) = store.save(&mut session) {
+                        eprintln!("⚠️ 会話を保存できません: {}", e);
                     }
                 }
-                _ => Err(format!("未知のツール: {}", func_name)),
-            };
-
-            // 結果を tool メッセージとして追加
-            let content = match result {
-                Ok(s) => s,
-                Err(e) => {
-                    eprintln!("❌ ツールエラー詳細: {}", e);
-                    format!("❌ エラー: {}", e)
-                }
-            };
+            }
+        }
+        println!();
+    }
+
+    println!("{}", usage.format_summary());
+    if session.turns() > 0 {
+        println!(
+            "💾 会話 {} を保存しました（weather chat --resume {} で再開）",
+            session.id, session.id
+        );
+    }
+    println!("👋 終了します");
+    Ok(())
+}
 
-            messages.push(json!({
-                "role": "tool",
-                "tool_call_id": tc.id,
-                "content": content,
-            }));
+/// 天気アプリ用の Agent（回答を受信しながら表示し、エラーも端末に出す）
+///
+/// メモとアラートは `store` に読み書きする
+fn build_agent(model: &str, transport: Box<dyn ChatTransport>, store: WeatherStore) -> Agent {
+    let config = AgentConfig {
+        model: model.to_string(),
+        system_prompt: SYSTEM_PROMPT.to_string(),
+        temperature: 0.3,
+        max_iter: MAX_ITER,
+        max_parallel_tools: MAX_PARALLEL_TOOLS,
+        // confirm() で y/N を聞くツールは並行実行すると入力が混ざる
+        exclusive_tools: CONFIRM_TOOLS.to_vec(),
+        stream: true,
+        // CONTEXT_MAX_TOKENS / CONTEXT_STRATEGY で変更可
+        context: Some(ContextBudget::from_env()),
+        response_format: None,
+        max_corrections: MAX_CORRECTIONS,
+    };
+
+    // 回答の表示を始めたか（差分が届かなかったときは Finished でまとめて表示する）
+    let printing = AtomicBool::new(false);
+    Agent::new(config, build_registry(&store), transport).on_event(move |event| match event {
+        AgentEvent::ContentDelta { text } => {
+            if !printing.swap(true, Ordering::Relaxed) {
+                print!("\n💬 ");
+            }
+            print!("{}", text);
+            let _ = io::stdout().flush();
+        }
+        AgentEvent::ToolFinished { result: Err(e), .. } => eprintln!("❌ ツールエラー詳細: {}", e),
+        AgentEvent::Finished { text } => {
+            if printing.swap(false, Ordering::Relaxed) {
+                println!();
+            } else {
+                println!("\n💬 {}", text);
+            }
+        }
+        AgentEvent::ContextTrimmed {
+            removed,
+            summarized,
+        } => {
+            let action = if *summarized { "要約" } else { "削除" };
+            println!(
+                "🗜️ 履歴が長くなったので古いメッセージ {} 件を{}しました",
+                removed, action
+            );
+        }
+        AgentEvent::Correction { attempt, reason } => {
+            eprintln!("🔁 引数がスキーマに合わないので直させます（{attempt} 回目）: {reason}")
+        }
+        AgentEvent::CorrectionsExhausted {
+            max_corrections, ..
+        } => eprintln!("⚠️ {max_corrections} 回直させても引数が合わないので打ち切り"),
+        AgentEvent::MaxIterations { max_iter } => {
+            printing.store(false, Ordering::Relaxed);
+            eprintln!("⚠️ 最大反復 {max_iter} に到達。打ち切り")
         }
+        AgentEvent::Retrying {
+            attempt,
+            max_retries,
+            wait,
+            error,
+        } => eprintln!(
+            "⏳ {error}。{:.1} 秒後に再試行します ({attempt}/{max_retries})",
+            wait.as_secs_f64()
+        ),
+        _ => {}
+    })
+}
+
+/// メモやアラートを読み書きするツールに保存先を渡す
+fn with_store<A, F, Fut>(store: &WeatherStore, tool: F) -> impl Fn(A) -> Fut + Send + Sync + 'static
+where
+    F: Fn(WeatherStore, A) -> Fut + Send + Sync + 'static,
+{
+    let store = store.clone();
+    move |args| tool(store.clone(), args)
+}
+
+/// 天気アプリのツールを登録した ToolRegistry
+fn build_registry(store: &WeatherStore) -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+
+    // ツール1: fetch_weather
+    registry.register(
+        "fetch_weather",
+        "指定された都市の現在の天気情報を取得する。地点が確定していれば lat / lon も指定する",
+        tool_fetch_weather,
+    );
+
+    // ツール2: save_weather_memo
+    registry.register(
+        "save_weather_memo",
+        "天気情報とメモを保存する",
+        with_store(store, tool_save_weather_memo),
+    );
+
+    // ツール3: list_memos
+    registry.register(
+        "list_memos",
+        "保存されている全ての天気メモを一覧表示する",
+        with_store(store, |store, _: NoArgs| tool_list_memos(store)),
+    );
+
+    // ツール4: search_memos
+    registry.register(
+        "search_memos",
+        "条件を組み合わせてメモを検索する（指定した条件はすべて満たすものだけ）",
+        with_store(store, tool_search_memos),
+    );
+
+    // ツール5: resolve_city
+    registry.register(
+        "resolve_city",
+        "都市名から地点候補（国・州・緯度経度）を検索する。同名の地点が複数ある場合はユーザーにどれか確認する",
+        tool_resolve_city,
+    );
+
+    // ツール6: weather_stats
+    registry.register(
+        "weather_stats",
+        "保存済みメモから都市ごとの最低・最高・平均気温、よくある天気、日別の気温推移を集計する",
+        with_store(store, tool_weather_stats),
+    );
+
+    // ツール7: update_weather_memo
+    registry.register(
+        "update_weather_memo",
+        "保存済みメモを ID 指定で修正する。指定した項目だけ変更し、実行前にユーザーの確認を取る",
+        with_store(store, tool_update_weather_memo),
+    );
+
+    // ツール8: delete_weather_memo
+    registry.register(
+        "delete_weather_memo",
+        "保存済みメモを ID 指定で削除する。実行前にユーザーの確認を取る",
+        with_store(store, tool_delete_weather_memo),
+    );
+
+    // ツール9: create_alert
+    registry.register(
+        "create_alert",
+        "天気のアラートルールを登録する。数値条件なら metric / op / value、天気条件なら weather を指定する",
+        with_store(store, tool_create_alert),
+    );
+
+    // ツール10: list_alerts
+    registry.register(
+        "list_alerts",
+        "登録されているアラートルールを一覧表示する",
+        with_store(store, |store, _: NoArgs| tool_list_alerts(store)),
+    );
+
+    // ツール11: delete_alert
+    registry.register(
+        "delete_alert",
+        "アラートルールを ID 指定で削除する。実行前にユーザーの確認を取る",
+        with_store(store, tool_delete_alert),
+    );
+
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if iter == MAX_ITER - 1 {
-            eprintln!("⚠️ 最大反復 {MAX_ITER} に到達。打ち切り");
+    use serde_json::Value;
+
+    use crate::function_call::replay::ReplayTransport;
+    use crate::function_call::testutil::TempPath;
+    use crate::function_call::types::{CalledFunction, ToolCall};
+    use crate::weather::alerts::parse_rule;
+    use crate::weather::types::AlertStorage;
+
+    fn initial_messages() -> Vec<ChatMessage> {
+        vec![ChatMessage::system(SYSTEM_PROMPT)]
+    }
+
+    fn user(text: &str) -> ChatMessage {
+        ChatMessage::user(text)
+    }
+
+    fn assistant(text: &str) -> ChatMessage {
+        ChatMessage::assistant(text)
+    }
+
+    fn fetch_weather_call() -> ChatMessage {
+        ChatMessage::assistant_tool_calls(
+            None,
+            vec![ToolCall {
+                id: "c1".to_string(),
+                call_type: "function".to_string(),
+                function: CalledFunction {
+                    name: "fetch_weather".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            }],
+        )
+    }
+
+    /// 手書きしていた頃の各ツールのパラメータ定義
+    const HAND_WRITTEN_SCHEMAS: &str = include_str!("testdata/tool_schemas.json");
+
+    #[test]
+    fn test_generated_schemas_match_hand_written() {
+        let expected: Value = serde_json::from_str(HAND_WRITTEN_SCHEMAS).unwrap();
+        let registry = build_registry(&WeatherStore::default());
+
+        let definitions = registry.definitions();
+        assert_eq!(definitions.len(), expected.as_object().unwrap().len());
+        for definition in definitions {
+            let name = definition.function.name;
+            let generated = serde_json::to_value(&definition.function.parameters).unwrap();
+            assert_eq!(generated, expected[name], "{name} のスキーマが一致しません");
         }
     }
 
-    Ok(())
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/exit\n"), ChatCommand::Exit);
+        assert_eq!(parse_command(" /reset "), ChatCommand::Reset);
+        assert_eq!(parse_command("/history"), ChatCommand::History);
+        assert_eq!(parse_command("/foo"), ChatCommand::Help);
+        assert_eq!(parse_command("  \n"), ChatCommand::Empty);
+        assert_eq!(
+            parse_command("じゃあ大阪は？\n"),
+            ChatCommand::Message("じゃあ大阪は？".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_history() {
+        assert!(format_history(&initial_messages()).contains("まだ会話はありません"));
+
+        let mut messages = initial_messages();
+        messages.extend([
+            ChatMessage::system(format!("{}札幌は雪", SUMMARY_PREFIX)),
+            user("東京の天気は？"),
+            fetch_weather_call(),
+            assistant(&"あ".repeat(100)),
+        ]);
+
+        let output = format_history(&messages);
+        assert!(output.contains("📝 これまでの会話の要約: 札幌は雪"));
+        assert!(output.contains("🧑 東京の天気は？"));
+        assert!(output.contains("ツール呼び出し: fetch_weather"));
+        assert!(output.contains("…"));
+        assert!(!output.contains("アシスタントです"));
+    }
+
+    /// 記録した 2 ターンの会話（ツール 2 件を並行実行 → 回答 → 続きの発言）を再生する
+    #[tokio::test]
+    async fn test_chat_replays_recorded_conversation() {
+        // なぜ: data/ の実データを読み書きしないよう、保存先は一時ディレクトリにする
+        let dir = TempPath::new("weather_replay");
+        let store = WeatherStore::new(&dir);
+        let mut alerts = AlertStorage::new();
+        alerts.add_rule(parse_rule("rain in 大阪 tomorrow").unwrap());
+        store.save_alerts(&alerts).unwrap();
+
+        let cassette =
+            serde_json::from_str(include_str!("testdata/alerts_and_memos.json")).unwrap();
+        let agent = build_agent(MODEL, Box::new(ReplayTransport::new(cassette)), store);
+        let mut messages = agent.initial_messages();
+
+        messages.push(user("アラートとメモを見せて"));
+        let run = agent.run(&mut messages).await.unwrap();
+        let tools: Vec<&str> = run.tool_trace.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tools, vec!["list_alerts", "list_memos"]);
+        let results: Vec<&str> = run
+            .tool_trace
+            .iter()
+            .map(|t| t.result.as_deref().unwrap())
+            .collect();
+        assert!(results[0].contains("大阪 で「rain」（明日）"));
+        assert!(results[1].contains("保存されているメモはありません"));
+        assert_eq!(
+            run.final_text.as_deref(),
+            Some("登録済みのアラートとメモを確認しました。")
+        );
+
+        // 2 ターン目は 1 ターン目の履歴を含めて送られる（role の並びが記録と一致する）
+        messages.push(user("ありがとう"));
+        let run = agent.run(&mut messages).await.unwrap();
+        assert!(run.tool_trace.is_empty());
+        assert_eq!(messages.len(), 8);
+
+        // 記録を使い切った後の発言はエラー
+        messages.push(user("もう一度"));
+        assert!(agent.run(&mut messages).await.is_err());
+    }
 }
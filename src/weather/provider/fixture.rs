@@ -0,0 +1,201 @@
+use std::fs;
+
+use async_trait::async_trait;
+use chrono::{Duration, Local, NaiveDateTime, Timelike};
+use serde::Deserialize;
+
+use crate::weather::provider::WeatherProvider;
+use crate::weather::types::{ForecastEntry, GeoLocation, WeatherInfo};
+
+/// 座標からフィクスチャの地点を引くときの許容差（度）
+const COORD_EPS: f64 = 0.1;
+
+/// フィクスチャファイル全体
+#[derive(Debug, Deserialize)]
+pub struct FixtureData {
+    pub locations: Vec<FixtureLocation>,
+}
+
+/// 1 地点分のデータ
+#[derive(Debug, Deserialize)]
+pub struct FixtureLocation {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>, // 英語名など、検索でヒットさせたい別名
+    pub country: String,
+    #[serde(default)]
+    pub state: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub current: FixtureReading,
+    #[serde(default)]
+    pub forecast: Vec<FixtureForecast>,
+}
+
+/// 天気・気温・湿度の組
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureReading {
+    pub weather: String,
+    pub temperature: f64,
+    pub humidity: u32,
+}
+
+/// 予報の 1 コマ（基準時刻からの相対時間で書く）
+#[derive(Debug, Deserialize)]
+pub struct FixtureForecast {
+    pub offset_hours: i64,
+    #[serde(flatten)]
+    pub reading: FixtureReading,
+}
+
+/// JSON フィクスチャを返すオフラインのプロバイダ（テスト・デモ用）
+pub struct FixtureProvider {
+    data: FixtureData,
+    base_time: NaiveDateTime, // 予報の offset_hours の起点
+}
+
+impl FixtureProvider {
+    /// データと基準時刻を指定して作成（テスト用）
+    pub fn new(data: FixtureData, base_time: NaiveDateTime) -> Self {
+        Self { data, base_time }
+    }
+
+    /// JSON ファイルから作成（予報の基準時刻は現在時刻の正時）
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("フィクスチャ読み込みエラー（{}）: {}", path, e))?;
+        let data: FixtureData = serde_json::from_str(&content)
+            .map_err(|e| format!("フィクスチャ JSON パースエラー: {}", e))?;
+
+        let now = Local::now().naive_local();
+        let base_time = now
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(now);
+
+        Ok(Self::new(data, base_time))
+    }
+
+    /// 座標に最も近い地点（許容差内のみ）
+    fn find_by_coords(&self, lat: f64, lon: f64) -> Result<&FixtureLocation, String> {
+        self.data
+            .locations
+            .iter()
+            .filter(|l| (l.lat - lat).abs() < COORD_EPS && (l.lon - lon).abs() < COORD_EPS)
+            .min_by(|a, b| {
+                let da = (a.lat - lat).powi(2) + (a.lon - lon).powi(2);
+                let db = (b.lat - lat).powi(2) + (b.lon - lon).powi(2);
+                da.total_cmp(&db)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "フィクスチャに該当する地点がありません: lat={}, lon={}",
+                    lat, lon
+                )
+            })
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for FixtureProvider {
+    fn name(&self) -> &'static str {
+        "Fixture"
+    }
+
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, String> {
+        let query_lower = query.to_lowercase();
+        Ok(self
+            .data
+            .locations
+            .iter()
+            .filter(|l| {
+                l.name.contains(query) || l.aliases.iter().any(|a| a.to_lowercase() == query_lower)
+            })
+            .map(|l| GeoLocation {
+                name: l.name.clone(),
+                country: l.country.clone(),
+                state: l.state.clone(),
+                lat: l.lat,
+                lon: l.lon,
+            })
+            .collect())
+    }
+
+    async fn current(&self, lat: f64, lon: f64) -> Result<WeatherInfo, String> {
+        let location = self.find_by_coords(lat, lon)?;
+        Ok(WeatherInfo {
+            city: location.name.clone(),
+            weather: location.current.weather.clone(),
+            temperature: location.current.temperature,
+            humidity: location.current.humidity,
+            lat: location.lat,
+            lon: location.lon,
+            local_time: self.base_time,
+        })
+    }
+
+    async fn forecast(&self, lat: f64, lon: f64) -> Result<Vec<ForecastEntry>, String> {
+        let location = self.find_by_coords(lat, lon)?;
+        Ok(location
+            .forecast
+            .iter()
+            .map(|f| ForecastEntry {
+                time: self.base_time + Duration::hours(f.offset_hours),
+                weather: f.reading.weather.clone(),
+                temperature: f.reading.temperature,
+                humidity: f.reading.humidity,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> FixtureProvider {
+        let data: FixtureData = serde_json::from_str(
+            r#"{"locations": [
+                {"name": "府中市", "aliases": ["Fuchu"], "country": "JP", "state": "東京都",
+                 "lat": 35.6689, "lon": 139.4776,
+                 "current": {"weather": "晴れ", "temperature": 21.0, "humidity": 50},
+                 "forecast": [{"offset_hours": 3, "weather": "曇り", "temperature": 19.0, "humidity": 60}]},
+                {"name": "府中市", "country": "JP", "state": "広島県",
+                 "lat": 34.5683, "lon": 133.2366,
+                 "current": {"weather": "小雨", "temperature": 18.5, "humidity": 85}}
+            ]}"#,
+        )
+        .unwrap();
+        let base_time =
+            NaiveDateTime::parse_from_str("2025-10-05 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        FixtureProvider::new(data, base_time)
+    }
+
+    #[tokio::test]
+    async fn test_geocode_partial_name_and_alias() {
+        let provider = provider();
+        assert_eq!(provider.geocode("府中").await.unwrap().len(), 2);
+        assert_eq!(provider.geocode("fuchu").await.unwrap().len(), 1);
+        assert!(provider.geocode("札幌").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_current_by_nearest_coords() {
+        let provider = provider();
+        let info = provider.current(34.57, 133.24).await.unwrap();
+        assert_eq!(info.weather, "小雨");
+        assert_eq!(info.temperature, 18.5);
+
+        assert!(provider.current(43.06, 141.35).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_forecast_offsets_from_base_time() {
+        let provider = provider();
+        let entries = provider.forecast(35.6689, 139.4776).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].time.to_string(), "2025-10-05 15:00:00");
+        assert_eq!(entries[0].weather, "曇り");
+    }
+}
@@ -0,0 +1,57 @@
+pub mod fixture;
+pub mod open_meteo;
+pub mod openweathermap;
+
+use std::env;
+
+use async_trait::async_trait;
+
+use crate::weather::types::{ForecastEntry, GeoLocation, WeatherInfo};
+
+use fixture::FixtureProvider;
+use open_meteo::OpenMeteo;
+use openweathermap::OpenWeatherMap;
+
+/// フィクスチャファイルの既定パス
+const DEFAULT_FIXTURE_FILE: &str = "data/fixtures/weather.json";
+
+/// 天気データの取得元
+///
+/// 各実装は API 固有のレスポンスを `WeatherInfo` / `ForecastEntry` / `GeoLocation` に変換して返す。
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    /// 表示用のプロバイダ名
+    fn name(&self) -> &'static str;
+
+    /// 地名から地点候補を取得（見つからなければ空）
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, String>;
+
+    /// 緯度・経度から現在の天気を取得
+    async fn current(&self, lat: f64, lon: f64) -> Result<WeatherInfo, String>;
+
+    /// 緯度・経度から予報を取得（時刻は現地時刻、古い順）
+    async fn forecast(&self, lat: f64, lon: f64) -> Result<Vec<ForecastEntry>, String>;
+}
+
+/// 環境変数 WEATHER_PROVIDER からプロバイダを選ぶ
+///
+/// * `openweathermap`（既定） - OPENWEATHER_API_KEY が必要
+/// * `open-meteo` - API キー不要
+/// * `fixture` - WEATHER_FIXTURE_FILE（既定: data/fixtures/weather.json）を返すオフライン用
+pub fn provider_from_env() -> Result<Box<dyn WeatherProvider>, String> {
+    let name = env::var("WEATHER_PROVIDER").unwrap_or_default();
+
+    match name.trim().to_lowercase().as_str() {
+        "" | "openweathermap" | "owm" => Ok(Box::new(OpenWeatherMap::from_env()?)),
+        "open-meteo" | "openmeteo" => Ok(Box::new(OpenMeteo::new())),
+        "fixture" => {
+            let path = env::var("WEATHER_FIXTURE_FILE")
+                .unwrap_or_else(|_| DEFAULT_FIXTURE_FILE.to_string());
+            Ok(Box::new(FixtureProvider::from_file(&path)?))
+        }
+        other => Err(format!(
+            "不明な WEATHER_PROVIDER です: {}（openweathermap / open-meteo / fixture）",
+            other
+        )),
+    }
+}
@@ -0,0 +1,334 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use reqwest::Client;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::weather::provider::WeatherProvider;
+use crate::weather::types::{ForecastEntry, GeoLocation, WeatherInfo};
+
+/// Open-Meteo の時刻書式（timezone=auto で現地時刻）
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// 予報を取得する日数（今日 + 明日 + 予備）
+const FORECAST_DAYS: &str = "3";
+
+/// Geocoding API のレスポンス構造（該当なしだと results 自体がない）
+#[derive(Debug, Deserialize)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResult {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    country_code: String,
+    #[serde(default)]
+    admin1: Option<String>, // 都道府県・州
+}
+
+/// Forecast API のレスポンス構造（必要な部分のみ）
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    current: Option<CurrentBlock>,
+    #[serde(default)]
+    hourly: Option<HourlyBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CurrentBlock {
+    time: String,
+    temperature_2m: f64,
+    relative_humidity_2m: u32,
+    weather_code: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct HourlyBlock {
+    time: Vec<String>,
+    temperature_2m: Vec<f64>,
+    relative_humidity_2m: Vec<u32>,
+    weather_code: Vec<u32>,
+}
+
+/// Open-Meteo（API キー不要）
+pub struct OpenMeteo {
+    client: Client,
+}
+
+impl OpenMeteo {
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, String> {
+        let response = self
+            .client
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| format!("API リクエストエラー: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "API エラー（ステータス: {}）: {}",
+                status, error_text
+            ));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("JSON パースエラー: {}", e))
+    }
+}
+
+impl Default for OpenMeteo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "Open-Meteo"
+    }
+
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, String> {
+        let data: GeocodingResponse = self
+            .get_json(
+                "https://geocoding-api.open-meteo.com/v1/search",
+                &[
+                    ("name", query),
+                    ("count", "5"),
+                    ("language", "ja"),
+                    ("format", "json"),
+                ],
+            )
+            .await?;
+
+        Ok(data.results.into_iter().map(to_geo_location).collect())
+    }
+
+    async fn current(&self, lat: f64, lon: f64) -> Result<WeatherInfo, String> {
+        let lat = lat.to_string();
+        let lon = lon.to_string();
+        let data: ForecastResponse = self
+            .get_json(
+                "https://api.open-meteo.com/v1/forecast",
+                &[
+                    ("latitude", lat.as_str()),
+                    ("longitude", lon.as_str()),
+                    (
+                        "current",
+                        "temperature_2m,relative_humidity_2m,weather_code",
+                    ),
+                    ("timezone", "auto"),
+                ],
+            )
+            .await?;
+
+        to_weather_info(data)
+    }
+
+    async fn forecast(&self, lat: f64, lon: f64) -> Result<Vec<ForecastEntry>, String> {
+        let lat = lat.to_string();
+        let lon = lon.to_string();
+        let data: ForecastResponse = self
+            .get_json(
+                "https://api.open-meteo.com/v1/forecast",
+                &[
+                    ("latitude", lat.as_str()),
+                    ("longitude", lon.as_str()),
+                    ("hourly", "temperature_2m,relative_humidity_2m,weather_code"),
+                    ("timezone", "auto"),
+                    ("forecast_days", FORECAST_DAYS),
+                ],
+            )
+            .await?;
+
+        to_forecast_entries(data)
+    }
+}
+
+/// WMO 天気コードを日本語の説明に変換
+pub fn describe_weather_code(code: u32) -> &'static str {
+    match code {
+        0 => "快晴",
+        1 => "晴れ",
+        2 => "一部曇り",
+        3 => "曇り",
+        45 | 48 => "霧",
+        51 | 53 | 55 => "霧雨",
+        56 | 57 => "着氷性の霧雨",
+        61 => "小雨",
+        63 => "雨",
+        65 => "大雨",
+        66 | 67 => "着氷性の雨",
+        71 => "小雪",
+        73 => "雪",
+        75 => "大雪",
+        77 => "霧雪",
+        80..=82 => "にわか雨",
+        85 | 86 => "にわか雪",
+        95 => "雷雨",
+        96 | 99 => "ひょうを伴う雷雨",
+        _ => "不明",
+    }
+}
+
+fn to_geo_location(result: GeocodingResult) -> GeoLocation {
+    GeoLocation {
+        name: result.name,
+        country: result.country_code,
+        state: result.admin1,
+        lat: result.latitude,
+        lon: result.longitude,
+    }
+}
+
+/// current ブロックを WeatherInfo に変換
+/// 地点名は返らないので、呼び出し側で上書きする前提で座標を入れておく
+fn to_weather_info(data: ForecastResponse) -> Result<WeatherInfo, String> {
+    let current = data
+        .current
+        .ok_or_else(|| "レスポンスに current がありません".to_string())?;
+    let local_time = NaiveDateTime::parse_from_str(&current.time, TIME_FORMAT)
+        .map_err(|e| format!("観測時刻が不正です: {} ({})", current.time, e))?;
+
+    Ok(WeatherInfo {
+        city: format!("{:.2},{:.2}", data.latitude, data.longitude),
+        weather: describe_weather_code(current.weather_code).to_string(),
+        temperature: current.temperature_2m,
+        humidity: current.relative_humidity_2m,
+        lat: data.latitude,
+        lon: data.longitude,
+        local_time,
+    })
+}
+
+/// hourly ブロック（列ごとの配列）を 1 時間ごとの ForecastEntry に変換
+fn to_forecast_entries(data: ForecastResponse) -> Result<Vec<ForecastEntry>, String> {
+    let hourly = data
+        .hourly
+        .ok_or_else(|| "レスポンスに hourly がありません".to_string())?;
+
+    hourly
+        .time
+        .iter()
+        .zip(&hourly.temperature_2m)
+        .zip(&hourly.relative_humidity_2m)
+        .zip(&hourly.weather_code)
+        .map(|(((time, temperature), humidity), code)| {
+            let time = NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+                .map_err(|e| format!("予報の時刻が不正です: {} ({})", time, e))?;
+            Ok(ForecastEntry {
+                time,
+                weather: describe_weather_code(*code).to_string(),
+                temperature: *temperature,
+                humidity: *humidity,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_weather_code() {
+        assert_eq!(describe_weather_code(0), "快晴");
+        assert_eq!(describe_weather_code(63), "雨");
+        assert_eq!(describe_weather_code(81), "にわか雨");
+        assert_eq!(describe_weather_code(1000), "不明");
+    }
+
+    #[test]
+    fn test_geocoding_without_results() {
+        let data: GeocodingResponse =
+            serde_json::from_str(r#"{"generationtime_ms": 0.5}"#).unwrap();
+        assert!(data.results.is_empty());
+    }
+
+    #[test]
+    fn test_to_geo_location() {
+        let data: GeocodingResponse = serde_json::from_str(
+            r#"{"results": [{
+                "id": 1863905, "name": "府中市", "latitude": 34.5683, "longitude": 133.2366,
+                "country_code": "JP", "admin1": "広島県"
+            }]}"#,
+        )
+        .unwrap();
+
+        let loc = to_geo_location(data.results.into_iter().next().unwrap());
+        assert_eq!(loc.name, "府中市");
+        assert_eq!(loc.country, "JP");
+        assert_eq!(loc.state.as_deref(), Some("広島県"));
+    }
+
+    #[test]
+    fn test_to_weather_info() {
+        let data: ForecastResponse = serde_json::from_str(
+            r#"{
+                "latitude": 35.7, "longitude": 139.6875,
+                "current": {"time": "2025-10-05T12:00", "temperature_2m": 22.4,
+                            "relative_humidity_2m": 58, "weather_code": 3}
+            }"#,
+        )
+        .unwrap();
+
+        let info = to_weather_info(data).unwrap();
+        assert_eq!(info.weather, "曇り");
+        assert_eq!(info.temperature, 22.4);
+        assert_eq!(info.humidity, 58);
+        assert_eq!(info.city, "35.70,139.69");
+        assert_eq!(info.local_time.to_string(), "2025-10-05 12:00:00");
+    }
+
+    #[test]
+    fn test_to_forecast_entries() {
+        let data: ForecastResponse = serde_json::from_str(
+            r#"{
+                "latitude": 34.69, "longitude": 135.5,
+                "hourly": {
+                    "time": ["2025-10-06T00:00", "2025-10-06T01:00"],
+                    "temperature_2m": [19.1, 18.7],
+                    "relative_humidity_2m": [80, 85],
+                    "weather_code": [61, 63]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let entries = to_forecast_entries(data).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].time.to_string(), "2025-10-06 01:00:00");
+        assert_eq!(entries[1].weather, "雨");
+        assert_eq!(entries[1].humidity, 85);
+    }
+
+    #[test]
+    fn test_to_weather_info_without_current() {
+        let data: ForecastResponse =
+            serde_json::from_str(r#"{"latitude": 35.7, "longitude": 139.7}"#).unwrap();
+        assert!(to_weather_info(data).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::env;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use reqwest::Client;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::weather::provider::WeatherProvider;
+use crate::weather::types::{ForecastEntry, GeoLocation, WeatherInfo};
+
+/// ジオコーディングで取得する候補の最大数
+const GEOCODE_LIMIT: &str = "5";
+
+/// OpenWeatherMap API のレスポンス構造（必要な部分のみ）
+#[derive(Debug, Deserialize)]
+struct OpenWeatherResponse {
+    name: String,
+    coord: Coord,
+    main: Main,
+    weather: Vec<Weather>,
+    dt: i64,       // 観測時刻の UNIX 時刻（UTC）
+    timezone: i64, // UTC からのずれ（秒）
+}
+
+#[derive(Debug, Deserialize)]
+struct Coord {
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Main {
+    temp: f64,
+    humidity: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Weather {
+    description: String,
+}
+
+/// 5 日間予報 API のレスポンス構造（必要な部分のみ）
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    list: Vec<ForecastItem>,
+    city: ForecastCity,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastItem {
+    dt: i64, // UNIX 時刻（UTC）
+    main: Main,
+    weather: Vec<Weather>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForecastCity {
+    timezone: i64, // UTC からのずれ（秒）
+}
+
+/// Geocoding API のレスポンス要素（必要な部分のみ）
+#[derive(Debug, Deserialize)]
+struct GeoResponseItem {
+    name: String,
+    #[serde(default)]
+    local_names: HashMap<String, String>,
+    lat: f64,
+    lon: f64,
+    country: String,
+    #[serde(default)]
+    state: Option<String>,
+}
+
+/// OpenWeatherMap（要 OPENWEATHER_API_KEY）
+pub struct OpenWeatherMap {
+    client: Client,
+    api_key: String,
+}
+
+impl OpenWeatherMap {
+    /// 環境変数 OPENWEATHER_API_KEY から作成
+    pub fn from_env() -> Result<Self, String> {
+        let api_key = env::var("OPENWEATHER_API_KEY")
+            .map_err(|_| "環境変数 OPENWEATHER_API_KEY が設定されていません".to_string())?;
+
+        Ok(Self {
+            client: Client::new(),
+            api_key,
+        })
+    }
+
+    /// GET して JSON をパース（appid は自動で付ける）
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, String> {
+        let response = self
+            .client
+            .get(url)
+            .query(query)
+            .query(&[("appid", self.api_key.as_str())])
+            .send()
+            .await
+            .map_err(|e| format!("API リクエストエラー: {}", e))?;
+
+        // ステータスコード確認
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "API エラー（ステータス: {}）: {}",
+                status, error_text
+            ));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("JSON パースエラー: {}", e))
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenWeatherMap {
+    fn name(&self) -> &'static str {
+        "OpenWeatherMap"
+    }
+
+    async fn geocode(&self, query: &str) -> Result<Vec<GeoLocation>, String> {
+        let items: Vec<GeoResponseItem> = self
+            .get_json(
+                "https://api.openweathermap.org/geo/1.0/direct",
+                &[("q", query), ("limit", GEOCODE_LIMIT)],
+            )
+            .await?;
+
+        Ok(items.into_iter().map(to_geo_location).collect())
+    }
+
+    async fn current(&self, lat: f64, lon: f64) -> Result<WeatherInfo, String> {
+        let lat = lat.to_string();
+        let lon = lon.to_string();
+        let data: OpenWeatherResponse = self
+            .get_json(
+                "https://api.openweathermap.org/data/2.5/weather",
+                &[
+                    ("lat", lat.as_str()),
+                    ("lon", lon.as_str()),
+                    ("units", "metric"), // 摂氏
+                    ("lang", "ja"),      // 日本語の説明
+                ],
+            )
+            .await?;
+
+        to_weather_info(data)
+    }
+
+    async fn forecast(&self, lat: f64, lon: f64) -> Result<Vec<ForecastEntry>, String> {
+        let lat = lat.to_string();
+        let lon = lon.to_string();
+        let data: ForecastResponse = self
+            .get_json(
+                "https://api.openweathermap.org/data/2.5/forecast",
+                &[
+                    ("lat", lat.as_str()),
+                    ("lon", lon.as_str()),
+                    ("units", "metric"),
+                    ("lang", "ja"),
+                ],
+            )
+            .await?;
+
+        to_forecast_entries(data)
+    }
+}
+
+/// 先頭の天気の説明（なければ「不明」）
+fn first_description(weather: &[Weather]) -> String {
+    weather
+        .first()
+        .map(|w| w.description.clone())
+        .unwrap_or_else(|| "不明".to_string())
+}
+
+/// 現在の天気レスポンスを WeatherInfo に変換
+/// `city` には観測地点名（API が返す名前）が入る
+fn to_weather_info(data: OpenWeatherResponse) -> Result<WeatherInfo, String> {
+    let local_time = DateTime::from_timestamp(data.dt + data.timezone, 0)
+        .ok_or_else(|| format!("観測時刻が不正です: {}", data.dt))?
+        .naive_utc();
+
+    Ok(WeatherInfo {
+        city: data.name,
+        weather: first_description(&data.weather),
+        temperature: data.main.temp,
+        humidity: data.main.humidity,
+        lat: data.coord.lat,
+        lon: data.coord.lon,
+        local_time,
+    })
+}
+
+/// 予報レスポンスを現地時刻の ForecastEntry に変換
+fn to_forecast_entries(data: ForecastResponse) -> Result<Vec<ForecastEntry>, String> {
+    let offset = data.city.timezone;
+    data.list
+        .into_iter()
+        .map(|item| {
+            let time = DateTime::from_timestamp(item.dt + offset, 0)
+                .ok_or_else(|| format!("予報の時刻が不正です: {}", item.dt))?
+                .naive_utc();
+            Ok(ForecastEntry {
+                time,
+                weather: first_description(&item.weather),
+                temperature: item.main.temp,
+                humidity: item.main.humidity,
+            })
+        })
+        .collect()
+}
+
+/// Geocoding のレスポンスを GeoLocation に変換（日本語名があれば優先）
+fn to_geo_location(item: GeoResponseItem) -> GeoLocation {
+    let name = item.local_names.get("ja").cloned().unwrap_or(item.name);
+
+    GeoLocation {
+        name,
+        country: item.country,
+        state: item.state,
+        lat: item.lat,
+        lon: item.lon,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_geo_location_prefers_japanese_name() {
+        let item = GeoResponseItem {
+            name: "Fuchu".to_string(),
+            local_names: HashMap::from([("ja".to_string(), "府中市".to_string())]),
+            lat: 35.67,
+            lon: 139.48,
+            country: "JP".to_string(),
+            state: Some("Tokyo".to_string()),
+        };
+
+        let loc = to_geo_location(item);
+        assert_eq!(loc.name, "府中市");
+        assert_eq!(loc.state.as_deref(), Some("Tokyo"));
+    }
+
+    #[test]
+    fn test_to_geo_location_without_local_names() {
+        let item = GeoResponseItem {
+            name: "Springfield".to_string(),
+            local_names: HashMap::new(),
+            lat: 39.8,
+            lon: -89.6,
+            country: "US".to_string(),
+            state: None,
+        };
+
+        assert_eq!(to_geo_location(item).name, "Springfield");
+    }
+
+    #[test]
+    fn test_to_weather_info() {
+        let data: OpenWeatherResponse = serde_json::from_str(
+            r#"{
+                "coord": {"lon": 139.6917, "lat": 35.6895},
+                "weather": [{"id": 803, "main": "Clouds", "description": "曇りがち"}],
+                "main": {"temp": 21.3, "feels_like": 21.0, "humidity": 64},
+                "name": "東京都",
+                "dt": 1759676400,
+                "timezone": 32400
+            }"#,
+        )
+        .unwrap();
+
+        let info = to_weather_info(data).unwrap();
+        assert_eq!(info.city, "東京都");
+        assert_eq!(info.weather, "曇りがち");
+        assert_eq!(info.temperature, 21.3);
+        assert_eq!(info.humidity, 64);
+        assert_eq!(info.lat, 35.6895);
+        // 2025-10-05 15:00 UTC = 2025-10-06 00:00 JST
+        assert_eq!(info.local_time.to_string(), "2025-10-06 00:00:00");
+    }
+
+    #[test]
+    fn test_to_forecast_entries_uses_local_time() {
+        // 2025-10-05 15:00 UTC = 2025-10-06 00:00 JST
+        let data: ForecastResponse = serde_json::from_str(
+            r#"{
+                "list": [{
+                    "dt": 1759676400,
+                    "main": {"temp": 18.2, "humidity": 80},
+                    "weather": [{"description": "小雨"}]
+                }],
+                "city": {"name": "Tokyo", "timezone": 32400}
+            }"#,
+        )
+        .unwrap();
+
+        let entries = to_forecast_entries(data).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].time.to_string(), "2025-10-06 00:00:00");
+        assert_eq!(entries[0].weather, "小雨");
+    }
+}
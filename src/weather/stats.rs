@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Duration, NaiveDate};
+
+use crate::weather::storage::WeatherStore;
+use crate::weather::types::{MemoFilter, MemoStorage, SavedMemo};
+
+/// 「よくある天気」として表示する件数
+const TOP_CONDITIONS: usize = 3;
+
+/// スパークラインに使う文字（低い → 高い）
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// 1 日分の平均気温
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyPoint {
+    pub date: NaiveDate,
+    pub avg_temp: f64,
+    pub count: usize,
+}
+
+/// 都市ごとの集計結果
+#[derive(Debug)]
+pub struct CityStats {
+    pub city: String,
+    pub count: usize,
+    pub min_temp: f64,
+    pub max_temp: f64,
+    pub avg_temp: f64,
+    pub top_conditions: Vec<(String, usize)>, // (天気, 件数) 多い順
+    pub daily: Vec<DailyPoint>,               // 日付の古い順
+}
+
+/// 都市ごとに集計（都市名順）
+pub fn city_stats(memos: &[&SavedMemo]) -> Vec<CityStats> {
+    let mut by_city: BTreeMap<&str, Vec<&SavedMemo>> = BTreeMap::new();
+    for memo in memos {
+        by_city.entry(memo.city.as_str()).or_default().push(memo);
+    }
+
+    by_city
+        .into_iter()
+        .map(|(city, memos)| {
+            let temps: Vec<f64> = memos.iter().map(|m| m.temperature).collect();
+            CityStats {
+                city: city.to_string(),
+                count: memos.len(),
+                min_temp: temps.iter().copied().fold(f64::INFINITY, f64::min),
+                max_temp: temps.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                avg_temp: temps.iter().sum::<f64>() / temps.len() as f64,
+                top_conditions: top_conditions(&memos),
+                daily: daily_trend(&memos),
+            }
+        })
+        .collect()
+}
+
+/// 天気の出現回数を多い順に（同数なら名前順）
+fn top_conditions(memos: &[&SavedMemo]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for memo in memos {
+        *counts.entry(memo.weather.as_str()).or_default() += 1;
+    }
+
+    let mut sorted: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(weather, count)| (weather.to_string(), count))
+        .collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted.truncate(TOP_CONDITIONS);
+    sorted
+}
+
+/// 日ごとの平均気温（日付の古い順、メモのない日は含まない）
+pub fn daily_trend(memos: &[&SavedMemo]) -> Vec<DailyPoint> {
+    let mut by_date: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+    for memo in memos {
+        if let Some(date) = memo.date() {
+            by_date.entry(date).or_default().push(memo.temperature);
+        }
+    }
+
+    by_date
+        .into_iter()
+        .map(|(date, temps)| DailyPoint {
+            date,
+            avg_temp: temps.iter().sum::<f64>() / temps.len() as f64,
+            count: temps.len(),
+        })
+        .collect()
+}
+
+/// 値の列をスパークラインに（None は空白）
+pub fn sparkline(values: &[Option<f64>]) -> String {
+    let present: Vec<f64> = values.iter().flatten().copied().collect();
+    if present.is_empty() {
+        return String::new();
+    }
+    let min = present.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = present.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|v| match v {
+            None => ' ',
+            // なぜ: 全部同じ値だと幅 0 で割れないので中段で描く
+            Some(_) if range == 0.0 => SPARK_CHARS[SPARK_CHARS.len() / 2],
+            Some(v) => {
+                let ratio = (v - min) / range;
+                let idx = (ratio * (SPARK_CHARS.len() - 1) as f64).round() as usize;
+                SPARK_CHARS[idx]
+            }
+        })
+        .collect()
+}
+
+/// 日別データを 1 日刻みに並べ直す（メモのない日は None）
+fn fill_days(daily: &[DailyPoint]) -> Vec<Option<f64>> {
+    let (Some(first), Some(last)) = (daily.first(), daily.last()) else {
+        return Vec::new();
+    };
+
+    let mut values = Vec::new();
+    let mut date = first.date;
+    let mut points = daily.iter().peekable();
+    while date <= last.date {
+        let value = match points.peek() {
+            Some(p) if p.date == date => points.next().map(|p| p.avg_temp),
+            _ => None,
+        };
+        values.push(value);
+        date += Duration::days(1);
+    }
+    values
+}
+
+/// 統計を表とスパークラインで整形
+pub fn render_stats(storage: &MemoStorage, filter: &MemoFilter) -> String {
+    let memos = storage.search(filter);
+    if memos.is_empty() {
+        return "📭 条件に合うメモはありません".to_string();
+    }
+
+    let stats = city_stats(&memos);
+    let mut output = format!("📊 天気メモ統計（{}）\n", filter.describe());
+    output.push_str("都市 | 件数 | 最低 | 最高 | 平均 | よくある天気\n");
+    for s in &stats {
+        let conditions: Vec<String> = s
+            .top_conditions
+            .iter()
+            .map(|(weather, count)| format!("{}({})", weather, count))
+            .collect();
+        output.push_str(&format!(
+            "{} | {} | {:.1}℃ | {:.1}℃ | {:.1}℃ | {}\n",
+            s.city,
+            s.count,
+            s.min_temp,
+            s.max_temp,
+            s.avg_temp,
+            conditions.join(", ")
+        ));
+    }
+
+    output.push_str("\n📈 日別平均気温の推移:\n");
+    for s in &stats {
+        let (Some(first), Some(last)) = (s.daily.first(), s.daily.last()) else {
+            continue;
+        };
+        output.push_str(&format!(
+            "{} {} ({} {:.1}℃ → {} {:.1}℃)\n",
+            s.city,
+            sparkline(&fill_days(&s.daily)),
+            first.date.format("%m-%d"),
+            first.avg_temp,
+            last.date.format("%m-%d"),
+            last.avg_temp
+        ));
+    }
+
+    output
+}
+
+/// メモファイルを読み込んで統計を整形
+pub fn format_stats(store: &WeatherStore, filter: &MemoFilter) -> Result<String, String> {
+    let storage = store.load_memos()?;
+    Ok(render_stats(&storage, filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weather::types::parse_date;
+
+    fn memo(timestamp: &str, city: &str, weather: &str, temperature: f64) -> SavedMemo {
+        SavedMemo {
+            id: 0,
+            timestamp: timestamp.to_string(),
+            city: city.to_string(),
+            weather: weather.to_string(),
+            temperature,
+            note: String::new(),
+            lat: None,
+            lon: None,
+        }
+    }
+
+    fn sample_storage() -> MemoStorage {
+        MemoStorage {
+            next_id: 1,
+            memos: vec![
+                memo("2025-10-05 12:00:00", "東京", "晴れ", 26.0),
+                memo("2025-10-05 08:00:00", "東京", "曇り", 20.0),
+                memo("2025-10-03 12:00:00", "東京", "晴れ", 22.0),
+                memo("2025-10-04 12:00:00", "大阪", "小雨", 27.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_city_stats_min_max_avg() {
+        let storage = sample_storage();
+        let memos = storage.search(&MemoFilter::default());
+
+        let stats = city_stats(&memos);
+        assert_eq!(stats.len(), 2);
+
+        // BTreeMap なので都市名順（大阪 < 東京）
+        let tokyo = &stats[1];
+        assert_eq!(tokyo.city, "東京");
+        assert_eq!(tokyo.count, 3);
+        assert_eq!(tokyo.min_temp, 20.0);
+        assert_eq!(tokyo.max_temp, 26.0);
+        assert!((tokyo.avg_temp - 68.0 / 3.0).abs() < 1e-9);
+        assert_eq!(tokyo.top_conditions[0], ("晴れ".to_string(), 2));
+    }
+
+    #[test]
+    fn test_daily_trend_averages_per_day() {
+        let storage = sample_storage();
+        let memos = storage.search(&MemoFilter {
+            city: Some("東京".to_string()),
+            ..Default::default()
+        });
+
+        let daily = daily_trend(&memos);
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[0].date, parse_date("2025-10-03").unwrap());
+        assert_eq!(daily[1].avg_temp, 23.0);
+        assert_eq!(daily[1].count, 2);
+    }
+
+    #[test]
+    fn test_fill_days_inserts_gaps() {
+        let daily = vec![
+            DailyPoint {
+                date: parse_date("2025-10-03").unwrap(),
+                avg_temp: 22.0,
+                count: 1,
+            },
+            DailyPoint {
+                date: parse_date("2025-10-05").unwrap(),
+                avg_temp: 23.0,
+                count: 2,
+            },
+        ];
+
+        assert_eq!(fill_days(&daily), vec![Some(22.0), None, Some(23.0)]);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[Some(0.0), Some(7.0), None, Some(3.5)]), "▁█ ▅");
+        assert_eq!(sparkline(&[Some(5.0), Some(5.0)]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_render_stats_empty() {
+        let storage = MemoStorage::new();
+        let output = render_stats(&storage, &MemoFilter::default());
+        assert!(output.contains("ありません"));
+    }
+
+    #[test]
+    fn test_render_stats_table() {
+        let storage = sample_storage();
+        let output = render_stats(&storage, &MemoFilter::default());
+
+        assert!(output.contains("東京 | 3 | 20.0℃ | 26.0℃ | 22.7℃ | 晴れ(2), 曇り(1)"));
+        assert!(output.contains("大阪 | 1 | 27.0℃"));
+        assert!(output.contains("東京 ▁ █ (10-03 22.0℃ → 10-05 23.0℃)"));
+    }
+}
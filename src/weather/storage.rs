@@ -1,19 +1,29 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::weather::types::{MemoStorage, SavedMemo};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
-/// メモファイルのデフォルトパス
-const MEMO_FILE: &str = "data/weather_memos.json";
+use crate::weather::types::{
+    AlertStorage, MemoFilter, MemoStorage, MemoUpdate, SavedMemo, TIMESTAMP_FORMAT,
+};
 
-/// メモをファイルから読み込む
-/// ファイルが存在しない場合は空のストレージを返す
-pub fn load_memos() -> Result<MemoStorage, String> {
-    let path = Path::new(MEMO_FILE);
+/// 保存先のデフォルトのディレクトリ
+const DATA_DIR: &str = "data";
 
+/// メモファイルの名前
+const MEMO_FILE: &str = "weather_memos.json";
+
+/// アラートルールの保存先（メモと同じディレクトリに置く）
+const ALERT_FILE: &str = "weather_alerts.json";
+
+/// JSON ファイルを読み込む
+/// ファイルが存在しない場合は None を返す
+fn load_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
     // ファイルが存在しない場合は空を返す
     if !path.exists() {
-        return Ok(MemoStorage::new());
+        return Ok(None);
     }
 
     // ファイル読み込み
@@ -21,16 +31,13 @@ pub fn load_memos() -> Result<MemoStorage, String> {
         .map_err(|e| format!("ファイル読み込みエラー: {}", e))?;
 
     // JSON パース
-    let storage: MemoStorage = serde_json::from_str(&content)
-        .map_err(|e| format!("JSON パースエラー: {}", e))?;
+    let value = serde_json::from_str(&content).map_err(|e| format!("JSON パースエラー: {}", e))?;
 
-    Ok(storage)
+    Ok(Some(value))
 }
 
-/// メモをファイルに保存
-pub fn save_memos(storage: &MemoStorage) -> Result<(), String> {
-    let path = Path::new(MEMO_FILE);
-
+/// JSON ファイルに保存
+fn save_json<T: Serialize>(value: &T, path: &Path) -> Result<(), String> {
     // data ディレクトリがなければ作成
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
@@ -38,7 +45,7 @@ pub fn save_memos(storage: &MemoStorage) -> Result<(), String> {
     }
 
     // JSON シリアライズ（整形あり）
-    let json = serde_json::to_string_pretty(storage)
+    let json = serde_json::to_string_pretty(value)
         .map_err(|e| format!("JSON シリアライズエラー: {}", e))?;
 
     // ファイル書き込み
@@ -48,62 +55,436 @@ pub fn save_memos(storage: &MemoStorage) -> Result<(), String> {
     Ok(())
 }
 
-/// 新しいメモを追加して保存
-pub fn add_and_save_memo(memo: SavedMemo) -> Result<String, String> {
-    let mut storage = load_memos()?;
-    storage.add_memo(memo.clone());
-    save_memos(&storage)?;
-    
-    Ok(format!(
-        "✅ メモを保存しました: {} | {} {}℃",
-        memo.city, memo.weather, memo.temperature
-    ))
+/// 天気メモとアラートルールの保存先
+///
+/// 既定は data/ 以下（テストでは一時ディレクトリを指定する）
+#[derive(Debug, Clone)]
+pub struct WeatherStore {
+    memo_file: PathBuf,
+    alert_file: PathBuf,
+}
+
+impl Default for WeatherStore {
+    fn default() -> Self {
+        Self::new(DATA_DIR)
+    }
 }
 
-/// メモ一覧を文字列で取得（表示用）
-pub fn format_all_memos() -> Result<String, String> {
-    let storage = load_memos()?;
+impl WeatherStore {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            memo_file: dir.as_ref().join(MEMO_FILE),
+            alert_file: dir.as_ref().join(ALERT_FILE),
+        }
+    }
+
+    /// メモをファイルから読み込む
+    /// ファイルが存在しない場合は空のストレージを返す
+    /// ID のない古いデータは ID を振ってその場で保存し直す
+    pub fn load_memos(&self) -> Result<MemoStorage, String> {
+        let Some(mut storage) = load_json::<MemoStorage>(&self.memo_file)? else {
+            return Ok(MemoStorage::new());
+        };
+
+        // ID 導入前のファイルを移行
+        if storage.assign_missing_ids() {
+            self.save_memos(&storage)?;
+        }
+
+        Ok(storage)
+    }
+
+    /// メモをファイルに保存
+    pub fn save_memos(&self, storage: &MemoStorage) -> Result<(), String> {
+        save_json(storage, &self.memo_file)
+    }
+
+    /// アラートルールをファイルから読み込む
+    /// ファイルが存在しない場合は空のストレージを返す
+    pub fn load_alerts(&self) -> Result<AlertStorage, String> {
+        Ok(load_json(&self.alert_file)?.unwrap_or_default())
+    }
+
+    /// アラートルールをファイルに保存
+    pub fn save_alerts(&self, storage: &AlertStorage) -> Result<(), String> {
+        save_json(storage, &self.alert_file)
+    }
+
+    /// 新しいメモを追加して保存
+    pub fn add_and_save_memo(&self, memo: SavedMemo) -> Result<String, String> {
+        let mut storage = self.load_memos()?;
+        let id = storage.add_memo(memo.clone());
+        self.save_memos(&storage)?;
+
+        Ok(format!(
+            "✅ メモを保存しました (id: {}): {} | {} {}℃",
+            id, memo.city, memo.weather, memo.temperature
+        ))
+    }
+
+    /// メモを部分更新して保存
+    pub fn update_and_save_memo(&self, id: u32, update: MemoUpdate) -> Result<String, String> {
+        let mut storage = self.load_memos()?;
+        if !storage.update_memo(id, update) {
+            return Err(format!("メモが見つかりません (id: {})", id));
+        }
+        self.save_memos(&storage)?;
+
+        let memo = storage
+            .find_memo(id)
+            .ok_or_else(|| format!("メモが見つかりません (id: {})", id))?;
+        Ok(format!("✅ メモを更新しました: {}", format_memo(memo)))
+    }
+
+    /// メモを削除して保存
+    pub fn delete_and_save_memo(&self, id: u32) -> Result<String, String> {
+        let mut storage = self.load_memos()?;
+        if !storage.delete_memo(id) {
+            return Err(format!("メモが見つかりません (id: {})", id));
+        }
+        self.save_memos(&storage)?;
+
+        Ok(format!("🗑️ メモを削除しました (id: {})", id))
+    }
+
+    /// メモ一覧を文字列で取得（表示用）
+    pub fn format_all_memos(&self) -> Result<String, String> {
+        let storage = self.load_memos()?;
+
+        if storage.memos.is_empty() {
+            return Ok("📭 保存されているメモはありません".to_string());
+        }
+
+        let mut output = String::from("📋 保存済みメモ一覧:\n");
+        for memo in &storage.memos {
+            output.push_str(&format_memo(memo));
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
+
+    /// 条件に合うメモを検索して文字列で返す
+    pub fn format_search_results(&self, filter: &MemoFilter) -> Result<String, String> {
+        let storage = self.load_memos()?;
+        let results = storage.search(filter);
+
+        if results.is_empty() {
+            return Ok(format!(
+                "🔍 条件（{}）に合うメモは見つかりませんでした",
+                filter.describe()
+            ));
+        }
+
+        let mut output = format!(
+            "🔍 検索結果（{}）: {} 件\n",
+            filter.describe(),
+            results.len()
+        );
+        for memo in results {
+            output.push_str(&format_memo(memo));
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
 
-    if storage.memos.is_empty() {
-        return Ok("📭 保存されているメモはありません".to_string());
+    /// 条件に合うメモを CSV で書き出す（`path` が None なら文字列で返す）
+    pub fn export_csv(&self, filter: &MemoFilter, path: Option<&str>) -> Result<String, String> {
+        let storage = self.load_memos()?;
+        let memos = storage.search(filter);
+        let csv = memos_to_csv(&memos)?;
+        write_export(&csv, path, format!("{} 件", memos.len()))
     }
 
-    let mut output = String::from("📋 保存済みメモ一覧:\n");
-    for (i, memo) in storage.memos.iter().enumerate() {
-        output.push_str(&format!(
-            "{}. {} | {} | {} {}℃ | {}\n",
-            i + 1,
-            memo.timestamp,
-            memo.city,
-            memo.weather,
-            memo.temperature,
-            memo.note
-        ));
+    /// 条件に合う座標付きメモを GeoJSON で書き出す（`path` が None なら文字列で返す）
+    pub fn export_geojson(
+        &self,
+        filter: &MemoFilter,
+        path: Option<&str>,
+    ) -> Result<String, String> {
+        let storage = self.load_memos()?;
+        let memos = storage.search(filter);
+        let geojson = memos_to_geojson(&memos);
+        let count = geojson["features"].as_array().map_or(0, Vec::len);
+
+        let text = serde_json::to_string_pretty(&geojson)
+            .map_err(|e| format!("JSON シリアライズエラー: {}", e))?;
+        let skipped = memos.len() - count;
+        let summary = if skipped > 0 {
+            format!("{} 件（座標のない {} 件は除外）", count, skipped)
+        } else {
+            format!("{} 件", count)
+        };
+        write_export(&text, path, summary)
     }
 
-    Ok(output)
+    /// CSV ファイルのメモを取り込んで保存
+    pub fn import_csv(&self, path: &str) -> Result<String, String> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("ファイル読み込みエラー: {}", e))?;
+        let memos = memos_from_csv(&content)?;
+
+        let mut storage = self.load_memos()?;
+        let (added, skipped) = storage.import_memos(memos);
+        self.save_memos(&storage)?;
+
+        Ok(format!(
+            "📥 {} から {} 件取り込みました（重複 {} 件はスキップ）",
+            path, added, skipped
+        ))
+    }
 }
 
-/// 特定都市のメモを検索して文字列で返す
-pub fn format_memos_by_city(city: &str) -> Result<String, String> {
-    let storage = load_memos()?;
-    let results = storage.search_by_city(city);
+/// 1 件のメモを 1 行に整形
+pub fn format_memo(memo: &SavedMemo) -> String {
+    format!(
+        "[id:{}] {} | {} | {} {}℃ | {}",
+        memo.id, memo.timestamp, memo.city, memo.weather, memo.temperature, memo.note
+    )
+}
+
+/// CSV の列（この順で書き出す）
+const CSV_HEADER: [&str; 8] = [
+    "id",
+    "timestamp",
+    "city",
+    "weather",
+    "temperature",
+    "note",
+    "lat",
+    "lon",
+];
+
+/// CSV の 1 行
+///
+/// JSON 用の SavedMemo は座標がないと lat / lon を省くため、列数が揃うよう別に持つ
+#[derive(Serialize, Deserialize)]
+struct MemoCsvRow {
+    #[serde(default)]
+    id: u32,
+    timestamp: String,
+    city: String,
+    weather: String,
+    temperature: f64,
+    note: String,
+    #[serde(default)]
+    lat: Option<f64>,
+    #[serde(default)]
+    lon: Option<f64>,
+}
+
+impl From<&SavedMemo> for MemoCsvRow {
+    fn from(memo: &SavedMemo) -> Self {
+        Self {
+            id: memo.id,
+            timestamp: memo.timestamp.clone(),
+            city: memo.city.clone(),
+            weather: memo.weather.clone(),
+            temperature: memo.temperature,
+            note: memo.note.clone(),
+            lat: memo.lat,
+            lon: memo.lon,
+        }
+    }
+}
+
+impl From<MemoCsvRow> for SavedMemo {
+    fn from(row: MemoCsvRow) -> Self {
+        Self {
+            id: row.id,
+            timestamp: row.timestamp,
+            city: row.city,
+            weather: row.weather,
+            temperature: row.temperature,
+            note: row.note,
+            lat: row.lat,
+            lon: row.lon,
+        }
+    }
+}
 
-    if results.is_empty() {
-        return Ok(format!("🔍 「{}」のメモは見つかりませんでした", city));
+/// メモを CSV に変換（ヘッダー付き、座標のないメモは lat / lon が空欄）
+pub fn memos_to_csv(memos: &[&SavedMemo]) -> Result<String, String> {
+    // なぜ: 自動ヘッダーは 1 行目の serialize 時に出るので、0 件でも出るよう自分で書く
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    writer
+        .write_record(CSV_HEADER)
+        .map_err(|e| format!("CSV 書き込みエラー: {}", e))?;
+    for memo in memos {
+        writer
+            .serialize(MemoCsvRow::from(*memo))
+            .map_err(|e| format!("CSV 書き込みエラー: {}", e))?;
     }
 
-    let mut output = format!("🔍 「{}」のメモ:\n", city);
-    for (i, memo) in results.iter().enumerate() {
-        output.push_str(&format!(
-            "{}. {} | {} {}℃ | {}\n",
-            i + 1,
-            memo.timestamp,
-            memo.weather,
-            memo.temperature,
-            memo.note
-        ));
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| format!("CSV 書き込みエラー: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("CSV 書き込みエラー: {}", e))
+}
+
+/// CSV からメモを読み込む（id 列・lat / lon 列は省略可）
+///
+/// 日時が読めない行はエラーにする（行番号付き）
+pub fn memos_from_csv(content: &str) -> Result<Vec<SavedMemo>, String> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("CSV 読み込みエラー: {}", e))?
+        .clone();
+
+    let mut memos = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("CSV 読み込みエラー: {}", e))?;
+        // なぜ: 引用符内の改行があると記録数と行番号がずれるので、レコードの開始行を使う
+        let line = record.position().map_or(0, |pos| pos.line());
+        let row: MemoCsvRow = record
+            .deserialize(Some(&headers))
+            .map_err(|e| format!("CSV {} 行目: {}", line, e))?;
+        let memo = SavedMemo::from(row);
+        if memo.date().is_none() {
+            return Err(format!(
+                "CSV {} 行目: 日時は {} 形式で指定してください: {}",
+                line, TIMESTAMP_FORMAT, memo.timestamp
+            ));
+        }
+        memos.push(memo);
     }
+    Ok(memos)
+}
 
-    Ok(output)
+/// 座標付きのメモを GeoJSON の FeatureCollection に変換
+///
+/// 座標のないメモは含めない
+pub fn memos_to_geojson(memos: &[&SavedMemo]) -> Value {
+    let features: Vec<Value> = memos
+        .iter()
+        .filter_map(|memo| {
+            let (lat, lon) = (memo.lat?, memo.lon?);
+            Some(json!({
+                "type": "Feature",
+                // GeoJSON は [経度, 緯度] の順
+                "geometry": {"type": "Point", "coordinates": [lon, lat]},
+                "properties": {
+                    "id": memo.id,
+                    "timestamp": memo.timestamp,
+                    "city": memo.city,
+                    "weather": memo.weather,
+                    "temperature": memo.temperature,
+                    "note": memo.note,
+                },
+            }))
+        })
+        .collect();
+
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+/// 書き出し先があればファイルへ、なければ内容をそのまま返す
+fn write_export(content: &str, path: Option<&str>, summary: String) -> Result<String, String> {
+    let Some(path) = path else {
+        return Ok(content.to_string());
+    };
+
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
+    Ok(format!("📤 {} に {}を書き出しました", path, summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memo(id: u32, city: &str, note: &str, coords: Option<(f64, f64)>) -> SavedMemo {
+        SavedMemo {
+            id,
+            timestamp: "2025-10-05 12:00:00".to_string(),
+            city: city.to_string(),
+            weather: "晴れ".to_string(),
+            temperature: 21.5,
+            note: note.to_string(),
+            lat: coords.map(|c| c.0),
+            lon: coords.map(|c| c.1),
+        }
+    }
+
+    #[test]
+    fn test_csv_round_trip_with_quotes() {
+        let memos = [
+            memo(1, "東京", "散歩, \"快適\"", Some((35.68, 139.76))),
+            memo(2, "大阪", "改行\nあり", None),
+        ];
+        let refs: Vec<&SavedMemo> = memos.iter().collect();
+
+        let csv = memos_to_csv(&refs).unwrap();
+        assert!(csv.starts_with("id,timestamp,city,weather,temperature,note,lat,lon\n"));
+        assert!(csv.contains("\"散歩, \"\"快適\"\"\""));
+
+        let parsed = memos_from_csv(&csv).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].note, "散歩, \"快適\"");
+        assert_eq!(parsed[0].lat, Some(35.68));
+        assert_eq!(parsed[1].note, "改行\nあり");
+        assert_eq!(parsed[1].lon, None);
+    }
+
+    #[test]
+    fn test_memos_to_csv_empty_has_header() {
+        assert_eq!(
+            memos_to_csv(&[]).unwrap(),
+            "id,timestamp,city,weather,temperature,note,lat,lon\n"
+        );
+    }
+
+    #[test]
+    fn test_memos_from_csv_without_optional_columns() {
+        let csv =
+            "timestamp,city,weather,temperature,note\n2025-10-05 08:00:00,札幌,雪,-2.5,寒い\n";
+        let memos = memos_from_csv(csv).unwrap();
+        assert_eq!(memos[0].id, 0);
+        assert_eq!(memos[0].temperature, -2.5);
+        assert_eq!(memos[0].lat, None);
+    }
+
+    #[test]
+    fn test_memos_from_csv_reports_line() {
+        let csv = "timestamp,city,weather,temperature,note\n\
+                   2025-10-05 08:00:00,札幌,雪,-2.5,\n\
+                   2025/10/05,東京,晴れ,20,\n";
+        let err = memos_from_csv(csv).unwrap_err();
+        assert!(err.contains("3 行目"));
+
+        let csv = "timestamp,city,weather,temperature,note\n2025-10-05 08:00:00,札幌,雪,寒い,\n";
+        assert!(memos_from_csv(csv).unwrap_err().contains("2 行目"));
+
+        // 引用符内の改行の後でも実際の行番号を返す
+        let csv = "timestamp,city,weather,temperature,note\n\
+                   2025-10-05 08:00:00,札幌,雪,-2.5,\"1 行目\n2 行目\"\n\
+                   2025/10/05,東京,晴れ,20,\n";
+        assert!(memos_from_csv(csv).unwrap_err().contains("4 行目"));
+    }
+
+    #[test]
+    fn test_memos_to_geojson_skips_memos_without_coords() {
+        let memos = [
+            memo(1, "東京", "", Some((35.68, 139.76))),
+            memo(2, "大阪", "", None),
+        ];
+        let refs: Vec<&SavedMemo> = memos.iter().collect();
+
+        let geojson = memos_to_geojson(&refs);
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(
+            features[0]["geometry"]["coordinates"],
+            json!([139.76, 35.68])
+        );
+        assert_eq!(features[0]["properties"]["city"], "東京");
+    }
 }
@@ -1,59 +1,272 @@
+use std::io::{self, Write};
+
 use chrono::Local;
 
-use crate::weather::api::{fetch_weather as api_fetch_weather, format_weather_info};
-use crate::weather::storage::{add_and_save_memo, format_all_memos, format_memos_by_city};
-use crate::weather::types::{FetchWeatherArgs, SaveMemoArgs, SavedMemo, SearchMemosArgs};
+use crate::weather::alerts::format_rules;
+use crate::weather::api::{
+    fetch_weather_by_coords, format_locations, format_weather_info, geocode_city,
+};
+use crate::weather::stats::format_stats;
+use crate::weather::storage::{WeatherStore, format_memo};
+use crate::weather::types::{
+    AlertRule, CreateAlertArgs, DeleteAlertArgs, DeleteMemoArgs, FetchWeatherArgs, MemoFilter,
+    MemoUpdate, ResolveCityArgs, SaveMemoArgs, SavedMemo, SearchMemosArgs, TIMESTAMP_FORMAT,
+    UpdateMemoArgs, WeatherStatsArgs, parse_date,
+};
 
 /// ツール1: 天気を取得
 /// 
-/// OpenWeatherMap API を呼び出して天気情報を取得し、
-/// 整形した文字列を返す
+/// lat / lon が指定されていればその座標で取得する。
+/// 都市名だけの場合はジオコーディングし、候補が複数あれば
+/// 天気は取得せず候補一覧を返してモデルに確認を促す
 pub async fn tool_fetch_weather(args: FetchWeatherArgs) -> Result<String, String> {
-    println!("🔧 ツール実行: fetch_weather(city=\"{}\")", args.city);
+    println!(
+        "🔧 ツール実行: fetch_weather(city=\"{}\", lat={:?}, lon={:?})",
+        args.city, args.lat, args.lon
+    );
 
-    let weather_info = api_fetch_weather(&args.city).await?;
-    let formatted = format_weather_info(&weather_info);
+    if let (Some(lat), Some(lon)) = (args.lat, args.lon) {
+        let mut weather_info = fetch_weather_by_coords(lat, lon).await?;
+        weather_info.city = args.city;
+        return Ok(format_weather_info(&weather_info));
+    }
 
-    Ok(formatted)
+    let candidates = geocode_city(&args.city).await?;
+    match candidates.as_slice() {
+        [] => Err(format!("「{}」に該当する地点が見つかりません", args.city)),
+        [location] => {
+            let mut weather_info = fetch_weather_by_coords(location.lat, location.lon).await?;
+            weather_info.city = location.name.clone();
+            Ok(format_weather_info(&weather_info))
+        }
+        _ => Ok(format!(
+            "{}⚠️ 地点が特定できません。ユーザーにどの地点か確認し、lat / lon を指定して再度 fetch_weather を呼んでください",
+            format_locations(&args.city, &candidates)
+        )),
+    }
 }
 
 /// ツール2: 天気メモを保存
 /// 
 /// 天気情報とユーザーのメモを JSON ファイルに保存
-pub async fn tool_save_weather_memo(args: SaveMemoArgs) -> Result<String, String> {
+pub async fn tool_save_weather_memo(
+    store: WeatherStore,
+    args: SaveMemoArgs,
+) -> Result<String, String> {
     println!(
         "🔧 ツール実行: save_weather_memo(city=\"{}\", weather=\"{}\", temp={}, note=\"{}\")",
         args.city, args.weather, args.temperature, args.note
     );
 
     // 現在時刻を取得（ISO8601 形式）
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let timestamp = Local::now().format(TIMESTAMP_FORMAT).to_string();
 
     let memo = SavedMemo {
+        id: 0, // 採番は保存時
         timestamp,
         city: args.city,
         weather: args.weather,
         temperature: args.temperature,
         note: args.note,
+        lat: args.lat,
+        lon: args.lon,
     };
 
-    add_and_save_memo(memo)
+    store.add_and_save_memo(memo)
 }
 
 /// ツール3: 全メモを一覧表示
 /// 
 /// 保存されている全ての天気メモを新しい順に表示
-pub async fn tool_list_memos() -> Result<String, String> {
+pub async fn tool_list_memos(store: WeatherStore) -> Result<String, String> {
     println!("🔧 ツール実行: list_memos()");
 
-    format_all_memos()
+    store.format_all_memos()
 }
 
-/// ツール4: 特定都市のメモを検索
+/// ツール4: 条件でメモを検索
 /// 
-/// 指定された都市名を含むメモだけを抽出して表示
-pub async fn tool_search_memos(args: SearchMemosArgs) -> Result<String, String> {
-    println!("🔧 ツール実行: search_memos(city=\"{}\")", args.city);
+/// 都市・期間・気温・天気キーワード・メモ本文を組み合わせて絞り込む
+pub async fn tool_search_memos(
+    store: WeatherStore,
+    args: SearchMemosArgs,
+) -> Result<String, String> {
+    println!("🔧 ツール実行: search_memos({:?})", args);
+
+    let filter = MemoFilter::try_from(args)?;
+    store.format_search_results(&filter)
+}
+
+/// ツール5: 都市名を地点候補に解決
+///
+/// 同名の地点が複数ある場合にユーザーへ確認するための候補一覧（座標付き）を返す
+pub async fn tool_resolve_city(args: ResolveCityArgs) -> Result<String, String> {
+    println!("🔧 ツール実行: resolve_city(city=\"{}\")", args.city);
+
+    let candidates = geocode_city(&args.city).await?;
+    Ok(format_locations(&args.city, &candidates))
+}
+
+/// ツール6: 天気メモの統計
+///
+/// 都市ごとの最低・最高・平均気温、よくある天気、日別の推移を返す
+pub async fn tool_weather_stats(
+    store: WeatherStore,
+    args: WeatherStatsArgs,
+) -> Result<String, String> {
+    println!(
+        "🔧 ツール実行: weather_stats(city={:?}, from={:?}, to={:?})",
+        args.city, args.from, args.to
+    );
+
+    let filter = MemoFilter {
+        city: args.city,
+        from: args.from.as_deref().map(parse_date).transpose()?,
+        to: args.to.as_deref().map(parse_date).transpose()?,
+        ..Default::default()
+    };
+    format_stats(&store, &filter)
+}
+
+/// ツール7: 天気メモを修正
+///
+/// 変更内容を表示し、端末でユーザーの確認が取れた場合だけ上書きする
+pub async fn tool_update_weather_memo(
+    store: WeatherStore,
+    args: UpdateMemoArgs,
+) -> Result<String, String> {
+    println!("🔧 ツール実行: update_weather_memo(id={})", args.id);
+
+    let storage = store.load_memos()?;
+    let current = storage
+        .find_memo(args.id)
+        .ok_or_else(|| format!("メモが見つかりません (id: {})", args.id))?;
+
+    let mut after = current.clone();
+    if let Some(city) = &args.city {
+        after.city = city.clone();
+    }
+    if let Some(weather) = &args.weather {
+        after.weather = weather.clone();
+    }
+    if let Some(temperature) = args.temperature {
+        after.temperature = temperature;
+    }
+    if let Some(note) = &args.note {
+        after.note = note.clone();
+    }
+
+    println!("  変更前: {}", format_memo(current));
+    println!("  変更後: {}", format_memo(&after));
+    if !confirm("このメモを更新しますか？")? {
+        return Ok(format!(
+            "ユーザーが更新をキャンセルしました (id: {})",
+            args.id
+        ));
+    }
+
+    store.update_and_save_memo(
+        args.id,
+        MemoUpdate {
+            city: args.city,
+            weather: args.weather,
+            temperature: args.temperature,
+            note: args.note,
+        },
+    )
+}
+
+/// ツール8: 天気メモを削除
+///
+/// 削除対象を表示し、端末でユーザーの確認が取れた場合だけ削除する
+pub async fn tool_delete_weather_memo(
+    store: WeatherStore,
+    args: DeleteMemoArgs,
+) -> Result<String, String> {
+    println!("🔧 ツール実行: delete_weather_memo(id={})", args.id);
+
+    let storage = store.load_memos()?;
+    let target = storage
+        .find_memo(args.id)
+        .ok_or_else(|| format!("メモが見つかりません (id: {})", args.id))?;
+
+    println!("  削除対象: {}", format_memo(target));
+    if !confirm("このメモを削除しますか？")? {
+        return Ok(format!(
+            "ユーザーが削除をキャンセルしました (id: {})",
+            args.id
+        ));
+    }
+
+    store.delete_and_save_memo(args.id)
+}
+
+/// ツール9: アラートルールを作成
+///
+/// 「東京の気温が 30℃ を超えたら」「明日大阪で雨なら」のような条件を保存する
+pub async fn tool_create_alert(
+    store: WeatherStore,
+    args: CreateAlertArgs,
+) -> Result<String, String> {
+    println!("🔧 ツール実行: create_alert({:?})", args);
+
+    let rule = AlertRule::try_from(args)?;
+    let mut storage = store.load_alerts()?;
+    let id = storage.add_rule(rule.clone());
+    store.save_alerts(&storage)?;
+
+    Ok(format!("✅ アラートを登録しました (id: {}): {}", id, rule))
+}
+
+/// ツール10: アラートルールを一覧表示
+pub async fn tool_list_alerts(store: WeatherStore) -> Result<String, String> {
+    println!("🔧 ツール実行: list_alerts()");
+
+    Ok(format_rules(&store.load_alerts()?))
+}
+
+/// ツール11: アラートルールを削除
+///
+/// 削除対象を表示し、端末でユーザーの確認が取れた場合だけ削除する
+pub async fn tool_delete_alert(
+    store: WeatherStore,
+    args: DeleteAlertArgs,
+) -> Result<String, String> {
+    println!("🔧 ツール実行: delete_alert(id={})", args.id);
+
+    let mut storage = store.load_alerts()?;
+    let target = storage
+        .find_rule(args.id)
+        .ok_or_else(|| format!("アラートが見つかりません (id: {})", args.id))?;
+
+    println!("  削除対象: [id:{}] {}", target.id, target);
+    if !confirm("このアラートを削除しますか？")? {
+        return Ok(format!(
+            "ユーザーが削除をキャンセルしました (id: {})",
+            args.id
+        ));
+    }
+
+    storage.delete_rule(args.id);
+    store.save_alerts(&storage)?;
+    Ok(format!("🗑️ アラートを削除しました (id: {})", args.id))
+}
+
+/// 実行前に confirm() で y/N を聞くツール（confirm() を呼ぶツールを足したらここにも足す）
+pub const CONFIRM_TOOLS: [&str; 3] = ["update_weather_memo", "delete_weather_memo", "delete_alert"];
+
+/// 破壊的な操作の前に端末で y/N を確認する
+// なぜ: モデルの判断だけで上書き・削除させず、必ず人が最終確認する
+fn confirm(question: &str) -> Result<bool, String> {
+    print!("⚠️ {} (y/N): ", question);
+    io::stdout()
+        .flush()
+        .map_err(|e| format!("出力エラー: {}", e))?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| format!("入力エラー: {}", e))?;
 
-    format_memos_by_city(&args.city)
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
 }
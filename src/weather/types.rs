@@ -1,69 +1,1036 @@
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{Local, NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::function_call::schema::{SchemaType, enum_schema, tool_args};
 
-/// OpenWeatherMap API からのレスポンス情報
+/// SavedMemo.timestamp の書式
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 現在の天気（どの WeatherProvider も同じ形で返す）
 #[derive(Debug, Deserialize)]
 pub struct WeatherInfo {
     pub city: String,
-    pub weather: String,  // 天気の説明 ("晴れ", "曇り", etc.)
+    pub weather: String,           // 天気の説明 ("晴れ", "曇り", etc.)
+    pub temperature: f64,          // 気温（摂氏）
+    pub humidity: u32,             // 湿度 (%)
+    pub lat: f64,                  // 緯度
+    pub lon: f64,                  // 経度
+    pub local_time: NaiveDateTime, // 観測時刻（都市の現地時刻）
+}
+
+/// 予報の 1 コマ（3 時間ごと、時刻は都市の現地時刻）
+#[derive(Debug, Clone)]
+pub struct ForecastEntry {
+    pub time: NaiveDateTime,
+    pub weather: String,  // 天気の説明
     pub temperature: f64, // 気温（摂氏）
     pub humidity: u32,    // 湿度 (%)
 }
 
+/// ジオコーディングで得た地点候補
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoLocation {
+    pub name: String,          // 地名（日本語名があれば日本語）
+    pub country: String,       // 国コード ("JP", "US", etc.)
+    pub state: Option<String>, // 都道府県・州
+    pub lat: f64,              // 緯度
+    pub lon: f64,              // 経度
+}
+
 /// 保存する天気メモ
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedMemo {
+    #[serde(default)]
+    pub id: u32, // 0 は未採番（ID 導入前のデータ）
     pub timestamp: String, // ISO8601 形式の日時
     pub city: String,      // 都市名
     pub weather: String,   // 天気
     pub temperature: f64,  // 気温
     pub note: String,      // ユーザーのメモ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lat: Option<f64>, // 緯度（座標付きで記録したメモのみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lon: Option<f64>, // 経度
+}
+
+impl SavedMemo {
+    /// 取得した天気情報から現在時刻のメモを作成
+    pub fn from_weather(info: &WeatherInfo, note: String) -> Self {
+        Self {
+            id: 0, // 採番は MemoStorage::add_memo で行う
+            timestamp: Local::now().format(TIMESTAMP_FORMAT).to_string(),
+            city: info.city.clone(),
+            weather: info.weather.clone(),
+            temperature: info.temperature,
+            note,
+            lat: Some(info.lat),
+            lon: Some(info.lon),
+        }
+    }
+
+    /// メモの日付（timestamp が読めなければ None）
+    pub fn date(&self) -> Option<NaiveDate> {
+        NaiveDateTime::parse_from_str(&self.timestamp, TIMESTAMP_FORMAT)
+            .ok()
+            .map(|dt| dt.date())
+    }
+}
+
+/// "YYYY-MM-DD" を日付に変換
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format!("日付は YYYY-MM-DD 形式で指定してください: {}", s))
+}
+
+/// メモの検索条件（すべて AND。None / 空の項目は条件なし）
+#[derive(Debug, Default, Clone)]
+pub struct MemoFilter {
+    pub city: Option<String>,          // 都市名の部分一致
+    pub from: Option<NaiveDate>,       // この日以降（含む）
+    pub to: Option<NaiveDate>,         // この日以前（含む）
+    pub min_temp: Option<f64>,         // 気温の下限（含む）
+    pub max_temp: Option<f64>,         // 気温の上限（含む）
+    pub weather_keywords: Vec<String>, // いずれかを天気に含む
+    pub note: Option<String>,          // メモ本文の部分一致
+}
+
+impl MemoFilter {
+    /// メモが全条件を満たすか
+    pub fn matches(&self, memo: &SavedMemo) -> bool {
+        self.matches_city(memo)
+            && self.matches_date(memo)
+            && self.min_temp.is_none_or(|min| memo.temperature >= min)
+            && self.max_temp.is_none_or(|max| memo.temperature <= max)
+            && self.matches_weather(memo)
+            && self
+                .note
+                .as_ref()
+                .is_none_or(|note| contains_ignore_case(&memo.note, note))
+    }
+
+    fn matches_city(&self, memo: &SavedMemo) -> bool {
+        self.city
+            .as_ref()
+            .is_none_or(|city| contains_ignore_case(&memo.city, city))
+    }
+
+    fn matches_date(&self, memo: &SavedMemo) -> bool {
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        // 日付範囲が指定されているときは日付が読めないメモを除外
+        let Some(date) = memo.date() else {
+            return false;
+        };
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+
+    fn matches_weather(&self, memo: &SavedMemo) -> bool {
+        self.weather_keywords.is_empty()
+            || self
+                .weather_keywords
+                .iter()
+                .any(|k| contains_ignore_case(&memo.weather, k))
+    }
+
+    /// 条件を人が読める形に（条件がなければ「条件なし」）
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(city) = &self.city {
+            parts.push(format!("都市「{}」", city));
+        }
+        if self.from.is_some() || self.to.is_some() {
+            parts.push(format!(
+                "期間 {}〜{}",
+                self.from.map(|d| d.to_string()).unwrap_or_default(),
+                self.to.map(|d| d.to_string()).unwrap_or_default()
+            ));
+        }
+        if self.min_temp.is_some() || self.max_temp.is_some() {
+            parts.push(format!(
+                "気温 {}〜{}℃",
+                self.min_temp.map(|t| t.to_string()).unwrap_or_default(),
+                self.max_temp.map(|t| t.to_string()).unwrap_or_default()
+            ));
+        }
+        if !self.weather_keywords.is_empty() {
+            let keywords: Vec<String> = self
+                .weather_keywords
+                .iter()
+                .map(|k| format!("「{}」", k))
+                .collect();
+            parts.push(format!("天気{}", keywords.join("")));
+        }
+        if let Some(note) = &self.note {
+            parts.push(format!("メモ「{}」", note));
+        }
+
+        if parts.is_empty() {
+            "条件なし".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// 大文字小文字を区別しない部分一致（英語の都市名・天気向け）
+fn contains_ignore_case(text: &str, pattern: &str) -> bool {
+    text.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+/// メモの部分更新（None の項目は変更しない）
+#[derive(Debug, Default)]
+pub struct MemoUpdate {
+    pub city: Option<String>,
+    pub weather: Option<String>,
+    pub temperature: Option<f64>,
+    pub note: Option<String>,
 }
 
 /// メモ全体を格納する構造
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MemoStorage {
     pub memos: Vec<SavedMemo>,
+    // なぜ: 最大 ID + 1 だと削除した ID が再利用されてしまうので、採番済みの続きを保存する
+    #[serde(default)]
+    pub next_id: u32,
+}
+
+impl Default for MemoStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MemoStorage {
     /// 空のストレージを作成
     pub fn new() -> Self {
-        Self { memos: Vec::new() }
+        Self {
+            memos: Vec::new(),
+            next_id: 1,
+        }
     }
 
     /// メモを追加（新しい順に並べる）
-    pub fn add_memo(&mut self, memo: SavedMemo) {
+    /// 採番した ID を返す
+    pub fn add_memo(&mut self, mut memo: SavedMemo) -> u32 {
+        memo.id = self.issue_id();
+        let id = memo.id;
         self.memos.insert(0, memo); // 先頭に追加
+        id
     }
 
-    /// 特定都市のメモを検索
-    pub fn search_by_city(&self, city: &str) -> Vec<&SavedMemo> {
-        self.memos
-            .iter()
-            .filter(|m| m.city.contains(city))
-            .collect()
+    /// 外部から取り込んだメモを追加する（ID は振り直す）
+    ///
+    /// 日時・都市・メモ本文が同じメモがすでにあれば重複としてスキップする。
+    /// 追加後は日時の新しい順に並べ直す。
+    ///
+    /// # Returns
+    /// (追加した件数, スキップした件数)
+    pub fn import_memos(&mut self, mut memos: Vec<SavedMemo>) -> (usize, usize) {
+        // 古い順に採番する
+        memos.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+        let (mut added, mut skipped) = (0, 0);
+        for memo in memos {
+            let duplicated = self.memos.iter().any(|m| {
+                m.timestamp == memo.timestamp && m.city == memo.city && m.note == memo.note
+            });
+            if duplicated {
+                skipped += 1;
+                continue;
+            }
+            self.add_memo(memo);
+            added += 1;
+        }
+
+        // timestamp は "YYYY-MM-DD HH:MM:SS" なので文字列順 = 日時順
+        self.memos.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        (added, skipped)
+    }
+
+    /// ID のないメモ（ID 導入前のデータ）に古い順で ID を振る
+    /// 1 件でも振った場合は true
+    pub fn assign_missing_ids(&mut self) -> bool {
+        let mut changed = false;
+        // memos は新しい順なので、後ろから振ると古いメモほど小さい ID になる
+        for i in (0..self.memos.len()).rev() {
+            if self.memos[i].id == 0 {
+                self.memos[i].id = self.issue_id();
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// ID でメモを探す
+    pub fn find_memo(&self, id: u32) -> Option<&SavedMemo> {
+        self.memos.iter().find(|m| m.id == id)
+    }
+
+    /// メモを部分更新（成功したら true）
+    pub fn update_memo(&mut self, id: u32, update: MemoUpdate) -> bool {
+        let Some(memo) = self.memos.iter_mut().find(|m| m.id == id) else {
+            return false;
+        };
+        if let Some(city) = update.city {
+            memo.city = city;
+        }
+        if let Some(weather) = update.weather {
+            memo.weather = weather;
+        }
+        if let Some(temperature) = update.temperature {
+            memo.temperature = temperature;
+        }
+        if let Some(note) = update.note {
+            memo.note = note;
+        }
+        true
+    }
+
+    /// メモを削除（成功したら true）
+    pub fn delete_memo(&mut self, id: u32) -> bool {
+        let before_len = self.memos.len();
+        self.memos.retain(|m| m.id != id);
+        self.memos.len() < before_len
+    }
+
+    /// 次の ID を払い出す
+    /// next_id が古いファイル由来で 0 や既存 ID 以下でも重複しないようにする
+    fn issue_id(&mut self) -> u32 {
+        let max_id = self.memos.iter().map(|m| m.id).max().unwrap_or(0);
+        let id = self.next_id.max(max_id + 1);
+        self.next_id = id + 1;
+        id
+    }
+
+    /// 条件に合うメモを検索（新しい順のまま）
+    pub fn search(&self, filter: &MemoFilter) -> Vec<&SavedMemo> {
+        self.memos.iter().filter(|m| filter.matches(m)).collect()
     }
 }
 
-// ツール用の引数型
+// アラート関連の型
 
-/// fetch_weather ツール用引数
-#[derive(Debug, Deserialize)]
-pub struct FetchWeatherArgs {
-    pub city: String,
+/// アラートで監視する数値
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    Temp,
+    Humidity,
 }
 
-/// save_weather_memo ツール用引数
-#[derive(Debug, Deserialize)]
-pub struct SaveMemoArgs {
-    pub city: String,
-    pub weather: String,
-    pub temperature: f64,
-    pub note: String,
+/// しきい値との比較（より大きい / より小さい）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Comparison {
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = "<")]
+    Below,
 }
 
-/// search_memos ツール用引数
-#[derive(Debug, Deserialize)]
-pub struct SearchMemosArgs {
+/// アラートの条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// 気温・湿度がしきい値を超える（下回る）
+    Threshold {
+        metric: AlertMetric,
+        op: Comparison,
+        value: f64,
+    },
+    /// 天気にキーワードを含む（"rain", "雨" など）
+    Weather { keyword: String },
+}
+
+/// どの時点の天気で判定するか
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertWhen {
+    Now,
+    Tomorrow,
+}
+
+// ツールの引数で使う enum の Schema（値は serde の rename に従う）
+
+impl SchemaType for AlertMetric {
+    fn schema() -> Value {
+        enum_schema(&[AlertMetric::Temp, AlertMetric::Humidity])
+    }
+}
+
+impl SchemaType for Comparison {
+    fn schema() -> Value {
+        enum_schema(&[Comparison::Above, Comparison::Below])
+    }
+}
+
+impl SchemaType for AlertWhen {
+    fn schema() -> Value {
+        enum_schema(&[AlertWhen::Now, AlertWhen::Tomorrow])
+    }
+}
+
+impl Display for AlertMetric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertMetric::Temp => write!(f, "気温"),
+            AlertMetric::Humidity => write!(f, "湿度"),
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparison::Above => write!(f, ">"),
+            Comparison::Below => write!(f, "<"),
+        }
+    }
+}
+
+impl Display for AlertWhen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertWhen::Now => write!(f, "現在"),
+            AlertWhen::Tomorrow => write!(f, "明日"),
+        }
+    }
+}
+
+/// 保存するアラートルール
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    #[serde(default)]
+    pub id: u32, // 0 は未採番
     pub city: String,
+    pub condition: AlertCondition,
+    pub when: AlertWhen,
+}
+
+impl Display for AlertRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.condition {
+            AlertCondition::Threshold { metric, op, value } => {
+                write!(
+                    f,
+                    "{} の{} {} {}（{}）",
+                    self.city, metric, op, value, self.when
+                )
+            }
+            AlertCondition::Weather { keyword } => {
+                write!(f, "{} で「{}」（{}）", self.city, keyword, self.when)
+            }
+        }
+    }
+}
+
+/// アラートルール全体を格納する構造
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlertStorage {
+    pub rules: Vec<AlertRule>,
+    #[serde(default)]
+    pub next_id: u32,
+}
+
+impl Default for AlertStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlertStorage {
+    /// 空のストレージを作成
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// ルールを追加（登録順）
+    /// 採番した ID を返す
+    pub fn add_rule(&mut self, mut rule: AlertRule) -> u32 {
+        let max_id = self.rules.iter().map(|r| r.id).max().unwrap_or(0);
+        rule.id = self.next_id.max(max_id + 1);
+        self.next_id = rule.id + 1;
+        let id = rule.id;
+        self.rules.push(rule);
+        id
+    }
+
+    /// ID でルールを探す
+    pub fn find_rule(&self, id: u32) -> Option<&AlertRule> {
+        self.rules.iter().find(|r| r.id == id)
+    }
+
+    /// ルールを削除（成功したら true）
+    pub fn delete_rule(&mut self, id: u32) -> bool {
+        let before_len = self.rules.len();
+        self.rules.retain(|r| r.id != id);
+        self.rules.len() < before_len
+    }
+}
+
+// ツール用の引数型
+
+tool_args! {
+    /// fetch_weather ツール用引数
+    /// lat / lon があれば座標で取得する（resolve_city で確定した地点など）
+    #[derive(Debug, Deserialize)]
+    pub struct FetchWeatherArgs {
+        /// 都市名（日本語または英語）
+        pub city: String,
+        /// 緯度（resolve_city の候補から選んだ地点）
+        #[serde(default)]
+        pub lat: Option<f64>,
+        /// 経度（resolve_city の候補から選んだ地点）
+        #[serde(default)]
+        pub lon: Option<f64>,
+    }
+}
+
+tool_args! {
+    /// resolve_city ツール用引数
+    #[derive(Debug, Deserialize)]
+    pub struct ResolveCityArgs {
+        /// 調べる都市名
+        pub city: String,
+    }
+}
+
+tool_args! {
+    /// save_weather_memo ツール用引数
+    #[derive(Debug, Deserialize)]
+    pub struct SaveMemoArgs {
+        /// 都市名
+        pub city: String,
+        /// 天気の説明
+        pub weather: String,
+        /// 気温（摂氏）
+        pub temperature: f64,
+        /// ユーザーのメモ
+        pub note: String,
+        /// 緯度（fetch_weather の結果の地点。地図に描くときに使う）
+        #[serde(default)]
+        pub lat: Option<f64>,
+        /// 経度（fetch_weather の結果の地点）
+        #[serde(default)]
+        pub lon: Option<f64>,
+    }
+}
+
+tool_args! {
+    /// update_weather_memo ツール用引数（指定した項目だけ更新）
+    #[derive(Debug, Deserialize)]
+    pub struct UpdateMemoArgs {
+        /// 修正するメモの ID（list_memos / search_memos で確認）
+        pub id: u32,
+        /// 新しい都市名
+        #[serde(default)]
+        pub city: Option<String>,
+        /// 新しい天気の説明
+        #[serde(default)]
+        pub weather: Option<String>,
+        /// 新しい気温（摂氏）
+        #[serde(default)]
+        pub temperature: Option<f64>,
+        /// 新しいメモ
+        #[serde(default)]
+        pub note: Option<String>,
+    }
+}
+
+tool_args! {
+    /// delete_weather_memo ツール用引数
+    #[derive(Debug, Deserialize)]
+    pub struct DeleteMemoArgs {
+        /// 削除するメモの ID（list_memos / search_memos で確認）
+        pub id: u32,
+    }
+}
+
+tool_args! {
+    /// search_memos ツール用引数（指定した条件をすべて満たすメモを探す）
+    #[derive(Debug, Deserialize)]
+    pub struct SearchMemosArgs {
+        /// 都市名（部分一致）
+        #[serde(default)]
+        pub city: Option<String>,
+        /// 開始日 YYYY-MM-DD（この日を含む）
+        #[serde(default)]
+        pub from: Option<String>,
+        /// 終了日 YYYY-MM-DD（この日を含む）
+        #[serde(default)]
+        pub to: Option<String>,
+        /// 気温の下限（摂氏、含む）
+        #[serde(default)]
+        pub min_temp: Option<f64>,
+        /// 気温の上限（摂氏、含む）
+        #[serde(default)]
+        pub max_temp: Option<f64>,
+        /// 天気のキーワード（例: ["雨", "雪"]、いずれかを含むもの）
+        #[serde(default)]
+        pub weather: Vec<String>,
+        /// メモ本文に含まれる文字列
+        #[serde(default)]
+        pub note: Option<String>,
+    }
+}
+
+impl TryFrom<SearchMemosArgs> for MemoFilter {
+    type Error = String;
+
+    fn try_from(args: SearchMemosArgs) -> Result<Self, Self::Error> {
+        Ok(MemoFilter {
+            city: args.city,
+            from: args.from.as_deref().map(parse_date).transpose()?,
+            to: args.to.as_deref().map(parse_date).transpose()?,
+            min_temp: args.min_temp,
+            max_temp: args.max_temp,
+            weather_keywords: args.weather,
+            note: args.note,
+        })
+    }
+}
+
+tool_args! {
+    /// weather_stats ツール用引数（日付は "YYYY-MM-DD"）
+    #[derive(Debug, Deserialize)]
+    pub struct WeatherStatsArgs {
+        /// 集計する都市名（省略時は全都市）
+        #[serde(default)]
+        pub city: Option<String>,
+        /// 開始日 YYYY-MM-DD（省略可）
+        #[serde(default)]
+        pub from: Option<String>,
+        /// 終了日 YYYY-MM-DD（省略可）
+        #[serde(default)]
+        pub to: Option<String>,
+    }
+}
+
+tool_args! {
+    /// create_alert ツール用引数
+    /// 数値のしきい値なら metric / op / value、天気なら weather を指定する
+    #[derive(Debug, Deserialize)]
+    pub struct CreateAlertArgs {
+        /// 対象の都市名
+        pub city: String,
+        /// 監視する数値（気温 / 湿度）
+        #[serde(default)]
+        pub metric: Option<AlertMetric>,
+        /// しきい値より大きい（>）か小さい（<）か
+        #[serde(default)]
+        pub op: Option<Comparison>,
+        /// しきい値（気温は摂氏、湿度は %）
+        #[serde(default)]
+        pub value: Option<f64>,
+        /// 天気のキーワード（例: rain, 雨, snow）
+        #[serde(default)]
+        pub weather: Option<String>,
+        /// 現在の天気で判定するか、明日の予報で判定するか（省略時は now）
+        #[serde(default)]
+        pub when: Option<AlertWhen>,
+    }
+}
+
+impl TryFrom<CreateAlertArgs> for AlertRule {
+    type Error = String;
+
+    fn try_from(args: CreateAlertArgs) -> Result<Self, Self::Error> {
+        let condition = match (args.metric, args.op, args.value, args.weather) {
+            (Some(metric), Some(op), Some(value), None) => {
+                AlertCondition::Threshold { metric, op, value }
+            }
+            (None, None, None, Some(keyword)) => AlertCondition::Weather { keyword },
+            _ => {
+                return Err(
+                    "metric / op / value の 3 つ、または weather のどちらか一方を指定してください"
+                        .to_string(),
+                );
+            }
+        };
+
+        Ok(AlertRule {
+            id: 0,
+            city: args.city,
+            condition,
+            when: args.when.unwrap_or(AlertWhen::Now),
+        })
+    }
+}
+
+tool_args! {
+    /// delete_alert ツール用引数
+    #[derive(Debug, Deserialize)]
+    pub struct DeleteAlertArgs {
+        /// 削除するアラートの ID（list_alerts で確認）
+        pub id: u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_storage() -> MemoStorage {
+        let mut storage = MemoStorage::new();
+        storage.add_memo(weather_memo(
+            "2025-10-01 09:00:00",
+            "Tokyo",
+            "晴れ",
+            22.0,
+            "散歩日和",
+        ));
+        storage.add_memo(weather_memo(
+            "2025-10-03 12:00:00",
+            "大阪",
+            "小雨",
+            19.5,
+            "出張",
+        ));
+        storage.add_memo(weather_memo(
+            "2025-10-05 18:00:00",
+            "東京",
+            "雨",
+            17.0,
+            "傘を忘れた",
+        ));
+        storage.add_memo(weather_memo(
+            "2025-10-07 08:00:00",
+            "東京",
+            "薄い雲",
+            25.0,
+            "出張前",
+        ));
+        storage
+    }
+
+    fn weather_memo(
+        timestamp: &str,
+        city: &str,
+        weather: &str,
+        temperature: f64,
+        note: &str,
+    ) -> SavedMemo {
+        SavedMemo {
+            id: 0,
+            timestamp: timestamp.to_string(),
+            city: city.to_string(),
+            weather: weather.to_string(),
+            temperature,
+            note: note.to_string(),
+            lat: None,
+            lon: None,
+        }
+    }
+
+    fn search_ids(storage: &MemoStorage, filter: &MemoFilter) -> Vec<u32> {
+        storage.search(filter).iter().map(|m| m.id).collect()
+    }
+
+    fn memo(id: u32, timestamp: &str, city: &str) -> SavedMemo {
+        SavedMemo {
+            id,
+            timestamp: timestamp.to_string(),
+            city: city.to_string(),
+            weather: "晴れ".to_string(),
+            temperature: 20.0,
+            note: String::new(),
+            lat: None,
+            lon: None,
+        }
+    }
+
+    #[test]
+    fn test_add_memo_assigns_ids() {
+        let mut storage = MemoStorage::new();
+        let first = storage.add_memo(memo(0, "2025-10-05 10:00:00", "東京"));
+        let second = storage.add_memo(memo(0, "2025-10-05 11:00:00", "大阪"));
+
+        assert_eq!(first, 1);
+        assert_eq!(second, 2);
+        // 新しい順
+        assert_eq!(storage.memos[0].id, 2);
+        assert_eq!(storage.memos[1].id, 1);
+    }
+
+    #[test]
+    fn test_ids_are_not_reused_after_delete() {
+        let mut storage = MemoStorage::new();
+        storage.add_memo(memo(0, "2025-10-05 10:00:00", "東京"));
+        let id = storage.add_memo(memo(0, "2025-10-05 11:00:00", "大阪"));
+        assert!(storage.delete_memo(id));
+
+        let new_id = storage.add_memo(memo(0, "2025-10-05 12:00:00", "札幌"));
+        assert_eq!(new_id, 3);
+    }
+
+    #[test]
+    fn test_import_memos_skips_duplicates_and_sorts() {
+        let mut storage = MemoStorage::new();
+        storage.add_memo(memo(0, "2025-10-05 10:00:00", "東京"));
+
+        let (added, skipped) = storage.import_memos(vec![
+            memo(7, "2025-10-06 09:00:00", "大阪"),
+            memo(8, "2025-10-05 10:00:00", "東京"), // 既存と同じ
+            memo(9, "2025-10-04 09:00:00", "札幌"),
+        ]);
+
+        assert_eq!((added, skipped), (2, 1));
+        // 取り込んだ ID は使わず、古い順に振り直す
+        let ids: Vec<(u32, &str)> = storage
+            .memos
+            .iter()
+            .map(|m| (m.id, m.city.as_str()))
+            .collect();
+        assert_eq!(ids, vec![(3, "大阪"), (1, "東京"), (2, "札幌")]);
+    }
+
+    #[test]
+    fn test_memo_without_coords_omits_lat_lon() {
+        let json = serde_json::to_string(&memo(1, "2025-10-05 10:00:00", "東京")).unwrap();
+        assert!(!json.contains("lat"));
+    }
+
+    #[test]
+    fn test_migrate_legacy_json_without_ids() {
+        let json = r#"{
+            "memos": [
+                {"timestamp": "2025-10-05 11:47:15", "city": "Tokyo", "weather": "雲", "temperature": 26.0, "note": "散歩日和"},
+                {"timestamp": "2025-10-05 11:46:55", "city": "大阪", "weather": "小雨", "temperature": 27.0, "note": "明日の出張準備"}
+            ]
+        }"#;
+        let mut storage: MemoStorage = serde_json::from_str(json).unwrap();
+
+        assert!(storage.assign_missing_ids());
+        // 古いメモほど小さい ID
+        assert_eq!(storage.memos[0].id, 2);
+        assert_eq!(storage.memos[1].id, 1);
+        assert_eq!(storage.next_id, 3);
+
+        // 2 回目は何もしない
+        assert!(!storage.assign_missing_ids());
+    }
+
+    #[test]
+    fn test_update_memo_partial() {
+        let mut storage = MemoStorage::new();
+        let id = storage.add_memo(memo(0, "2025-10-05 10:00:00", "東京"));
+
+        let updated = storage.update_memo(
+            id,
+            MemoUpdate {
+                temperature: Some(18.5),
+                note: Some("肌寒い".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert!(updated);
+        let memo = storage.find_memo(id).unwrap();
+        assert_eq!(memo.city, "東京");
+        assert_eq!(memo.temperature, 18.5);
+        assert_eq!(memo.note, "肌寒い");
+    }
+
+    #[test]
+    fn test_update_and_delete_not_found() {
+        let mut storage = MemoStorage::new();
+        storage.add_memo(memo(0, "2025-10-05 10:00:00", "東京"));
+
+        assert!(!storage.update_memo(999, MemoUpdate::default()));
+        assert!(!storage.delete_memo(999));
+        assert_eq!(storage.memos.len(), 1);
+    }
+
+    #[test]
+    fn test_search_without_filter_returns_all() {
+        let storage = sample_storage();
+        assert_eq!(
+            search_ids(&storage, &MemoFilter::default()),
+            vec![4, 3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_search_city_filter_ignores_case() {
+        let storage = sample_storage();
+        let filter = MemoFilter {
+            city: Some("tokyo".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search_ids(&storage, &filter), vec![1]);
+    }
+
+    #[test]
+    fn test_search_by_date_range() {
+        let storage = sample_storage();
+        let filter = MemoFilter {
+            from: Some(parse_date("2025-10-03").unwrap()),
+            to: Some(parse_date("2025-10-05").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(search_ids(&storage, &filter), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_search_by_date_skips_unreadable_timestamp() {
+        let mut storage = sample_storage();
+        storage.add_memo(weather_memo("不明", "東京", "晴れ", 20.0, ""));
+        let filter = MemoFilter {
+            from: Some(parse_date("2025-10-01").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(search_ids(&storage, &filter), vec![4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_search_by_temperature_range() {
+        let storage = sample_storage();
+        let filter = MemoFilter {
+            min_temp: Some(19.5),
+            max_temp: Some(22.0),
+            ..Default::default()
+        };
+        assert_eq!(search_ids(&storage, &filter), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_search_by_weather_keywords_any() {
+        let storage = sample_storage();
+        let filter = MemoFilter {
+            weather_keywords: vec!["雨".to_string(), "雪".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(search_ids(&storage, &filter), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_search_by_note_text() {
+        let storage = sample_storage();
+        let filter = MemoFilter {
+            note: Some("出張".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search_ids(&storage, &filter), vec![4, 2]);
+    }
+
+    #[test]
+    fn test_search_combined_filters() {
+        let storage = sample_storage();
+        let filter = MemoFilter {
+            city: Some("東京".to_string()),
+            note: Some("出張".to_string()),
+            min_temp: Some(20.0),
+            ..Default::default()
+        };
+        assert_eq!(search_ids(&storage, &filter), vec![4]);
+    }
+
+    #[test]
+    fn test_filter_from_search_args() {
+        let args: SearchMemosArgs = serde_json::from_str(
+            r#"{"city": "東京", "from": "2025-10-01", "weather": ["雨"], "max_temp": 20}"#,
+        )
+        .unwrap();
+
+        let filter = MemoFilter::try_from(args).unwrap();
+        assert_eq!(filter.city.as_deref(), Some("東京"));
+        assert_eq!(filter.from, Some(parse_date("2025-10-01").unwrap()));
+        assert_eq!(filter.to, None);
+        assert_eq!(filter.max_temp, Some(20.0));
+        assert_eq!(filter.weather_keywords, vec!["雨".to_string()]);
+    }
+
+    #[test]
+    fn test_filter_from_search_args_invalid_date() {
+        let args: SearchMemosArgs = serde_json::from_str(r#"{"from": "10/01"}"#).unwrap();
+        assert!(MemoFilter::try_from(args).is_err());
+    }
+
+    #[test]
+    fn test_filter_describe() {
+        assert_eq!(MemoFilter::default().describe(), "条件なし");
+
+        let filter = MemoFilter {
+            city: Some("東京".to_string()),
+            min_temp: Some(20.0),
+            weather_keywords: vec!["雨".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(filter.describe(), "都市「東京」, 気温 20〜℃, 天気「雨」");
+    }
+
+    #[test]
+    fn test_alert_storage_add_and_delete() {
+        let mut storage = AlertStorage::new();
+        let rule = AlertRule {
+            id: 0,
+            city: "東京".to_string(),
+            condition: AlertCondition::Weather {
+                keyword: "rain".to_string(),
+            },
+            when: AlertWhen::Tomorrow,
+        };
+
+        let first = storage.add_rule(rule.clone());
+        let second = storage.add_rule(rule);
+        assert_eq!((first, second), (1, 2));
+
+        assert!(storage.delete_rule(first));
+        assert!(!storage.delete_rule(first));
+        assert_eq!(storage.add_rule(storage.rules[0].clone()), 3);
+    }
+
+    #[test]
+    fn test_alert_rule_json_format() {
+        let rule = AlertRule {
+            id: 1,
+            city: "東京".to_string(),
+            condition: AlertCondition::Threshold {
+                metric: AlertMetric::Temp,
+                op: Comparison::Above,
+                value: 30.0,
+            },
+            when: AlertWhen::Now,
+        };
+
+        let json = serde_json::to_value(&rule).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "id": 1,
+                "city": "東京",
+                "condition": {"type": "threshold", "metric": "temp", "op": ">", "value": 30.0},
+                "when": "now"
+            })
+        );
+        assert_eq!(rule.to_string(), "東京 の気温 > 30（現在）");
+    }
+
+    #[test]
+    fn test_alert_rule_from_create_args() {
+        let args: CreateAlertArgs =
+            serde_json::from_str(r#"{"city": "大阪", "weather": "rain", "when": "tomorrow"}"#)
+                .unwrap();
+        let rule = AlertRule::try_from(args).unwrap();
+        assert_eq!(
+            rule.condition,
+            AlertCondition::Weather {
+                keyword: "rain".to_string()
+            }
+        );
+        assert_eq!(rule.when, AlertWhen::Tomorrow);
+
+        // 数値条件と天気条件の混在・不足はエラー
+        let args: CreateAlertArgs =
+            serde_json::from_str(r#"{"city": "大阪", "metric": "temp", "weather": "rain"}"#)
+                .unwrap();
+        assert!(AlertRule::try_from(args).is_err());
+    }
 }
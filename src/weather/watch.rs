@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use tokio::time::{MissedTickBehavior, interval, sleep};
+
+use crate::weather::api::{
+    fetch_weather_by_coords, format_location, format_locations, geocode_city,
+};
+use crate::weather::storage::WeatherStore;
+use crate::weather::types::{GeoLocation, SavedMemo, WeatherInfo};
+
+/// 自動記録したメモに付けるメモ文
+const WATCH_NOTE: &str = "自動記録（weather watch）";
+
+/// 1 回の記録で API を試す最大回数
+const MAX_ATTEMPTS: u32 = 5;
+
+/// リトライ待ち時間の初期値と上限
+const BACKOFF_BASE: Duration = Duration::from_secs(5);
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// 記録 1 回分の結果
+#[derive(Debug, PartialEq)]
+pub enum RecordOutcome {
+    Saved,
+    Duplicate,
+}
+
+/// "1h", "30m", "45s", "1h30m" のような間隔指定を Duration に変換
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("間隔は 1h / 30m / 45s のように指定してください: {}", s);
+
+    let mut total = 0u64;
+    let mut digits = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let value: u64 = digits.parse().map_err(|_| invalid())?;
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        total = value
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        digits.clear();
+    }
+
+    // 単位なしの数字が残っている or 合計 0 は不正
+    if !digits.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// attempt 回目（0 始まり）の失敗後に待つ時間
+/// 指数的に伸ばすが、上限と記録間隔を超えない
+pub fn backoff_delay(attempt: u32, every: Duration) -> Duration {
+    let delay = BACKOFF_BASE.saturating_mul(2u32.saturating_pow(attempt));
+    delay.min(BACKOFF_MAX).min(every)
+}
+
+/// 直前の記録と同じ内容か（天気と気温が一致）
+pub fn is_same_reading(prev: &SavedMemo, info: &WeatherInfo) -> bool {
+    prev.city == info.city && prev.weather == info.weather && prev.temperature == info.temperature
+}
+
+/// 観測対象の地点を決める
+/// 座標指定があればそれを使い、なければジオコーディングで 1 地点に絞れた場合のみ採用
+pub async fn resolve_location(
+    city: &str,
+    coords: Option<(f64, f64)>,
+) -> Result<GeoLocation, String> {
+    if let Some((lat, lon)) = coords {
+        return Ok(GeoLocation {
+            name: city.to_string(),
+            country: String::new(),
+            state: None,
+            lat,
+            lon,
+        });
+    }
+
+    let mut candidates = geocode_city(city).await?;
+    match candidates.len() {
+        0 => Err(format!("「{}」に該当する地点が見つかりません", city)),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(format!(
+            "{}--lat / --lon で地点を指定してください",
+            format_locations(city, &candidates)
+        )),
+    }
+}
+
+/// 天気を 1 回取得し、直前と同じでなければメモに追記
+async fn record_once(location: &GeoLocation) -> Result<RecordOutcome, String> {
+    let mut info = fetch_weather_by_coords(location.lat, location.lon).await?;
+    info.city = location.name.clone();
+
+    let store = WeatherStore::default();
+    let storage = store.load_memos()?;
+    // なぜ: memos は新しい順なので最初に見つかったものが直前の記録
+    let prev = storage.memos.iter().find(|m| m.city == info.city);
+    if prev.is_some_and(|p| is_same_reading(p, &info)) {
+        return Ok(RecordOutcome::Duplicate);
+    }
+
+    store.add_and_save_memo(SavedMemo::from_weather(&info, WATCH_NOTE.to_string()))?;
+    Ok(RecordOutcome::Saved)
+}
+
+/// 失敗時はバックオフしながら最大 MAX_ATTEMPTS 回まで記録を試す
+async fn record_with_retry(
+    location: &GeoLocation,
+    every: Duration,
+) -> Result<RecordOutcome, String> {
+    let mut attempt = 0;
+    loop {
+        match record_once(location).await {
+            Ok(outcome) => return Ok(outcome),
+            Err(e) if attempt + 1 >= MAX_ATTEMPTS => return Err(e),
+            Err(e) => {
+                let delay = backoff_delay(attempt, every);
+                eprintln!(
+                    "⚠️ 取得失敗（{}回目）: {} → {}秒後に再試行",
+                    attempt + 1,
+                    e,
+                    delay.as_secs()
+                );
+                sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// 指定間隔で天気を記録し続ける（Ctrl-C で終了）
+pub async fn watch(location: GeoLocation, every: Duration) -> Result<(), String> {
+    println!(
+        "👀 {} を {} 秒ごとに記録します（Ctrl-C で終了）",
+        format_location(&location),
+        every.as_secs()
+    );
+
+    let mut ticker = interval(every);
+    // なぜ: スリープ復帰などで遅れた分をまとめて取りに行かない
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = ticker.tick() => {}
+        }
+
+        // NOTE: 保存は同期処理なので、ここで中断されても書きかけのファイルは残らない
+        tokio::select! {
+            _ = &mut shutdown => break,
+            result = record_with_retry(&location, every) => match result {
+                Ok(RecordOutcome::Saved) => println!("✅ 記録しました: {}", location.name),
+                Ok(RecordOutcome::Duplicate) => println!("⏭️ 前回と同じ内容のためスキップ"),
+                Err(e) => eprintln!("❌ 記録できませんでした（次回に再挑戦）: {}", e),
+            },
+        }
+    }
+
+    println!("\n👋 記録を終了しました");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn info(weather: &str, temperature: f64) -> WeatherInfo {
+        WeatherInfo {
+            city: "東京".to_string(),
+            weather: weather.to_string(),
+            temperature,
+            humidity: 50,
+            lat: 35.68,
+            lon: 139.76,
+            local_time: NaiveDateTime::parse_from_str("2025-10-05 12:00", "%Y-%m-%d %H:%M")
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_interval("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_interval("1h30m").unwrap(), Duration::from_secs(5400));
+    }
+
+    #[test]
+    fn test_parse_interval_invalid() {
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("10").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("5d").is_err());
+        assert!(parse_interval("0m").is_err());
+        // 桁あふれはパニックせずエラー
+        assert!(parse_interval("99999999999999999h").is_err());
+        assert!(parse_interval("5124095576030431h5124095576030431h").is_err());
+        assert!(parse_interval("99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_and_caps() {
+        let hour = Duration::from_secs(3600);
+        assert_eq!(backoff_delay(0, hour), Duration::from_secs(5));
+        assert_eq!(backoff_delay(1, hour), Duration::from_secs(10));
+        assert_eq!(backoff_delay(3, hour), Duration::from_secs(40));
+        assert_eq!(backoff_delay(20, hour), BACKOFF_MAX);
+
+        // 記録間隔より長くは待たない
+        assert_eq!(
+            backoff_delay(3, Duration::from_secs(30)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn test_is_same_reading() {
+        let prev = SavedMemo::from_weather(&info("晴れ", 20.0), WATCH_NOTE.to_string());
+
+        assert!(is_same_reading(&prev, &info("晴れ", 20.0)));
+        assert!(!is_same_reading(&prev, &info("晴れ", 20.5)));
+        assert!(!is_same_reading(&prev, &info("曇り", 20.0)));
+    }
+}